@@ -65,11 +65,11 @@ pub fn decrypt_note_for_owner(c: &mut Criterion) {
                     &ekp,
                 );
 
-                return (receiver_key.incoming_view_key().clone(), merkle_note);
+                return (receiver_key.view_key().clone(), merkle_note);
             },
             // Benchmark
-            |(ivk, merkle_note)| {
-                merkle_note.decrypt_note_for_owner(&ivk).unwrap();
+            |(view_key, merkle_note)| {
+                merkle_note.decrypt_note_for_owner(&view_key).unwrap();
             },
             BatchSize::SmallInput,
         );
@@ -4,6 +4,7 @@
 
 use std::fmt::Display;
 
+use ironfish::errors::{IronfishError, IronfishErrorPayload};
 use ironfish::keys::Language;
 use ironfish::serializing::bytes_to_hex;
 use ironfish::PublicAddress;
@@ -27,6 +28,31 @@ fn to_napi_err(err: impl Display) -> napi::Error {
     Error::from_reason(err.to_string())
 }
 
+/// Throw an [`IronfishError`] as a JS error whose `code` is the kind of the
+/// error. Balance errors also carry the `assetId`, `spendsAndMints`,
+/// `outputsAndBurns` and `intendedFee` of the asset that does not balance.
+fn to_napi_ironfish_err(env: Env, err: IronfishError) -> napi::Error {
+    match throw_ironfish_err(env, &err) {
+        // The error was thrown already, napi only needs to know there is one
+        Ok(()) => Error::new(Status::PendingException, err.to_string()),
+        Err(e) => e,
+    }
+}
+
+fn throw_ironfish_err(env: Env, err: &IronfishError) -> Result<()> {
+    let mut js_error = env.create_error(Error::from_reason(err.to_string()))?;
+    js_error.set_named_property("code", format!("{:?}", err.kind))?;
+
+    if let Some(IronfishErrorPayload::InvalidBalance(details)) = &err.payload {
+        js_error.set_named_property("assetId", bytes_to_hex(details.asset_id.as_bytes()))?;
+        js_error.set_named_property("spendsAndMints", BigInt::from(details.spends_and_mints))?;
+        js_error.set_named_property("outputsAndBurns", BigInt::from(details.outputs_and_burns))?;
+        js_error.set_named_property("intendedFee", BigInt::from(details.intended_fee))?;
+    }
+
+    env.throw(js_error)
+}
+
 // unfortunately napi doesn't support reexport of enums (bip39::Language) so we
 // have to recreate if we want type safety. hopefully in the future this will work with napi:
 // #[napi]
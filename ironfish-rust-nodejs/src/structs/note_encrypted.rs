@@ -12,6 +12,7 @@ use napi_derive::napi;
 use ironfish::merkle_note::NOTE_ENCRYPTION_KEY_SIZE;
 use ironfish::note::ENCRYPTED_NOTE_SIZE;
 use ironfish::serializing::aead::MAC_SIZE;
+use ironfish::transaction::TransactionVersion;
 use ironfish::MerkleNote;
 
 use crate::to_napi_err;
@@ -39,7 +40,9 @@ impl NativeNoteEncrypted {
     #[napi(constructor)]
     pub fn new(js_bytes: JsBuffer) -> Result<Self> {
         let bytes = js_bytes.into_value()?;
-        let note = MerkleNote::read(bytes.as_ref()).map_err(to_napi_err)?;
+        // Notes of V3 transactions are not exposed until the transaction
+        // format in TypeScript supports that version
+        let note = MerkleNote::read(bytes.as_ref(), TransactionVersion::V1).map_err(to_napi_err)?;
 
         Ok(NativeNoteEncrypted { note })
     }
@@ -104,14 +107,19 @@ impl NativeNoteEncrypted {
         let incoming_view_key =
             IncomingViewKey::from_hex(&incoming_hex_key).map_err(to_napi_err)?;
 
-        Ok(match self.note.decrypt_note_for_owner(&incoming_view_key) {
-            Ok(note) => {
-                let mut vec = vec![];
-                note.write(&mut vec).map_err(to_napi_err)?;
-                Some(Buffer::from(vec))
-            }
-            Err(_) => None,
-        })
+        Ok(
+            match self
+                .note
+                .decrypt_note_for_incoming_view_key(&incoming_view_key)
+            {
+                Ok(note) => {
+                    let mut vec = vec![];
+                    note.write(&mut vec).map_err(to_napi_err)?;
+                    Some(Buffer::from(vec))
+                }
+                Err(_) => None,
+            },
+        )
     }
 
     /// Returns undefined if the note was unable to be decrypted with the given key.
@@ -33,7 +33,7 @@ use napi::{
 };
 use napi_derive::napi;
 
-use crate::to_napi_err;
+use crate::{to_napi_err, to_napi_ironfish_err};
 
 use super::note::NativeNote;
 use super::spend_proof::NativeSpendDescription;
@@ -218,6 +218,7 @@ impl NativeTransaction {
     #[napi]
     pub fn mint(
         &mut self,
+        env: Env,
         asset: &NativeAsset,
         value: BigInt,
         transfer_ownership_to: Option<&str>,
@@ -227,12 +228,12 @@ impl NativeTransaction {
             None => self
                 .transaction
                 .add_mint(asset.asset, value_u64)
-                .map_err(to_napi_err)?,
+                .map_err(|e| to_napi_ironfish_err(env, e))?,
             Some(new_owner) => {
                 let new_owner = PublicAddress::from_hex(new_owner).map_err(to_napi_err)?;
                 self.transaction
                     .add_mint_with_new_owner(asset.asset, value_u64, new_owner)
-                    .map_err(to_napi_err)?;
+                    .map_err(|e| to_napi_ironfish_err(env, e))?;
             }
         }
 
@@ -241,14 +242,14 @@ impl NativeTransaction {
 
     /// Burn some supply of a given asset and value as part of this transaction.
     #[napi]
-    pub fn burn(&mut self, asset_id_js_bytes: JsBuffer, value: BigInt) -> Result<()> {
+    pub fn burn(&mut self, env: Env, asset_id_js_bytes: JsBuffer, value: BigInt) -> Result<()> {
         let asset_id_bytes = asset_id_js_bytes.into_value()?;
         let asset_id = AssetIdentifier::new(asset_id_bytes.as_ref().try_into().unwrap())
             .map_err(to_napi_err)?;
         let value_u64 = value.get_u64().1;
         self.transaction
             .add_burn(asset_id, value_u64)
-            .map_err(to_napi_err)?;
+            .map_err(|e| to_napi_ironfish_err(env, e))?;
 
         Ok(())
     }
@@ -299,6 +300,7 @@ impl NativeTransaction {
     #[napi]
     pub fn post(
         &mut self,
+        env: Env,
         spender_hex_key: String,
         change_goes_to: Option<String>,
         intended_transaction_fee: BigInt,
@@ -315,7 +317,7 @@ impl NativeTransaction {
         let posted_transaction = self
             .transaction
             .post(&spender_key, change_key, intended_transaction_fee_u64)
-            .map_err(to_napi_err)?;
+            .map_err(|e| to_napi_ironfish_err(env, e))?;
 
         let mut vec: Vec<u8> = vec![];
         posted_transaction.write(&mut vec).map_err(to_napi_err)?;
@@ -327,6 +329,7 @@ impl NativeTransaction {
     #[napi]
     pub fn build(
         &mut self,
+        env: Env,
         proof_authorizing_key_str: String,
         view_key_str: String,
         outgoing_view_key_str: String,
@@ -352,7 +355,7 @@ impl NativeTransaction {
                 intended_transaction_fee.get_i64().0,
                 change_address,
             )
-            .map_err(to_napi_err)?;
+            .map_err(|e| to_napi_ironfish_err(env, e))?;
 
         let mut vec: Vec<u8> = vec![];
         unsigned_transaction.write(&mut vec).map_err(to_napi_err)?;
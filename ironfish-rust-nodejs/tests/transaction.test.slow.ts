@@ -25,5 +25,28 @@ describe('Transaction', () => {
       expect(() => { proposedTx.post(key.spendingKey, null, 0n)}).not.toThrow()
 
     })
+
+    it('throws an error naming the asset that does not balance', () => {
+      const key = generateKey()
+      const asset = new Asset(key.publicAddress, 'testcoin', '')
+      const proposedTx = new Transaction(1)
+      proposedTx.mint(asset, 5n)
+      proposedTx.burn(asset.id(), 7n)
+
+      let error: unknown
+      try {
+        proposedTx.post(key.spendingKey, null, 0n)
+      } catch (e) {
+        error = e
+      }
+
+      expect(error).toMatchObject({
+        code: 'InvalidBalance',
+        assetId: asset.id().toString('hex'),
+        spendsAndMints: 5n,
+        outputsAndBurns: 7n,
+        intendedFee: 0n,
+      })
+    })
   })
 })
@@ -42,17 +42,26 @@ pub struct Asset {
 impl Asset {
     /// Create a new AssetType from a public address, name, chain, and network
     pub fn new(creator: PublicAddress, name: &str, metadata: &str) -> Result<Asset, IronfishError> {
+        Asset::new_with_metadata_bytes(creator, name, str_to_array(metadata))
+    }
+
+    /// Create a new asset from its name and the raw bytes of its metadata,
+    /// using the first nonce that gives a valid asset identifier
+    pub(crate) fn new_with_metadata_bytes(
+        creator: PublicAddress,
+        name: &str,
+        metadata: [u8; METADATA_LENGTH],
+    ) -> Result<Asset, IronfishError> {
         let trimmed_name = name.trim();
         if trimmed_name.is_empty() {
             return Err(IronfishError::new(IronfishErrorKind::InvalidData));
         }
 
         let name_bytes = str_to_array(trimmed_name);
-        let metadata_bytes = str_to_array(metadata);
 
         let mut nonce = 0u8;
         loop {
-            if let Ok(asset) = Asset::new_with_nonce(creator, name_bytes, metadata_bytes, nonce) {
+            if let Ok(asset) = Asset::new_with_nonce(creator, name_bytes, metadata, nonce) {
                 return Ok(asset);
             }
             nonce = nonce
@@ -0,0 +1,385 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Tracks the creator, owner and supply of every asset, following the rules
+//! enforced by the node when connecting transactions:
+//!
+//! - The first mint of an asset must be made by its creator, and every
+//!   following mint by its current owner. V2 mints may then transfer the
+//!   ownership of the asset to another address.
+//! - Only assets that were minted can be burned, and no more than their
+//!   circulating supply.
+//! - Mints of a transaction are applied before its burns.
+//!
+//! Transactions must be applied in block order, and can be reverted in the
+//! reverse order to handle reorgs.
+
+use std::collections::HashMap;
+
+use super::asset_identifier::{AssetIdentifier, NATIVE_ASSET};
+use crate::{
+    errors::{IronfishError, IronfishErrorKind},
+    keys::PublicAddress,
+    transaction::Transaction,
+};
+
+/// Ownership and supply of an asset
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AssetRecord {
+    /// Address of the account that created the asset
+    pub creator: PublicAddress,
+
+    /// Address of the account currently allowed to mint the asset
+    pub owner: PublicAddress,
+
+    /// Total value minted since the asset was created
+    pub minted: u64,
+
+    /// Total value burned since the asset was created
+    pub burned: u64,
+}
+
+impl AssetRecord {
+    /// Value of the asset currently in circulation
+    pub fn supply(&self) -> u64 {
+        self.minted - self.burned
+    }
+}
+
+/// Read access to the state of assets, implemented by [`AssetLedger`] and by
+/// any other store of [`AssetRecord`]s, such as the database of a node
+pub trait AssetLedgerView {
+    /// Get the ownership and supply of an asset, if it was ever minted
+    fn asset_record(&self, asset_id: &AssetIdentifier) -> Option<AssetRecord>;
+}
+
+/// Changes made by an applied transaction, used to revert it
+struct JournalEntry {
+    transaction_hash: [u8; 32],
+
+    /// State of every asset touched by the transaction before it was applied
+    previous: Vec<(AssetIdentifier, Option<AssetRecord>)>,
+}
+
+/// State of all the assets on a chain, built by applying the transactions of
+/// the chain in order
+#[derive(Default)]
+pub struct AssetLedger {
+    assets: HashMap<AssetIdentifier, AssetRecord>,
+    journal: Vec<JournalEntry>,
+}
+
+impl AssetLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the ownership and supply of an asset, if it was ever minted
+    pub fn get(&self, asset_id: &AssetIdentifier) -> Option<&AssetRecord> {
+        self.assets.get(asset_id)
+    }
+
+    /// Iterate over all the assets that were ever minted
+    pub fn iter(&self) -> impl Iterator<Item = (&AssetIdentifier, &AssetRecord)> {
+        self.assets.iter()
+    }
+
+    /// Apply the mints and burns of `transaction` on top of the current state.
+    /// The transaction is either fully applied, or not at all if it breaks
+    /// one of the rules of the ledger.
+    pub fn apply_transaction(&mut self, transaction: &Transaction) -> Result<(), IronfishError> {
+        let transaction_hash = transaction.transaction_signature_hash()?;
+        let mut previous = vec![];
+
+        if let Err(e) = self.apply_descriptions(transaction, &mut previous) {
+            self.restore(previous);
+            return Err(e);
+        }
+
+        self.journal.push(JournalEntry {
+            transaction_hash,
+            previous,
+        });
+
+        Ok(())
+    }
+
+    /// Revert the last applied transaction, which must be `transaction`.
+    pub fn revert_transaction(&mut self, transaction: &Transaction) -> Result<(), IronfishError> {
+        let transaction_hash = transaction.transaction_signature_hash()?;
+
+        match self.journal.last() {
+            Some(entry) if entry.transaction_hash == transaction_hash => {}
+            _ => return Err(IronfishError::new(IronfishErrorKind::InvalidTransaction)),
+        }
+
+        let entry = self.journal.pop().unwrap();
+        self.restore(entry.previous);
+
+        Ok(())
+    }
+
+    /// Only keep what is needed to revert the last `depth` transactions, for
+    /// callers that never reorg deeper than that.
+    pub fn prune_history(&mut self, depth: usize) {
+        let excess = self.journal.len().saturating_sub(depth);
+        self.journal.drain(..excess);
+    }
+
+    fn apply_descriptions(
+        &mut self,
+        transaction: &Transaction,
+        previous: &mut Vec<(AssetIdentifier, Option<AssetRecord>)>,
+    ) -> Result<(), IronfishError> {
+        for mint in transaction.mints() {
+            let asset_id = *mint.asset.id();
+            self.save_previous(&asset_id, previous);
+
+            let record = self.assets.entry(asset_id).or_insert(AssetRecord {
+                creator: mint.asset.creator,
+                owner: mint.asset.creator,
+                minted: 0,
+                burned: 0,
+            });
+
+            if mint.owner != record.owner {
+                return Err(IronfishError::new(IronfishErrorKind::InvalidMintOwner));
+            }
+
+            record.minted = record
+                .minted
+                .checked_add(mint.value)
+                .ok_or_else(|| IronfishError::new(IronfishErrorKind::IllegalValue))?;
+
+            if let Some(new_owner) = mint.transfer_ownership_to {
+                record.owner = new_owner;
+            }
+        }
+
+        for burn in transaction.burns() {
+            if burn.asset_id == NATIVE_ASSET {
+                return Err(IronfishError::new(
+                    IronfishErrorKind::InvalidAssetIdentifier,
+                ));
+            }
+
+            self.save_previous(&burn.asset_id, previous);
+
+            let record = self
+                .assets
+                .get_mut(&burn.asset_id)
+                .ok_or_else(|| IronfishError::new(IronfishErrorKind::InvalidAssetIdentifier))?;
+
+            if burn.value > record.supply() {
+                return Err(IronfishError::new(IronfishErrorKind::InvalidBalance));
+            }
+            record.burned += burn.value;
+        }
+
+        Ok(())
+    }
+
+    /// Remember the state of an asset before it is first changed by a
+    /// transaction
+    fn save_previous(
+        &self,
+        asset_id: &AssetIdentifier,
+        previous: &mut Vec<(AssetIdentifier, Option<AssetRecord>)>,
+    ) {
+        if !previous.iter().any(|(id, _)| id == asset_id) {
+            previous.push((*asset_id, self.assets.get(asset_id).copied()));
+        }
+    }
+
+    fn restore(&mut self, previous: Vec<(AssetIdentifier, Option<AssetRecord>)>) {
+        for (asset_id, record) in previous {
+            match record {
+                Some(record) => self.assets.insert(asset_id, record),
+                None => self.assets.remove(&asset_id),
+            };
+        }
+    }
+}
+
+impl AssetLedgerView for AssetLedger {
+    fn asset_record(&self, asset_id: &AssetIdentifier) -> Option<AssetRecord> {
+        self.get(asset_id).copied()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::AssetLedger;
+    use crate::{
+        assets::{asset::Asset, asset_identifier::NATIVE_ASSET},
+        errors::IronfishErrorKind,
+        keys::{PublicAddress, SaplingKey},
+        note::Note,
+        test_util::make_fake_witness,
+        transaction::{ProposedTransaction, Transaction, TransactionVersion},
+    };
+
+    fn mint(
+        key: &SaplingKey,
+        asset: Asset,
+        value: u64,
+        new_owner: Option<PublicAddress>,
+    ) -> Transaction {
+        let mut transaction = ProposedTransaction::new(TransactionVersion::latest());
+        match new_owner {
+            Some(new_owner) => transaction
+                .add_mint_with_new_owner(asset, value, new_owner)
+                .unwrap(),
+            None => transaction.add_mint(asset, value).unwrap(),
+        }
+        transaction.post(key, None, 0).unwrap()
+    }
+
+    fn burn(key: &SaplingKey, asset: Asset, value: u64) -> Transaction {
+        let note = Note::new(
+            key.public_address(),
+            value,
+            "",
+            *asset.id(),
+            key.public_address(),
+        );
+        let witness = make_fake_witness(&note);
+
+        let mut transaction = ProposedTransaction::new(TransactionVersion::latest());
+        transaction.add_spend(note, &witness).unwrap();
+        transaction.add_burn(*asset.id(), value).unwrap();
+        transaction.post(key, None, 0).unwrap()
+    }
+
+    #[test]
+    fn test_mint_and_burn() {
+        let creator_key = SaplingKey::generate_key();
+        let creator = creator_key.public_address();
+        let asset = Asset::new(creator, "Testcoin", "").unwrap();
+
+        let mut ledger = AssetLedger::new();
+        assert!(ledger.get(asset.id()).is_none());
+
+        let first_mint = mint(&creator_key, asset, 10, None);
+        ledger.apply_transaction(&first_mint).unwrap();
+        ledger
+            .apply_transaction(&mint(&creator_key, asset, 5, None))
+            .unwrap();
+        let burn_transaction = burn(&creator_key, asset, 12);
+        ledger.apply_transaction(&burn_transaction).unwrap();
+
+        let record = ledger.get(asset.id()).unwrap();
+        assert_eq!(record.creator, creator);
+        assert_eq!(record.owner, creator);
+        assert_eq!(record.minted, 15);
+        assert_eq!(record.burned, 12);
+        assert_eq!(record.supply(), 3);
+
+        // Cannot burn more than the supply
+        assert!(matches!(
+            ledger.apply_transaction(&burn(&creator_key, asset, 4)),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidBalance)
+        ));
+        assert_eq!(ledger.get(asset.id()).unwrap().burned, 12);
+
+        // Cannot burn assets that were never minted
+        let other_asset = Asset::new(creator, "Othercoin", "").unwrap();
+        assert!(matches!(
+            ledger.apply_transaction(&burn(&creator_key, other_asset, 1)),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidAssetIdentifier)
+        ));
+
+        // Cannot burn the native asset
+        let native = Note::new(creator, 1, "", NATIVE_ASSET, creator);
+        let witness = make_fake_witness(&native);
+        let mut transaction = ProposedTransaction::new(TransactionVersion::latest());
+        transaction.add_spend(native, &witness).unwrap();
+        transaction.add_burn(NATIVE_ASSET, 1).unwrap();
+        let transaction = transaction.post(&creator_key, None, 0).unwrap();
+        assert!(matches!(
+            ledger.apply_transaction(&transaction),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidAssetIdentifier)
+        ));
+    }
+
+    #[test]
+    fn test_ownership_transfer() {
+        let creator_key = SaplingKey::generate_key();
+        let owner_key = SaplingKey::generate_key();
+        let asset = Asset::new(creator_key.public_address(), "Testcoin", "").unwrap();
+
+        let mut ledger = AssetLedger::new();
+
+        // The first mint must be made by the creator
+        assert!(matches!(
+            ledger.apply_transaction(&mint(&owner_key, asset, 10, None)),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidMintOwner)
+        ));
+        assert!(ledger.get(asset.id()).is_none());
+
+        ledger
+            .apply_transaction(&mint(
+                &creator_key,
+                asset,
+                10,
+                Some(owner_key.public_address()),
+            ))
+            .unwrap();
+        let record = ledger.get(asset.id()).unwrap();
+        assert_eq!(record.creator, creator_key.public_address());
+        assert_eq!(record.owner, owner_key.public_address());
+
+        // The creator cannot mint anymore
+        assert!(matches!(
+            ledger.apply_transaction(&mint(&creator_key, asset, 1, None)),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidMintOwner)
+        ));
+
+        ledger
+            .apply_transaction(&mint(&owner_key, asset, 5, None))
+            .unwrap();
+        assert_eq!(ledger.get(asset.id()).unwrap().minted, 15);
+    }
+
+    #[test]
+    fn test_revert() {
+        let creator_key = SaplingKey::generate_key();
+        let owner_key = SaplingKey::generate_key();
+        let asset = Asset::new(creator_key.public_address(), "Testcoin", "").unwrap();
+
+        let mut ledger = AssetLedger::new();
+
+        let first_mint = mint(&creator_key, asset, 10, None);
+        let transfer = mint(&creator_key, asset, 5, Some(owner_key.public_address()));
+        let burn_transaction = burn(&creator_key, asset, 3);
+        ledger.apply_transaction(&first_mint).unwrap();
+        ledger.apply_transaction(&transfer).unwrap();
+        ledger.apply_transaction(&burn_transaction).unwrap();
+
+        // Transactions must be reverted in reverse order
+        assert!(matches!(
+            ledger.revert_transaction(&transfer),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidTransaction)
+        ));
+
+        ledger.revert_transaction(&burn_transaction).unwrap();
+        assert_eq!(ledger.get(asset.id()).unwrap().burned, 0);
+
+        ledger.revert_transaction(&transfer).unwrap();
+        let record = ledger.get(asset.id()).unwrap();
+        assert_eq!(record.owner, creator_key.public_address());
+        assert_eq!(record.minted, 10);
+
+        ledger.revert_transaction(&first_mint).unwrap();
+        assert!(ledger.get(asset.id()).is_none());
+
+        // Pruned transactions cannot be reverted
+        ledger.apply_transaction(&first_mint).unwrap();
+        ledger.apply_transaction(&transfer).unwrap();
+        ledger.prune_history(1);
+        ledger.revert_transaction(&transfer).unwrap();
+        assert!(ledger.revert_transaction(&first_mint).is_err());
+        assert_eq!(ledger.get(asset.id()).unwrap().minted, 10);
+    }
+}
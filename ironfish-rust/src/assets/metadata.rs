@@ -0,0 +1,368 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Structured asset metadata, stored in the [`METADATA_LENGTH`] bytes of the
+//! metadata of an [`Asset`] so that wallets can display custom assets
+//! consistently.
+//!
+//! Structured metadata starts with [`STRUCTURED_METADATA_TAG`], which can
+//! never start a UTF-8 string, so it cannot be confused with the free-form
+//! metadata of existing assets. It is followed by the schema version and by a
+//! list of fields, each made of a type byte, a length byte and the value of
+//! the field. The rest of the metadata is zero padding.
+
+use super::asset::{Asset, METADATA_LENGTH, NAME_LENGTH};
+use crate::{
+    errors::{IronfishError, IronfishErrorKind},
+    PublicAddress,
+};
+
+/// First byte of structured metadata
+pub const STRUCTURED_METADATA_TAG: u8 = 0xF5;
+
+/// Version of the schema of the structured metadata
+pub const ASSET_METADATA_VERSION: u8 = 1;
+
+/// Maximum number of decimals of an asset. Values are u64, so more decimals
+/// would not leave room for a single whole unit.
+pub const MAX_DECIMALS: u8 = 18;
+
+/// Maximum length of a ticker symbol
+pub const MAX_TICKER_LENGTH: usize = 12;
+
+const FIELD_DECIMALS: u8 = 1;
+const FIELD_TICKER: u8 = 2;
+const FIELD_URL: u8 = 3;
+const FIELD_CONTENT_HASH: u8 = 4;
+const FIELD_MAX_SUPPLY: u8 = 5;
+const FIELD_FIXED_SUPPLY: u8 = 6;
+
+/// Typed metadata of an asset. All the fields are optional.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AssetMetadata {
+    /// Number of decimals used to display values of the asset
+    pub decimals: Option<u8>,
+
+    /// Ticker symbol, made of ASCII letters and digits
+    pub ticker: Option<String>,
+
+    /// URL of a description of the asset
+    pub url: Option<String>,
+
+    /// Hash of off-chain content describing the asset
+    pub content_hash: Option<[u8; 32]>,
+
+    /// Maximum total value that can ever be minted. See
+    /// [`super::policy::validate_mint_policy`]
+    pub max_supply: Option<u64>,
+
+    /// Whether the supply is fixed by the first mint, so that later mints
+    /// cannot increase it. See [`super::policy::validate_mint_policy`]
+    pub fixed_supply: bool,
+}
+
+impl AssetMetadata {
+    /// Encode the metadata. Fails if a field is invalid or if the fields do
+    /// not fit in [`METADATA_LENGTH`] bytes.
+    pub fn encode(&self) -> Result<[u8; METADATA_LENGTH], IronfishError> {
+        let max_supply = self.max_supply.map(u64::to_le_bytes);
+        let mut fields: Vec<(u8, &[u8])> = vec![];
+
+        if let Some(decimals) = &self.decimals {
+            if *decimals > MAX_DECIMALS {
+                return Err(invalid_metadata());
+            }
+            fields.push((FIELD_DECIMALS, std::slice::from_ref(decimals)));
+        }
+
+        if let Some(ticker) = &self.ticker {
+            if !is_valid_ticker(ticker) {
+                return Err(invalid_metadata());
+            }
+            fields.push((FIELD_TICKER, ticker.as_bytes()));
+        }
+
+        if let Some(url) = &self.url {
+            if !is_valid_url(url) {
+                return Err(invalid_metadata());
+            }
+            fields.push((FIELD_URL, url.as_bytes()));
+        }
+
+        if let Some(content_hash) = &self.content_hash {
+            fields.push((FIELD_CONTENT_HASH, content_hash));
+        }
+
+        if let Some(max_supply) = &max_supply {
+            fields.push((FIELD_MAX_SUPPLY, max_supply));
+        }
+
+        // The flag is set by the presence of the field, which has no value
+        if self.fixed_supply {
+            fields.push((FIELD_FIXED_SUPPLY, &[]));
+        }
+
+        let mut metadata = [0; METADATA_LENGTH];
+        metadata[0] = STRUCTURED_METADATA_TAG;
+        metadata[1] = ASSET_METADATA_VERSION;
+
+        let mut offset = 2;
+        for (field_type, value) in fields {
+            let end = offset + 2 + value.len();
+            // Lengths are stored in a byte, but no field can be that long
+            // without also overflowing the metadata
+            if end > METADATA_LENGTH {
+                return Err(invalid_metadata());
+            }
+
+            metadata[offset] = field_type;
+            metadata[offset + 1] = value.len() as u8;
+            metadata[offset + 2..end].copy_from_slice(value);
+            offset = end;
+        }
+
+        Ok(metadata)
+    }
+
+    /// Decode structured metadata. Returns `None` for free-form metadata, and
+    /// fails if the metadata is tagged as structured but is malformed.
+    pub fn decode(metadata: &[u8; METADATA_LENGTH]) -> Result<Option<Self>, IronfishError> {
+        if metadata[0] != STRUCTURED_METADATA_TAG {
+            return Ok(None);
+        }
+        if metadata[1] != ASSET_METADATA_VERSION {
+            return Err(invalid_metadata());
+        }
+
+        let mut result = AssetMetadata::default();
+        let mut offset = 2;
+        while offset < METADATA_LENGTH && metadata[offset] != 0 {
+            let field_type = metadata[offset];
+            let length = *metadata.get(offset + 1).ok_or_else(invalid_metadata)? as usize;
+            let value = metadata
+                .get(offset + 2..offset + 2 + length)
+                .ok_or_else(invalid_metadata)?;
+            offset += 2 + length;
+
+            let duplicate = match field_type {
+                FIELD_DECIMALS => {
+                    if length != 1 || value[0] > MAX_DECIMALS {
+                        return Err(invalid_metadata());
+                    }
+                    result.decimals.replace(value[0]).is_some()
+                }
+                FIELD_TICKER => {
+                    let ticker =
+                        String::from_utf8(value.to_vec()).map_err(|_| invalid_metadata())?;
+                    if !is_valid_ticker(&ticker) {
+                        return Err(invalid_metadata());
+                    }
+                    result.ticker.replace(ticker).is_some()
+                }
+                FIELD_URL => {
+                    let url = String::from_utf8(value.to_vec()).map_err(|_| invalid_metadata())?;
+                    if !is_valid_url(&url) {
+                        return Err(invalid_metadata());
+                    }
+                    result.url.replace(url).is_some()
+                }
+                FIELD_CONTENT_HASH => {
+                    let content_hash = value.try_into().map_err(|_| invalid_metadata())?;
+                    result.content_hash.replace(content_hash).is_some()
+                }
+                FIELD_MAX_SUPPLY => {
+                    let max_supply = value.try_into().map_err(|_| invalid_metadata())?;
+                    result
+                        .max_supply
+                        .replace(u64::from_le_bytes(max_supply))
+                        .is_some()
+                }
+                FIELD_FIXED_SUPPLY => {
+                    if length != 0 {
+                        return Err(invalid_metadata());
+                    }
+                    std::mem::replace(&mut result.fixed_supply, true)
+                }
+                _ => return Err(invalid_metadata()),
+            };
+            if duplicate {
+                return Err(invalid_metadata());
+            }
+        }
+
+        // Everything after the fields must be padding
+        if metadata[offset..].iter().any(|b| *b != 0) {
+            return Err(invalid_metadata());
+        }
+
+        Ok(Some(result))
+    }
+}
+
+fn is_valid_ticker(ticker: &str) -> bool {
+    !ticker.is_empty()
+        && ticker.len() <= MAX_TICKER_LENGTH
+        && ticker.bytes().all(|b| b.is_ascii_alphanumeric())
+}
+
+fn is_valid_url(url: &str) -> bool {
+    !url.is_empty() && !url.chars().any(char::is_control)
+}
+
+fn invalid_metadata() -> IronfishError {
+    IronfishError::new(IronfishErrorKind::InvalidAssetMetadata)
+}
+
+impl Asset {
+    /// Create an asset with structured metadata. Unlike [`Asset::new`], fails
+    /// instead of truncating if the name does not fit in [`NAME_LENGTH`]
+    /// bytes.
+    pub fn with_structured_metadata(
+        creator: PublicAddress,
+        name: &str,
+        metadata: &AssetMetadata,
+    ) -> Result<Asset, IronfishError> {
+        if name.trim().len() > NAME_LENGTH {
+            return Err(IronfishError::new(IronfishErrorKind::InvalidData));
+        }
+
+        Asset::new_with_metadata_bytes(creator, name, metadata.encode()?)
+    }
+
+    /// Decode the structured metadata of the asset, or `None` if the asset
+    /// has free-form metadata.
+    pub fn parse_metadata(&self) -> Result<Option<AssetMetadata>, IronfishError> {
+        AssetMetadata::decode(&self.metadata)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AssetMetadata, STRUCTURED_METADATA_TAG};
+    use crate::{
+        assets::asset::{Asset, METADATA_LENGTH},
+        errors::IronfishErrorKind,
+        SaplingKey,
+    };
+
+    fn full_metadata() -> AssetMetadata {
+        AssetMetadata {
+            decimals: Some(8),
+            ticker: Some("TEST".to_string()),
+            url: Some("https://example.com/testcoin.json".to_string()),
+            content_hash: Some([7; 32]),
+            max_supply: Some(21_000_000),
+            fixed_supply: true,
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let creator = SaplingKey::generate_key().public_address();
+
+        let metadata = full_metadata();
+        let asset = Asset::with_structured_metadata(creator, "Testcoin", &metadata).unwrap();
+        assert_eq!(asset.metadata()[0], STRUCTURED_METADATA_TAG);
+        assert_eq!(asset.parse_metadata().unwrap(), Some(metadata));
+
+        let empty = AssetMetadata::default();
+        let asset = Asset::with_structured_metadata(creator, "Testcoin", &empty).unwrap();
+        assert_eq!(asset.parse_metadata().unwrap(), Some(empty));
+
+        // The metadata is part of the asset identifier
+        let other = AssetMetadata {
+            decimals: Some(2),
+            ..AssetMetadata::default()
+        };
+        let other_asset = Asset::with_structured_metadata(creator, "Testcoin", &other).unwrap();
+        assert_ne!(asset.id(), other_asset.id());
+    }
+
+    #[test]
+    fn test_free_form_metadata() {
+        let creator = SaplingKey::generate_key().public_address();
+
+        let asset = Asset::new(creator, "Testcoin", "{ 'token_identifier': '0x123' }").unwrap();
+        assert_eq!(asset.parse_metadata().unwrap(), None);
+
+        let asset = Asset::new(creator, "Testcoin", "").unwrap();
+        assert_eq!(asset.parse_metadata().unwrap(), None);
+    }
+
+    #[test]
+    fn test_encode_errors() {
+        let creator = SaplingKey::generate_key().public_address();
+
+        let is_invalid = |metadata: AssetMetadata| {
+            matches!(
+                metadata.encode(),
+                Err(e) if matches!(e.kind, IronfishErrorKind::InvalidAssetMetadata)
+            )
+        };
+
+        // Would be truncated
+        let url = format!("https://example.com/{}", "a".repeat(60));
+        assert!(is_invalid(AssetMetadata {
+            url: Some(url),
+            ..full_metadata()
+        }));
+
+        assert!(is_invalid(AssetMetadata {
+            decimals: Some(19),
+            ..AssetMetadata::default()
+        }));
+        assert!(is_invalid(AssetMetadata {
+            ticker: Some("TOO LONG TICKER".to_string()),
+            ..AssetMetadata::default()
+        }));
+        assert!(is_invalid(AssetMetadata {
+            ticker: Some("T-1".to_string()),
+            ..AssetMetadata::default()
+        }));
+        assert!(is_invalid(AssetMetadata {
+            url: Some("".to_string()),
+            ..AssetMetadata::default()
+        }));
+
+        assert!(matches!(
+            Asset::with_structured_metadata(creator, &"a".repeat(33), &full_metadata()),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidData)
+        ));
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let is_invalid = |metadata: [u8; METADATA_LENGTH]| {
+            matches!(
+                AssetMetadata::decode(&metadata),
+                Err(e) if matches!(e.kind, IronfishErrorKind::InvalidAssetMetadata)
+            )
+        };
+
+        let valid = full_metadata().encode().unwrap();
+
+        let mut metadata = valid;
+        metadata[1] = 2;
+        assert!(is_invalid(metadata), "unknown version");
+
+        let mut metadata = valid;
+        metadata[2] = 0x7f;
+        assert!(is_invalid(metadata), "unknown field");
+
+        let mut metadata = valid;
+        metadata[METADATA_LENGTH - 1] = 1;
+        assert!(is_invalid(metadata), "data after the fields");
+
+        let mut metadata = [0; METADATA_LENGTH];
+        metadata[..2].copy_from_slice(&[STRUCTURED_METADATA_TAG, 1]);
+        metadata[2..5].copy_from_slice(&[1, 1, 2]);
+        metadata[5..8].copy_from_slice(&[1, 1, 3]);
+        assert!(is_invalid(metadata), "duplicate field");
+
+        let mut metadata = [0; METADATA_LENGTH];
+        metadata[..2].copy_from_slice(&[STRUCTURED_METADATA_TAG, 1]);
+        metadata[2..4].copy_from_slice(&[3, 200]);
+        assert!(is_invalid(metadata), "field past the end");
+    }
+}
@@ -3,3 +3,6 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 pub mod asset;
 pub mod asset_identifier;
+pub mod ledger;
+pub mod metadata;
+pub mod policy;
@@ -0,0 +1,220 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Supply policies declared in the structured metadata of an asset.
+//!
+//! Policies are not enforced by consensus: an owner can always mint more of
+//! an asset. They let holders, wallets and explorers reject or flag the mints
+//! that break the policy the asset was created with. Assets with free-form
+//! metadata have no policy.
+
+use std::collections::HashMap;
+
+use super::{asset_identifier::AssetIdentifier, ledger::AssetLedgerView, metadata::AssetMetadata};
+use crate::{
+    errors::{IronfishError, IronfishErrorKind},
+    transaction::Transaction,
+};
+
+/// Check the mints of `transaction` against the supply policies of their
+/// assets, given the state of the assets before the transaction:
+///
+/// - The total value ever minted of an asset with a `max_supply` cannot
+///   exceed it. Burning an asset does not allow minting more of it.
+/// - An asset with a `fixed_supply` cannot be minted again once a positive
+///   value of it was minted. Mints of zero value, which can transfer the
+///   ownership of the asset, are allowed and do not fix the supply.
+///
+/// Fails with [`IronfishErrorKind::MintPolicyViolation`] if a mint breaks the
+/// policy of its asset, or with [`IronfishErrorKind::InvalidAssetMetadata`] if
+/// the policy cannot be parsed.
+pub fn validate_mint_policy(
+    view: &impl AssetLedgerView,
+    transaction: &Transaction,
+) -> Result<(), IronfishError> {
+    // Total minted for each asset, including the previous mints of this
+    // transaction
+    let mut minted: HashMap<AssetIdentifier, u64> = HashMap::new();
+
+    for mint in transaction.mints() {
+        let metadata = match mint.asset.parse_metadata()? {
+            Some(metadata) => metadata,
+            None => continue,
+        };
+
+        let asset_id = mint.asset.id();
+        let previously_minted = match minted.get(asset_id) {
+            Some(value) => Some(*value),
+            None => view.asset_record(asset_id).map(|record| record.minted),
+        };
+
+        let total = previously_minted
+            .unwrap_or(0)
+            .checked_add(mint.value)
+            .ok_or_else(|| IronfishError::new(IronfishErrorKind::IllegalValue))?;
+
+        check_policy(
+            &metadata,
+            previously_minted.unwrap_or(0) > 0,
+            mint.value,
+            total,
+        )?;
+
+        minted.insert(*asset_id, total);
+    }
+
+    Ok(())
+}
+
+fn check_policy(
+    metadata: &AssetMetadata,
+    was_minted: bool,
+    value: u64,
+    total: u64,
+) -> Result<(), IronfishError> {
+    if metadata.fixed_supply && was_minted && value > 0 {
+        return Err(IronfishError::new(IronfishErrorKind::MintPolicyViolation));
+    }
+
+    if let Some(max_supply) = metadata.max_supply {
+        if total > max_supply {
+            return Err(IronfishError::new(IronfishErrorKind::MintPolicyViolation));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::validate_mint_policy;
+    use crate::{
+        assets::{asset::Asset, ledger::AssetLedger, metadata::AssetMetadata},
+        errors::IronfishErrorKind,
+        keys::{PublicAddress, SaplingKey},
+        transaction::{ProposedTransaction, Transaction, TransactionVersion},
+    };
+
+    fn mint(
+        key: &SaplingKey,
+        mints: &[(Asset, u64)],
+        new_owner: Option<PublicAddress>,
+    ) -> Transaction {
+        let mut transaction = ProposedTransaction::new(TransactionVersion::latest());
+        for (asset, value) in mints {
+            match new_owner {
+                Some(new_owner) => transaction
+                    .add_mint_with_new_owner(*asset, *value, new_owner)
+                    .unwrap(),
+                None => transaction.add_mint(*asset, *value).unwrap(),
+            }
+        }
+        transaction.post(key, None, 0).unwrap()
+    }
+
+    fn is_violation(ledger: &AssetLedger, transaction: &Transaction) -> bool {
+        matches!(
+            validate_mint_policy(ledger, transaction),
+            Err(e) if matches!(e.kind, IronfishErrorKind::MintPolicyViolation)
+        )
+    }
+
+    #[test]
+    fn test_max_supply() {
+        let key = SaplingKey::generate_key();
+        let metadata = AssetMetadata {
+            max_supply: Some(100),
+            ..AssetMetadata::default()
+        };
+        let asset =
+            Asset::with_structured_metadata(key.public_address(), "Testcoin", &metadata).unwrap();
+
+        let mut ledger = AssetLedger::new();
+
+        // Mints in the same transaction add up
+        assert!(is_violation(
+            &ledger,
+            &mint(&key, &[(asset, 60), (asset, 41)], None)
+        ));
+
+        let transaction = mint(&key, &[(asset, 60), (asset, 40)], None);
+        validate_mint_policy(&ledger, &transaction).unwrap();
+        ledger.apply_transaction(&transaction).unwrap();
+
+        assert!(is_violation(&ledger, &mint(&key, &[(asset, 1)], None)));
+        validate_mint_policy(&ledger, &mint(&key, &[(asset, 0)], None)).unwrap();
+    }
+
+    #[test]
+    fn test_fixed_supply() {
+        let key = SaplingKey::generate_key();
+        let new_owner = SaplingKey::generate_key().public_address();
+        let metadata = AssetMetadata {
+            fixed_supply: true,
+            ..AssetMetadata::default()
+        };
+        let asset =
+            Asset::with_structured_metadata(key.public_address(), "Testcoin", &metadata).unwrap();
+
+        let mut ledger = AssetLedger::new();
+
+        assert!(is_violation(
+            &ledger,
+            &mint(&key, &[(asset, 10), (asset, 10)], None)
+        ));
+
+        let transaction = mint(&key, &[(asset, 10)], None);
+        validate_mint_policy(&ledger, &transaction).unwrap();
+        ledger.apply_transaction(&transaction).unwrap();
+
+        assert!(is_violation(&ledger, &mint(&key, &[(asset, 1)], None)));
+
+        // Ownership can still be transferred
+        validate_mint_policy(&ledger, &mint(&key, &[(asset, 0)], Some(new_owner))).unwrap();
+    }
+
+    #[test]
+    fn test_fixed_supply_after_zero_mint() {
+        let key = SaplingKey::generate_key();
+        let metadata = AssetMetadata {
+            fixed_supply: true,
+            ..AssetMetadata::default()
+        };
+        let asset =
+            Asset::with_structured_metadata(key.public_address(), "Testcoin", &metadata).unwrap();
+
+        let mut ledger = AssetLedger::new();
+
+        // A zero mint does not fix the supply, in the same transaction or in
+        // an earlier one
+        validate_mint_policy(&ledger, &mint(&key, &[(asset, 0), (asset, 10)], None)).unwrap();
+
+        let transaction = mint(&key, &[(asset, 0)], None);
+        validate_mint_policy(&ledger, &transaction).unwrap();
+        ledger.apply_transaction(&transaction).unwrap();
+
+        let transaction = mint(&key, &[(asset, 10)], None);
+        validate_mint_policy(&ledger, &transaction).unwrap();
+        ledger.apply_transaction(&transaction).unwrap();
+
+        assert!(is_violation(&ledger, &mint(&key, &[(asset, 1)], None)));
+    }
+
+    #[test]
+    fn test_no_policy() {
+        let key = SaplingKey::generate_key();
+        let ledger = AssetLedger::new();
+
+        let asset = Asset::new(key.public_address(), "Testcoin", "").unwrap();
+        validate_mint_policy(&ledger, &mint(&key, &[(asset, i64::MAX as u64)], None)).unwrap();
+
+        let asset = Asset::with_structured_metadata(
+            key.public_address(),
+            "Testcoin",
+            &AssetMetadata::default(),
+        )
+        .unwrap();
+        validate_mint_policy(&ledger, &mint(&key, &[(asset, i64::MAX as u64)], None)).unwrap();
+    }
+}
@@ -10,10 +10,13 @@ use std::io;
 use std::num;
 use std::string;
 
+use crate::{assets::asset_identifier::AssetIdentifier, serializing::bytes_to_hex};
+
 #[derive(Debug)]
 pub struct IronfishError {
     pub kind: IronfishErrorKind,
     pub source: Option<Box<dyn Error>>,
+    pub payload: Option<IronfishErrorPayload>,
     pub backtrace: Backtrace,
 }
 
@@ -32,8 +35,10 @@ pub enum IronfishErrorKind {
     IllegalValue,
     InconsistentWitness,
     InvalidAssetIdentifier,
+    InvalidAssetMetadata,
     InvalidAuthorizingKey,
     InvalidBalance,
+    InvalidBech32,
     InvalidCommitment,
     InvalidData,
     InvalidDecryptionKey,
@@ -43,7 +48,9 @@ pub enum IronfishErrorKind {
     InvalidFrostIdentifier,
     InvalidFrostSignatureShare,
     InvalidLanguageEncoding,
+    InvalidMemo,
     InvalidMinersFeeTransaction,
+    InvalidMintOwner,
     InvalidMintProof,
     InvalidMintSignature,
     InvalidMnemonicString,
@@ -64,8 +71,10 @@ pub enum IronfishErrorKind {
     InvalidWord,
     Io,
     IsSmallOrder,
+    MintPolicyViolation,
     RandomnessError,
     RoundTwoSigningFailure,
+    ThreadPanicked,
     TryFromInt,
     Utf8,
 }
@@ -75,6 +84,7 @@ impl IronfishError {
         Self {
             kind,
             source: None,
+            payload: None,
             backtrace: Backtrace::capture(),
         }
     }
@@ -86,6 +96,16 @@ impl IronfishError {
         Self {
             kind,
             source: Some(source.into()),
+            payload: None,
+            backtrace: Backtrace::capture(),
+        }
+    }
+
+    pub fn new_with_payload(kind: IronfishErrorKind, payload: IronfishErrorPayload) -> Self {
+        Self {
+            kind,
+            source: None,
+            payload: Some(payload),
             backtrace: Backtrace::capture(),
         }
     }
@@ -93,10 +113,53 @@ impl IronfishError {
 
 impl Error for IronfishError {}
 
+/// Structured context attached to some errors, for callers that need more
+/// than the kind of the error
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IronfishErrorPayload {
+    /// Attached to [`IronfishErrorKind::InvalidBalance`] errors raised when
+    /// the value of an asset in a transaction does not balance or overflows
+    InvalidBalance(BalanceErrorDetails),
+}
+
+impl fmt::Display for IronfishErrorPayload {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IronfishErrorPayload::InvalidBalance(details) => write!(
+                f,
+                "asset {}, spends and mints {}, outputs and burns {}, intended fee {}",
+                bytes_to_hex(details.asset_id.as_bytes()),
+                details.spends_and_mints,
+                details.outputs_and_burns,
+                details.intended_fee
+            ),
+        }
+    }
+}
+
+/// Totals of the asset that does not balance in a transaction
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BalanceErrorDetails {
+    pub asset_id: AssetIdentifier,
+
+    /// Total value of the spends and mints of the asset
+    pub spends_and_mints: u64,
+
+    /// Total value of the outputs and burns of the asset
+    pub outputs_and_burns: u64,
+
+    /// Fee of the transaction, which is paid in the native asset. Zero when
+    /// the balance overflowed before the fee was known.
+    pub intended_fee: i64,
+}
+
 impl fmt::Display for IronfishError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let has_backtrace = self.backtrace.status() == BacktraceStatus::Captured;
         write!(f, "{:?}", self.kind)?;
+        if let Some(payload) = &self.payload {
+            write!(f, ": {}", payload)?;
+        }
         if let Some(source) = &self.source {
             write!(f, "\nCaused by: \n{}", source)?;
         }
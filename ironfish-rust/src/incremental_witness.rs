@@ -0,0 +1,460 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::io;
+
+use blstrs::Scalar;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use ironfish_zkp::constants::TREE_DEPTH;
+
+use crate::{
+    errors::{IronfishError, IronfishErrorKind},
+    serializing::read_scalar,
+    witness::{Witness, WitnessNode, WitnessTrait},
+    MerkleNoteHash, NoteCommitmentTree,
+};
+
+/// Witness for a single note that is kept up to date as new notes are added
+/// to the note commitment tree, without access to the rest of the tree.
+///
+/// Create it from a [`NoteCommitmentTree`] that contains the note, or from
+/// the frontier of the tree just before the note was added, then
+/// [`IncrementalWitness::append`] every note commitment that is added to the
+/// tree after that. At any time, the witness proves that the note is part of
+/// the tree at its current size.
+///
+/// Only `O(TREE_DEPTH)` hashes are stored: the siblings of the nodes on the
+/// path from the note to the root that are already complete, and the
+/// frontier of the tree (the complete nodes that are still waiting for their
+/// right sibling), which is enough to compute the hash of a sibling that is
+/// only partially filled.
+///
+/// The size of the tree is reported as a `u32` by [`WitnessTrait`], so the
+/// witness holds at most `u32::MAX` notes, one less than a full tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IncrementalWitness {
+    position: u64,
+    leaf: Scalar,
+    tree_size: u32,
+
+    /// Sibling of the node containing the note at each depth, once all the
+    /// leaves of the sibling are in the tree
+    siblings: Vec<Option<Scalar>>,
+
+    /// `frontier[depth]` is the complete node at `depth` that does not have a
+    /// right sibling yet, if any
+    frontier: Vec<Option<Scalar>>,
+}
+
+impl IncrementalWitness {
+    /// Create a witness for the note at `position`, for the current size of
+    /// `tree`. Returns `None` if there is no note at that position, or if the
+    /// tree holds more than `u32::MAX` notes.
+    pub fn from_tree(tree: &NoteCommitmentTree, position: usize) -> Option<Self> {
+        let leaf = tree.get(position)?.0;
+        let tree_size = u32::try_from(tree.size()).ok()?;
+
+        let siblings = (0..TREE_DEPTH)
+            .map(|depth| tree.complete_node_hash(depth, (position >> depth) ^ 1))
+            .collect();
+
+        Some(IncrementalWitness {
+            position: position as u64,
+            leaf,
+            tree_size,
+            siblings,
+            frontier: tree.frontier(),
+        })
+    }
+
+    /// Create a witness for `note_hash`, added to a tree of `tree_size` notes
+    /// with the given frontier, as returned by
+    /// [`NoteCommitmentTree::frontier`]. This does not need the rest of the
+    /// tree: the left siblings of the new note are the nodes of the frontier.
+    ///
+    /// Fails with [`IronfishErrorKind::InvalidData`] if the frontier does not
+    /// match `tree_size`, or if the tree is full.
+    pub fn from_frontier(
+        tree_size: u32,
+        frontier: &[Option<Scalar>],
+        note_hash: MerkleNoteHash,
+    ) -> Result<Self, IronfishError> {
+        if !is_valid_frontier(tree_size, frontier) {
+            return Err(IronfishError::new(IronfishErrorKind::InvalidData));
+        }
+
+        let mut witness = IncrementalWitness {
+            position: u64::from(tree_size),
+            leaf: note_hash.0,
+            tree_size,
+            siblings: frontier.to_vec(),
+            frontier: frontier.to_vec(),
+        };
+        witness.append(note_hash)?;
+
+        Ok(witness)
+    }
+
+    /// Position of the note in the tree
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Note commitment of the witnessed note
+    pub fn leaf(&self) -> MerkleNoteHash {
+        MerkleNoteHash(self.leaf)
+    }
+
+    /// Update the witness with the next note commitment added to the tree.
+    /// Fails with [`IronfishErrorKind::InvalidData`] if the tree is full.
+    pub fn append(&mut self, note_hash: MerkleNoteHash) -> Result<(), IronfishError> {
+        let tree_size = self
+            .tree_size
+            .checked_add(1)
+            .ok_or_else(|| IronfishError::new(IronfishErrorKind::InvalidData))?;
+        let mut node = note_hash.0;
+
+        for depth in 0..TREE_DEPTH {
+            // `node` is complete, and at this index
+            let index = u64::from(self.tree_size) >> depth;
+
+            if index == (self.position >> depth) ^ 1 {
+                self.siblings[depth] = Some(node);
+            }
+
+            if index % 2 == 0 {
+                self.frontier[depth] = Some(node);
+                break;
+            }
+
+            let left = self.frontier[depth]
+                .take()
+                .expect("the frontier contains the left sibling of every right node");
+            node = MerkleNoteHash::combine_hash(depth, &left, &node);
+        }
+
+        self.tree_size = tree_size;
+        Ok(())
+    }
+
+    /// Create a [`Witness`] with the current authentication path and root hash
+    pub fn witness(&self) -> Witness {
+        let mut current = self.leaf;
+        let mut auth_path = Vec::with_capacity(TREE_DEPTH);
+
+        for depth in 0..TREE_DEPTH {
+            let index = self.position >> depth;
+
+            let node = if index % 2 == 0 {
+                let sibling = match self.siblings[depth] {
+                    Some(sibling) => sibling,
+                    None if (index + 1) << depth < u64::from(self.tree_size) => {
+                        self.partial_node_hash(depth)
+                    }
+                    None => current,
+                };
+                current = MerkleNoteHash::combine_hash(depth, &current, &sibling);
+                WitnessNode::Left(sibling)
+            } else {
+                let sibling = self.siblings[depth]
+                    .expect("left siblings are complete when the note is added");
+                current = MerkleNoteHash::combine_hash(depth, &sibling, &current);
+                WitnessNode::Right(sibling)
+            };
+
+            auth_path.push(node);
+        }
+
+        Witness {
+            tree_size: self.tree_size as usize,
+            root_hash: current,
+            auth_path,
+        }
+    }
+
+    /// Hash of the incomplete node at `depth` that contains the last leaf of
+    /// the tree, computed from the frontier below it.
+    fn partial_node_hash(&self, depth: usize) -> Scalar {
+        let mut current: Option<Scalar> = None;
+
+        for (level, frontier_node) in self.frontier[..depth].iter().enumerate() {
+            current = match (frontier_node, current) {
+                (Some(left), Some(right)) => {
+                    Some(MerkleNoteHash::combine_hash(level, left, &right))
+                }
+                (Some(left), None) => Some(MerkleNoteHash::combine_hash(level, left, left)),
+                (None, Some(left)) => Some(MerkleNoteHash::combine_hash(level, &left, &left)),
+                (None, None) => None,
+            };
+        }
+
+        current.expect("the node contains at least one leaf")
+    }
+
+    pub fn read<R: io::Read>(mut reader: R) -> Result<Self, IronfishError> {
+        let position = reader.read_u64::<LittleEndian>()?;
+        let tree_size = u32::try_from(reader.read_u64::<LittleEndian>()?)
+            .map_err(|_| IronfishError::new(IronfishErrorKind::InvalidData))?;
+        if position >= u64::from(tree_size) {
+            return Err(IronfishError::new(IronfishErrorKind::InvalidData));
+        }
+        let leaf = read_scalar(&mut reader)?;
+
+        let siblings = read_optional_hashes(&mut reader)?;
+        let frontier = read_optional_hashes(&mut reader)?;
+
+        let witness = IncrementalWitness {
+            position,
+            leaf,
+            tree_size,
+            siblings,
+            frontier,
+        };
+        if !witness.is_consistent() {
+            return Err(IronfishError::new(IronfishErrorKind::InvalidData));
+        }
+
+        Ok(witness)
+    }
+
+    /// Check that the stored siblings and frontier are the ones expected for
+    /// the position and tree size, which [`IncrementalWitness::append`] and
+    /// [`IncrementalWitness::witness`] rely on
+    fn is_consistent(&self) -> bool {
+        if !is_valid_frontier(self.tree_size, &self.frontier) {
+            return false;
+        }
+
+        self.siblings.iter().enumerate().all(|(depth, sibling)| {
+            let index = (self.position >> depth) ^ 1;
+            // Left siblings are always complete, right siblings once their
+            // last leaf is in the tree
+            let is_complete = index % 2 == 0 || (index + 1) << depth <= u64::from(self.tree_size);
+            sibling.is_some() == is_complete
+        })
+    }
+
+    pub fn write<W: io::Write>(&self, mut writer: W) -> Result<(), IronfishError> {
+        writer.write_u64::<LittleEndian>(self.position)?;
+        writer.write_u64::<LittleEndian>(u64::from(self.tree_size))?;
+        writer.write_all(&self.leaf.to_bytes_le())?;
+
+        write_optional_hashes(&mut writer, &self.siblings)?;
+        write_optional_hashes(&mut writer, &self.frontier)?;
+
+        Ok(())
+    }
+}
+
+impl WitnessTrait for IncrementalWitness {
+    fn verify(&self, my_hash: &MerkleNoteHash) -> bool {
+        self.witness().verify(my_hash)
+    }
+
+    fn get_auth_path(&self) -> Vec<WitnessNode<Scalar>> {
+        self.witness().auth_path
+    }
+
+    fn root_hash(&self) -> Scalar {
+        self.witness().root_hash
+    }
+
+    fn tree_size(&self) -> u32 {
+        self.tree_size
+    }
+}
+
+/// The frontier of a tree of `tree_size` leaves has a node at each depth where
+/// the number of complete nodes is odd
+fn is_valid_frontier(tree_size: u32, frontier: &[Option<Scalar>]) -> bool {
+    frontier.len() == TREE_DEPTH
+        && frontier
+            .iter()
+            .enumerate()
+            .all(|(depth, node)| node.is_some() == ((tree_size >> depth) % 2 == 1))
+}
+
+fn read_optional_hashes<R: io::Read>(mut reader: R) -> Result<Vec<Option<Scalar>>, IronfishError> {
+    let mut hashes = Vec::with_capacity(TREE_DEPTH);
+    for _ in 0..TREE_DEPTH {
+        let hash = match reader.read_u8()? {
+            0 => None,
+            1 => Some(read_scalar(&mut reader)?),
+            _ => return Err(IronfishError::new(IronfishErrorKind::InvalidData)),
+        };
+        hashes.push(hash);
+    }
+
+    Ok(hashes)
+}
+
+fn write_optional_hashes<W: io::Write>(
+    mut writer: W,
+    hashes: &[Option<Scalar>],
+) -> Result<(), IronfishError> {
+    for hash in hashes {
+        match hash {
+            Some(hash) => {
+                writer.write_u8(1)?;
+                writer.write_all(&hash.to_bytes_le())?;
+            }
+            None => writer.write_u8(0)?,
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use blstrs::Scalar;
+    use ironfish_zkp::constants::TREE_DEPTH;
+
+    use super::IncrementalWitness;
+    use crate::{
+        errors::IronfishErrorKind, witness::WitnessTrait, MerkleNoteHash, NoteCommitmentTree,
+    };
+
+    fn leaf(value: u64) -> MerkleNoteHash {
+        MerkleNoteHash(Scalar::from(value))
+    }
+
+    #[test]
+    fn test_incremental_witness() {
+        let mut tree = NoteCommitmentTree::new();
+        for value in 0..5 {
+            tree.append(leaf(value));
+        }
+
+        let mut witnesses: Vec<IncrementalWitness> = (0..5)
+            .map(|position| IncrementalWitness::from_tree(&tree, position).unwrap())
+            .collect();
+
+        for value in 5..19 {
+            let position = tree.append(leaf(value));
+            for witness in witnesses.iter_mut() {
+                witness.append(leaf(value)).unwrap();
+            }
+            witnesses.push(IncrementalWitness::from_tree(&tree, position).unwrap());
+
+            for witness in witnesses.iter() {
+                let expected = tree.witness(witness.position() as usize).unwrap();
+                assert_eq!(witness.witness(), expected);
+                assert_eq!(witness.tree_size(), tree.size() as u32);
+                assert!(witness.verify(&witness.leaf()));
+            }
+        }
+
+        assert!(IncrementalWitness::from_tree(&tree, 19).is_none());
+    }
+
+    #[test]
+    fn test_incremental_witness_serialization() {
+        let mut tree = NoteCommitmentTree::new();
+        for value in 0..6 {
+            tree.append(leaf(value));
+        }
+        let mut witness = IncrementalWitness::from_tree(&tree, 2).unwrap();
+
+        let mut serialized = vec![];
+        witness.write(&mut serialized).unwrap();
+        let mut deserialized = IncrementalWitness::read(&serialized[..]).unwrap();
+        assert_eq!(deserialized, witness);
+
+        tree.append(leaf(6));
+        witness.append(leaf(6)).unwrap();
+        deserialized.append(leaf(6)).unwrap();
+        assert_eq!(deserialized.witness(), tree.witness(2).unwrap());
+
+        serialized[16 + 32] = 2;
+        assert!(matches!(
+            IncrementalWitness::read(&serialized[..]),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidData)
+        ));
+    }
+
+    #[test]
+    fn test_incremental_witness_from_frontier() {
+        let mut tree = NoteCommitmentTree::new();
+        for value in 0..11 {
+            let witness = IncrementalWitness::from_frontier(
+                tree.size() as u32,
+                &tree.frontier(),
+                leaf(value),
+            )
+            .unwrap();
+            let position = tree.append(leaf(value));
+
+            assert_eq!(
+                witness,
+                IncrementalWitness::from_tree(&tree, position).unwrap()
+            );
+        }
+
+        // The frontier must match the size of the tree
+        assert!(matches!(
+            IncrementalWitness::from_frontier(12, &tree.frontier(), leaf(11)),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidData)
+        ));
+    }
+
+    #[test]
+    fn test_incremental_witness_full_tree() {
+        // A tree of `u32::MAX - 1` notes has a complete node at every depth
+        // except the first
+        let frontier: Vec<Option<Scalar>> = (0..TREE_DEPTH)
+            .map(|depth| (depth > 0).then(|| Scalar::from(depth as u64)))
+            .collect();
+        let mut witness =
+            IncrementalWitness::from_frontier(u32::MAX - 1, &frontier, leaf(0)).unwrap();
+        assert_eq!(witness.tree_size(), u32::MAX);
+
+        // The size of the tree would no longer fit in a u32
+        assert!(matches!(
+            witness.append(leaf(1)),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidData)
+        ));
+        assert!(matches!(
+            IncrementalWitness::from_frontier(u32::MAX, &witness.frontier, leaf(1)),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidData)
+        ));
+
+        let mut serialized = vec![];
+        witness.write(&mut serialized).unwrap();
+        serialized[8..16].copy_from_slice(&(1u64 << 32).to_le_bytes());
+        assert!(matches!(
+            IncrementalWitness::read(&serialized[..]),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidData)
+        ));
+    }
+
+    #[test]
+    fn test_incremental_witness_read_inconsistent() {
+        let mut tree = NoteCommitmentTree::new();
+        for value in 0..6 {
+            tree.append(leaf(value));
+        }
+        let witness = IncrementalWitness::from_tree(&tree, 2).unwrap();
+
+        // Missing sibling
+        let mut tampered = witness.clone();
+        tampered.siblings[0] = None;
+        let mut serialized = vec![];
+        tampered.write(&mut serialized).unwrap();
+        assert!(matches!(
+            IncrementalWitness::read(&serialized[..]),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidData)
+        ));
+
+        // Frontier of a tree of another size
+        let mut tampered = witness;
+        tampered.frontier[0] = Some(Scalar::from(1));
+        let mut serialized = vec![];
+        tampered.write(&mut serialized).unwrap();
+        assert!(matches!(
+            IncrementalWitness::read(&serialized[..]),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidData)
+        ));
+    }
+}
@@ -0,0 +1,423 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Bech32m encoding of keys and addresses.
+//!
+//! The hex encodings have no checksum, so a typo in a key or address is
+//! either rejected as an invalid point or, worse, silently turns into a
+//! different valid key. The Bech32m encoding adds a checksum that detects
+//! typos, and a human-readable prefix that identifies the type of the key
+//! and the network it is meant for, for example `ifaddr1...` for a mainnet
+//! public address or `ifivktest1...` for a testnet incoming view key.
+//!
+//! The encoded payload is the same byte representation used by the hex
+//! encodings.
+
+use bech32::{FromBase32, ToBase32, Variant};
+
+use super::{
+    FullViewingKey, IncomingViewKey, OutgoingViewKey, PublicAddress, SaplingKey, ViewKey,
+    FULL_VIEWING_KEY_SIZE,
+};
+use crate::errors::{IronfishError, IronfishErrorKind};
+
+/// The network a key or address is meant to be used on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Network {
+    Mainnet,
+    Testnet,
+    Devnet,
+}
+
+/// The kinds of keys that can be encoded with Bech32m
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyType {
+    SpendingKey,
+    ViewKey,
+    IncomingViewKey,
+    OutgoingViewKey,
+    PublicAddress,
+    FullViewingKey,
+}
+
+/// Human-readable prefix for every combination of key type and network
+const HUMAN_READABLE_PREFIXES: [(KeyType, Network, &str); 18] = [
+    (KeyType::SpendingKey, Network::Mainnet, "ifsk"),
+    (KeyType::SpendingKey, Network::Testnet, "ifsktest"),
+    (KeyType::SpendingKey, Network::Devnet, "ifskdev"),
+    (KeyType::ViewKey, Network::Mainnet, "ifvk"),
+    (KeyType::ViewKey, Network::Testnet, "ifvktest"),
+    (KeyType::ViewKey, Network::Devnet, "ifvkdev"),
+    (KeyType::IncomingViewKey, Network::Mainnet, "ifivk"),
+    (KeyType::IncomingViewKey, Network::Testnet, "ifivktest"),
+    (KeyType::IncomingViewKey, Network::Devnet, "ifivkdev"),
+    (KeyType::OutgoingViewKey, Network::Mainnet, "ifovk"),
+    (KeyType::OutgoingViewKey, Network::Testnet, "ifovktest"),
+    (KeyType::OutgoingViewKey, Network::Devnet, "ifovkdev"),
+    (KeyType::PublicAddress, Network::Mainnet, "ifaddr"),
+    (KeyType::PublicAddress, Network::Testnet, "ifaddrtest"),
+    (KeyType::PublicAddress, Network::Devnet, "ifaddrdev"),
+    (KeyType::FullViewingKey, Network::Mainnet, "iffvk"),
+    (KeyType::FullViewingKey, Network::Testnet, "iffvktest"),
+    (KeyType::FullViewingKey, Network::Devnet, "iffvkdev"),
+];
+
+impl KeyType {
+    /// The human-readable prefix used for this key type on `network`
+    pub fn human_readable_prefix(&self, network: Network) -> &'static str {
+        HUMAN_READABLE_PREFIXES
+            .iter()
+            .find(|(key_type, key_network, _)| key_type == self && *key_network == network)
+            .map(|(_, _, prefix)| *prefix)
+            .expect("every key type has a prefix for every network")
+    }
+
+    /// Find the key type and network that use the given human-readable prefix
+    pub fn from_human_readable_prefix(prefix: &str) -> Option<(KeyType, Network)> {
+        HUMAN_READABLE_PREFIXES
+            .iter()
+            .find(|(_, _, key_prefix)| *key_prefix == prefix)
+            .map(|(key_type, network, _)| (*key_type, *network))
+    }
+
+    /// Size in bytes of the encoded key
+    fn size(&self) -> usize {
+        match self {
+            KeyType::ViewKey => 64,
+            KeyType::FullViewingKey => FULL_VIEWING_KEY_SIZE,
+            _ => 32,
+        }
+    }
+}
+
+/// A key of any type, parsed from its Bech32m encoding by [`decode_bech32`]
+#[derive(Clone)]
+pub enum Bech32Key {
+    SpendingKey(SaplingKey),
+    ViewKey(ViewKey),
+    IncomingViewKey(IncomingViewKey),
+    OutgoingViewKey(OutgoingViewKey),
+    PublicAddress(PublicAddress),
+    FullViewingKey(FullViewingKey),
+}
+
+impl Bech32Key {
+    pub fn key_type(&self) -> KeyType {
+        match self {
+            Bech32Key::SpendingKey(_) => KeyType::SpendingKey,
+            Bech32Key::ViewKey(_) => KeyType::ViewKey,
+            Bech32Key::IncomingViewKey(_) => KeyType::IncomingViewKey,
+            Bech32Key::OutgoingViewKey(_) => KeyType::OutgoingViewKey,
+            Bech32Key::PublicAddress(_) => KeyType::PublicAddress,
+            Bech32Key::FullViewingKey(_) => KeyType::FullViewingKey,
+        }
+    }
+}
+
+/// Parse a Bech32m encoded key of any type, reporting which type of key it
+/// is and which network it is meant for.
+pub fn decode_bech32(value: &str) -> Result<(Network, Bech32Key), IronfishError> {
+    let (key_type, network, bytes) = decode(value)?;
+
+    let key = match key_type {
+        KeyType::SpendingKey => Bech32Key::SpendingKey(SaplingKey::read(&mut &bytes[..])?),
+        KeyType::ViewKey => Bech32Key::ViewKey(view_key_from_bytes(&bytes)?),
+        KeyType::IncomingViewKey => {
+            Bech32Key::IncomingViewKey(IncomingViewKey::read(&mut &bytes[..])?)
+        }
+        KeyType::OutgoingViewKey => {
+            Bech32Key::OutgoingViewKey(outgoing_view_key_from_bytes(&bytes))
+        }
+        KeyType::PublicAddress => Bech32Key::PublicAddress(PublicAddress::read(&mut &bytes[..])?),
+        KeyType::FullViewingKey => Bech32Key::FullViewingKey(FullViewingKey::read(&bytes[..])?),
+    };
+
+    Ok((network, key))
+}
+
+/// Decode the bytes of a Bech32m encoded key, failing if it is not of the
+/// expected type or not meant for the expected network.
+fn decode_expected(
+    value: &str,
+    expected_type: KeyType,
+    expected_network: Network,
+) -> Result<Vec<u8>, IronfishError> {
+    let (key_type, network, bytes) = decode(value)?;
+    if key_type != expected_type || network != expected_network {
+        return Err(IronfishError::new(IronfishErrorKind::InvalidBech32));
+    }
+
+    Ok(bytes)
+}
+
+fn encode(key_type: KeyType, network: Network, bytes: &[u8]) -> String {
+    bech32::encode(
+        key_type.human_readable_prefix(network),
+        bytes.to_base32(),
+        Variant::Bech32m,
+    )
+    .expect("human-readable prefixes are valid")
+}
+
+fn decode(value: &str) -> Result<(KeyType, Network, Vec<u8>), IronfishError> {
+    let (prefix, data, variant) = bech32::decode(value)
+        .map_err(|e| IronfishError::new_with_source(IronfishErrorKind::InvalidBech32, e))?;
+    if variant != Variant::Bech32m {
+        return Err(IronfishError::new(IronfishErrorKind::InvalidBech32));
+    }
+
+    let (key_type, network) = KeyType::from_human_readable_prefix(&prefix)
+        .ok_or_else(|| IronfishError::new(IronfishErrorKind::InvalidBech32))?;
+
+    let bytes = Vec::<u8>::from_base32(&data)
+        .map_err(|e| IronfishError::new_with_source(IronfishErrorKind::InvalidBech32, e))?;
+    if bytes.len() != key_type.size() {
+        return Err(IronfishError::new(IronfishErrorKind::InvalidBech32));
+    }
+
+    Ok((key_type, network, bytes))
+}
+
+fn view_key_from_bytes(bytes: &[u8]) -> Result<ViewKey, IronfishError> {
+    let mut view_key_bytes = [0; 64];
+    view_key_bytes.copy_from_slice(bytes);
+    ViewKey::from_bytes(&view_key_bytes)
+}
+
+fn outgoing_view_key_from_bytes(bytes: &[u8]) -> OutgoingViewKey {
+    let mut view_key = [0; 32];
+    view_key.copy_from_slice(bytes);
+    OutgoingViewKey { view_key }
+}
+
+impl SaplingKey {
+    /// Private spending key encoded with Bech32m for the given network
+    pub fn to_bech32(&self, network: Network) -> String {
+        encode(KeyType::SpendingKey, network, &self.spending_key())
+    }
+
+    /// Load a key from its Bech32m encoding. Fails if the string does not
+    /// encode a spending key for `network`.
+    pub fn from_bech32(value: &str, network: Network) -> Result<Self, IronfishError> {
+        let bytes = decode_expected(value, KeyType::SpendingKey, network)?;
+        Self::read(&mut &bytes[..])
+    }
+}
+
+impl ViewKey {
+    /// Viewing key encoded with Bech32m for the given network
+    pub fn to_bech32(&self, network: Network) -> String {
+        encode(KeyType::ViewKey, network, &self.to_bytes())
+    }
+
+    /// Load a key from its Bech32m encoding. Fails if the string does not
+    /// encode a view key for `network`.
+    pub fn from_bech32(value: &str, network: Network) -> Result<Self, IronfishError> {
+        let bytes = decode_expected(value, KeyType::ViewKey, network)?;
+        view_key_from_bytes(&bytes)
+    }
+}
+
+impl IncomingViewKey {
+    /// Incoming viewing key encoded with Bech32m for the given network
+    pub fn to_bech32(&self, network: Network) -> String {
+        encode(KeyType::IncomingViewKey, network, &self.view_key.to_bytes())
+    }
+
+    /// Load a key from its Bech32m encoding. Fails if the string does not
+    /// encode an incoming view key for `network`.
+    pub fn from_bech32(value: &str, network: Network) -> Result<Self, IronfishError> {
+        let bytes = decode_expected(value, KeyType::IncomingViewKey, network)?;
+        Self::read(&mut &bytes[..])
+    }
+}
+
+impl OutgoingViewKey {
+    /// Outgoing viewing key encoded with Bech32m for the given network
+    pub fn to_bech32(&self, network: Network) -> String {
+        encode(KeyType::OutgoingViewKey, network, &self.view_key)
+    }
+
+    /// Load a key from its Bech32m encoding. Fails if the string does not
+    /// encode an outgoing view key for `network`.
+    pub fn from_bech32(value: &str, network: Network) -> Result<Self, IronfishError> {
+        let bytes = decode_expected(value, KeyType::OutgoingViewKey, network)?;
+        Ok(outgoing_view_key_from_bytes(&bytes))
+    }
+}
+
+impl PublicAddress {
+    /// Public address encoded with Bech32m for the given network
+    pub fn to_bech32(&self, network: Network) -> String {
+        encode(KeyType::PublicAddress, network, &self.public_address())
+    }
+
+    /// Load an address from its Bech32m encoding. Fails if the string does
+    /// not encode a public address for `network`.
+    pub fn from_bech32(value: &str, network: Network) -> Result<Self, IronfishError> {
+        let bytes = decode_expected(value, KeyType::PublicAddress, network)?;
+        Self::read(&mut &bytes[..])
+    }
+}
+
+impl FullViewingKey {
+    /// Watch-only keys encoded with Bech32m for the given network
+    pub fn to_bech32(&self, network: Network) -> String {
+        encode(KeyType::FullViewingKey, network, &self.to_bytes())
+    }
+
+    /// Load the keys from their Bech32m encoding. Fails if the string does not
+    /// encode a full viewing key for `network`, or if the keys do not belong
+    /// together.
+    pub fn from_bech32(value: &str, network: Network) -> Result<Self, IronfishError> {
+        let bytes = decode_expected(value, KeyType::FullViewingKey, network)?;
+        Self::read(&bytes[..])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode_bech32, Bech32Key, KeyType, Network};
+    use crate::{
+        errors::IronfishErrorKind, FullViewingKey, IncomingViewKey, OutgoingViewKey, PublicAddress,
+        SaplingKey, ViewKey,
+    };
+
+    #[test]
+    fn test_round_trip() {
+        let key = SaplingKey::generate_key();
+
+        for network in [Network::Mainnet, Network::Testnet, Network::Devnet] {
+            let encoded = key.to_bech32(network);
+            let decoded = SaplingKey::from_bech32(&encoded, network).unwrap();
+            assert_eq!(decoded.spending_key(), key.spending_key());
+
+            let encoded = key.view_key().to_bech32(network);
+            let decoded = ViewKey::from_bech32(&encoded, network).unwrap();
+            assert_eq!(decoded.to_bytes(), key.view_key().to_bytes());
+
+            let encoded = key.incoming_view_key().to_bech32(network);
+            let decoded = IncomingViewKey::from_bech32(&encoded, network).unwrap();
+            assert_eq!(decoded.hex_key(), key.incoming_view_key().hex_key());
+
+            let encoded = key.outgoing_view_key().to_bech32(network);
+            let decoded = OutgoingViewKey::from_bech32(&encoded, network).unwrap();
+            assert_eq!(decoded.hex_key(), key.outgoing_view_key().hex_key());
+
+            let encoded = key.public_address().to_bech32(network);
+            let decoded = PublicAddress::from_bech32(&encoded, network).unwrap();
+            assert_eq!(decoded, key.public_address());
+
+            let encoded = key.full_viewing_key().to_bech32(network);
+            let decoded = FullViewingKey::from_bech32(&encoded, network).unwrap();
+            assert_eq!(decoded.to_bytes(), key.full_viewing_key().to_bytes());
+        }
+    }
+
+    #[test]
+    fn test_matches_byte_format() {
+        let address = PublicAddress::from_hex(
+            "8a4685307f159e95418a0dd3d38a3245f488c1baf64bc914f53486efd370c563",
+        )
+        .unwrap();
+
+        let encoded = address.to_bech32(Network::Mainnet);
+        assert!(encoded.starts_with("ifaddr1"));
+
+        let (prefix, data, variant) = bech32::decode(&encoded).unwrap();
+        assert_eq!(prefix, "ifaddr");
+        assert_eq!(variant, bech32::Variant::Bech32m);
+        assert_eq!(
+            <Vec<u8> as bech32::FromBase32>::from_base32(&data).unwrap(),
+            address.public_address()
+        );
+    }
+
+    #[test]
+    fn test_human_readable_prefixes_are_unique() {
+        let key_types = [
+            KeyType::SpendingKey,
+            KeyType::ViewKey,
+            KeyType::IncomingViewKey,
+            KeyType::OutgoingViewKey,
+            KeyType::PublicAddress,
+            KeyType::FullViewingKey,
+        ];
+
+        for key_type in key_types {
+            for network in [Network::Mainnet, Network::Testnet, Network::Devnet] {
+                let prefix = key_type.human_readable_prefix(network);
+                assert_eq!(
+                    KeyType::from_human_readable_prefix(prefix),
+                    Some((key_type, network))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_reports_key_type() {
+        let key = SaplingKey::generate_key();
+
+        let encoded = key.incoming_view_key().to_bech32(Network::Testnet);
+        let (network, decoded) = decode_bech32(&encoded).unwrap();
+        assert_eq!(network, Network::Testnet);
+        assert_eq!(decoded.key_type(), KeyType::IncomingViewKey);
+        assert!(matches!(decoded, Bech32Key::IncomingViewKey(ivk)
+            if ivk.hex_key() == key.incoming_view_key().hex_key()));
+
+        let encoded = key.to_bech32(Network::Devnet);
+        let (network, decoded) = decode_bech32(&encoded).unwrap();
+        assert_eq!(network, Network::Devnet);
+        assert_eq!(decoded.key_type(), KeyType::SpendingKey);
+    }
+
+    #[test]
+    fn test_wrong_type_or_network() {
+        let key = SaplingKey::generate_key();
+        let encoded = key.incoming_view_key().to_bech32(Network::Mainnet);
+
+        assert!(matches!(
+            PublicAddress::from_bech32(&encoded, Network::Mainnet),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidBech32)
+        ));
+        assert!(matches!(
+            IncomingViewKey::from_bech32(&encoded, Network::Testnet),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidBech32)
+        ));
+    }
+
+    #[test]
+    fn test_typo_is_detected() {
+        let key = SaplingKey::generate_key();
+        let encoded = key.public_address().to_bech32(Network::Mainnet);
+
+        // Change a single character of the payload
+        let mut typo: Vec<char> = encoded.chars().collect();
+        let position = typo.len() / 2;
+        typo[position] = if typo[position] == 'q' { 'p' } else { 'q' };
+        let typo: String = typo.into_iter().collect();
+
+        assert!(matches!(
+            PublicAddress::from_bech32(&typo, Network::Mainnet),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidBech32)
+        ));
+    }
+
+    #[test]
+    fn test_rejects_bech32_variant() {
+        let key = SaplingKey::generate_key();
+        let encoded = bech32::encode(
+            "ifaddr",
+            bech32::ToBase32::to_base32(&key.public_address().public_address()),
+            bech32::Variant::Bech32,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            decode_bech32(&encoded),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidBech32)
+        ));
+    }
+}
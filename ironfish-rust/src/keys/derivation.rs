@@ -0,0 +1,194 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Hierarchical deterministic derivation of spending keys, in the spirit of
+//! ZIP-32, so that any number of accounts can be recovered from a single
+//! mnemonic.
+//!
+//! * The master key is derived from a BIP39 seed (the PBKDF2 stretch of a
+//!   mnemonic and optional passphrase):
+//!   `sk_m = BLAKE2b-256("IronFishHDMaster", seed)`
+//! * Child keys are derived with hardened derivation only:
+//!   `sk_i = BLAKE2b-256("IronFishHDChild_", sk_parent || LE32(i + 2^31))`
+//!
+//! Since only hardened derivation is supported, the parent spending key is
+//! secret and is used directly as the key of the PRF, instead of a separate
+//! chain code. Any [`SaplingKey`] can be used as a parent, including keys
+//! that were not themselves derived from a seed.
+//!
+//! Test vectors (spending keys as hex), for the mnemonic made of 23 times
+//! `abandon` followed by `art`:
+//!
+//! | passphrase | path      | spending key                                                       |
+//! |------------|-----------|--------------------------------------------------------------------|
+//! | (empty)    | `m`       | `9eee86a92cece0e2f9074d61055fcf68d18c545b4de283b4257e36c24a7031a3` |
+//! | (empty)    | `m/0'`    | `97cbe62d57f1a6a057c5148f343ccca9f7852619f5d7d7583476149f54f1d5ed` |
+//! | (empty)    | `m/1'`    | `688446b5d60d434bf7629eb5a17502b4376f921fdddd4440c428c342af6d6186` |
+//! | (empty)    | `m/0'/5'` | `b9de813343728f4b6639c91cc77af7d85244419df32e88b4330fbc0a2fe9c40d` |
+//! | `TREZOR`   | `m`       | `c45f16eb285cf32dffe58b6d36c095d99c4ab9fab52c9e4ee8931f6ca57f7551` |
+//! | `TREZOR`   | `m/0'`    | `ea3217fbac98574d30f2129f7aeecae5bc5c44c991c33fc3eb786076ddebfafe` |
+//! | `TREZOR`   | `m/1'`    | `2d7461e50435adfc4bc1ad93ea83a5f3594e770d62e8e34879c18cfd37662c35` |
+//! | `TREZOR`   | `m/0'/5'` | `249b8d0c21ed748cf7066f9c8760f0b8ebdc662c56d247c52a16533f99628815` |
+
+use bip39::{Language, Mnemonic, Seed};
+use blake2b_simd::Params as Blake2b;
+
+use super::{SaplingKey, SPEND_KEY_SIZE};
+use crate::errors::{IronfishError, IronfishErrorKind};
+
+const MASTER_KEY_PERSONALIZATION: &[u8; 16] = b"IronFishHDMaster";
+const CHILD_KEY_PERSONALIZATION: &[u8; 16] = b"IronFishHDChild_";
+
+/// Offset added to child indexes to mark them as hardened, as in BIP32
+pub const HARDENED_KEY_OFFSET: u32 = 1 << 31;
+
+impl SaplingKey {
+    /// Derive the master key from a BIP39 seed.
+    pub fn from_seed(seed: &[u8]) -> Result<Self, IronfishError> {
+        let hash = Blake2b::new()
+            .hash_length(SPEND_KEY_SIZE)
+            .personal(MASTER_KEY_PERSONALIZATION)
+            .hash(seed);
+
+        let mut spending_key = [0; SPEND_KEY_SIZE];
+        spending_key.copy_from_slice(hash.as_bytes());
+        Self::new(spending_key)
+    }
+
+    /// Derive the master key from a bip-39 phrase and an optional passphrase
+    /// (use an empty string for no passphrase).
+    ///
+    /// Unlike [`SaplingKey::from_words`], which uses the entropy of the phrase
+    /// directly as the spending key, this stretches the phrase into a BIP39
+    /// seed, so phrases of any length are accepted.
+    pub fn from_mnemonic_seed(
+        words: &str,
+        passphrase: &str,
+        language: Language,
+    ) -> Result<Self, IronfishError> {
+        let mnemonic = Mnemonic::from_phrase(words, language)
+            .map_err(|_| IronfishError::new(IronfishErrorKind::InvalidMnemonicString))?;
+        let seed = Seed::new(&mnemonic, passphrase);
+        Self::from_seed(seed.as_bytes())
+    }
+
+    /// Derive the hardened child key at `index`, for example the key of
+    /// account `index`. `index` must be lower than [`HARDENED_KEY_OFFSET`].
+    pub fn derive_child(&self, index: u32) -> Result<Self, IronfishError> {
+        if index >= HARDENED_KEY_OFFSET {
+            return Err(IronfishError::new(IronfishErrorKind::IllegalValue));
+        }
+
+        let hash = Blake2b::new()
+            .hash_length(SPEND_KEY_SIZE)
+            .personal(CHILD_KEY_PERSONALIZATION)
+            .to_state()
+            .update(&self.spending_key)
+            .update(&(index + HARDENED_KEY_OFFSET).to_le_bytes())
+            .finalize();
+
+        let mut spending_key = [0; SPEND_KEY_SIZE];
+        spending_key.copy_from_slice(hash.as_bytes());
+        Self::new(spending_key)
+    }
+
+    /// Derive the key at the given path of hardened indexes, so that
+    /// `&[0, 5]` is the key at `m/0'/5'` if this is the master key.
+    pub fn derive_path(&self, path: &[u32]) -> Result<Self, IronfishError> {
+        path.iter()
+            .try_fold(self.clone(), |key, index| key.derive_child(*index))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::HARDENED_KEY_OFFSET;
+    use crate::{errors::IronfishErrorKind, keys::Language, SaplingKey};
+
+    const MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon \
+        abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+        abandon abandon abandon abandon abandon art";
+
+    #[test]
+    fn test_vectors() {
+        let vectors: [(&str, &[u32], &str); 8] = [
+            (
+                "",
+                &[],
+                "9eee86a92cece0e2f9074d61055fcf68d18c545b4de283b4257e36c24a7031a3",
+            ),
+            (
+                "",
+                &[0],
+                "97cbe62d57f1a6a057c5148f343ccca9f7852619f5d7d7583476149f54f1d5ed",
+            ),
+            (
+                "",
+                &[1],
+                "688446b5d60d434bf7629eb5a17502b4376f921fdddd4440c428c342af6d6186",
+            ),
+            (
+                "",
+                &[0, 5],
+                "b9de813343728f4b6639c91cc77af7d85244419df32e88b4330fbc0a2fe9c40d",
+            ),
+            (
+                "TREZOR",
+                &[],
+                "c45f16eb285cf32dffe58b6d36c095d99c4ab9fab52c9e4ee8931f6ca57f7551",
+            ),
+            (
+                "TREZOR",
+                &[0],
+                "ea3217fbac98574d30f2129f7aeecae5bc5c44c991c33fc3eb786076ddebfafe",
+            ),
+            (
+                "TREZOR",
+                &[1],
+                "2d7461e50435adfc4bc1ad93ea83a5f3594e770d62e8e34879c18cfd37662c35",
+            ),
+            (
+                "TREZOR",
+                &[0, 5],
+                "249b8d0c21ed748cf7066f9c8760f0b8ebdc662c56d247c52a16533f99628815",
+            ),
+        ];
+
+        for (passphrase, path, expected) in vectors {
+            let master =
+                SaplingKey::from_mnemonic_seed(MNEMONIC, passphrase, Language::English).unwrap();
+            let key = master.derive_path(path).unwrap();
+            assert_eq!(key.hex_spending_key(), expected);
+        }
+    }
+
+    #[test]
+    fn test_derive_child() {
+        let master = SaplingKey::generate_key();
+
+        let child = master.derive_child(3).unwrap();
+        assert_eq!(
+            child.hex_spending_key(),
+            master.derive_path(&[3]).unwrap().hex_spending_key()
+        );
+        assert_ne!(child.hex_spending_key(), master.hex_spending_key());
+        assert_ne!(
+            child.hex_spending_key(),
+            master.derive_child(4).unwrap().hex_spending_key()
+        );
+
+        assert!(matches!(
+            master.derive_child(HARDENED_KEY_OFFSET),
+            Err(e) if matches!(e.kind, IronfishErrorKind::IllegalValue)
+        ));
+    }
+
+    #[test]
+    fn test_invalid_mnemonic() {
+        assert!(matches!(
+            SaplingKey::from_mnemonic_seed("abandon abandon", "", Language::English),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidMnemonicString)
+        ));
+    }
+}
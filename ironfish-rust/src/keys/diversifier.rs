@@ -0,0 +1,455 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Diversified public addresses.
+//!
+//! A single [`SaplingKey`] can receive funds on many addresses that cannot be
+//! linked to each other by anyone without the incoming view key, so that a
+//! different address can be handed out for every payment. A
+//! [`DiversifiedAddress`] has two parts:
+//!
+//! * The owner of the notes sent to it. The spend circuit requires the owner
+//!   of a note to be `PUBLIC_KEY_GENERATOR * ivk`, where `ivk` is the hash of
+//!   the authorizing key `ak` and of the nullifier deriving key `nk`. The
+//!   owner of the address at index `i` is derived from `ak` and
+//!   `nk + t_i * PROOF_GENERATION_KEY_GENERATOR`, where `t_i` is a hash of
+//!   the view key and of `i`, and its notes are spent with the proof
+//!   authorizing key `nsk + t_i`.
+//! * A diversifier `d`, which is `i` encrypted with FF1 under a key derived
+//!   from the incoming view key of the account, and the transmission key
+//!   `pk_d = ivk * g_d`, where `g_d` is hashed from `d` as in Sapling.
+//!
+//! Since [`TransactionVersion::V3`](crate::transaction::TransactionVersion::V3),
+//! notes are encrypted with an ephemeral key `esk_d * g_d` and the shared
+//! secret `esk_d * pk_d`. The incoming view key of the account computes the same
+//! secret with a single Diffie-Hellman per note, whichever address received
+//! it, and recovers the index of the address from the encrypted diversifier.
+//!
+//! The address at index 0 is the regular public address of the key, with a
+//! diversifier of all zeros and `g_d = PUBLIC_KEY_GENERATOR`.
+//!
+//! Unlike the transmission keys, the owners are derived from the [`ViewKey`]
+//! and not from the incoming view key alone. The spend circuit recomputes
+//! `ivk` as a hash of `ak` and `nk`, and requires the owner of the note to be
+//! `PUBLIC_KEY_GENERATOR * ivk`, so the owner of every address must be the
+//! public address of a view key `(ak, nk_i)`, which cannot be computed from
+//! `ivk`. Deriving owners from the incoming view key needs new spend and
+//! output circuits and parameters that check `pk_d = ivk * g_d` directly.
+//!
+//! As a result, an incoming view key detects and decrypts the notes sent to
+//! any address of the account, but only the view key can check that the
+//! owner sent along with a note is the owner of one of its addresses, see
+//! [`MerkleNote::decrypt_note_for_owner`](crate::MerkleNote::decrypt_note_for_owner).
+
+use aes::Aes256;
+use blake2b_simd::Params as Blake2b;
+use fpe::ff1::{BinaryNumeralString, FF1};
+use group::GroupEncoding;
+use ironfish_zkp::{
+    constants::{PROOF_GENERATION_KEY_GENERATOR, PUBLIC_KEY_GENERATOR},
+    group_hash, ProofGenerationKey,
+};
+use jubjub::SubgroupPoint;
+
+use std::io;
+
+use super::{IncomingViewKey, PublicAddress, SaplingKey, ViewKey, PUBLIC_ADDRESS_SIZE};
+use crate::errors::{IronfishError, IronfishErrorKind};
+
+const DIVERSIFIER_PERSONALIZATION: &[u8; 16] = b"Iron Fish Divers";
+const DIVERSIFIER_KEY_PERSONALIZATION: &[u8; 16] = b"Iron Fish DivKey";
+const DIVERSIFIED_BASE_PERSONALIZATION: &[u8; 8] = b"IronF_gd";
+
+pub const DIVERSIFIER_SIZE: usize = 11;
+
+/// Size of a serialized [`DiversifiedAddress`]: owner address, diversifier
+/// and transmission key.
+pub const DIVERSIFIED_ADDRESS_SIZE: usize = PUBLIC_ADDRESS_SIZE + DIVERSIFIER_SIZE + 32;
+
+/// Offset `t_i` applied to the nullifier deriving key of the address at
+/// `index`. The offset is zero for index 0, so that the address at index 0 is
+/// the regular public address.
+fn diversifier_offset(view_key: &ViewKey, index: u64) -> jubjub::Fr {
+    if index == 0 {
+        return jubjub::Fr::zero();
+    }
+
+    let hash = Blake2b::new()
+        .hash_length(64)
+        .personal(DIVERSIFIER_PERSONALIZATION)
+        .to_state()
+        .update(&view_key.authorizing_key.to_bytes())
+        .update(&view_key.nullifier_deriving_key.to_bytes())
+        .update(&index.to_le_bytes())
+        .finalize();
+
+    jubjub::Fr::from_bytes_wide(hash.as_array())
+}
+
+/// Diversified base `g_d` of `diversifier`, or `None` if the diversifier does
+/// not hash to a valid point. The zero diversifier is the one of the regular
+/// public address.
+pub(crate) fn diversified_base(diversifier: &[u8; DIVERSIFIER_SIZE]) -> Option<SubgroupPoint> {
+    if diversifier == &[0; DIVERSIFIER_SIZE] {
+        return Some(*PUBLIC_KEY_GENERATOR);
+    }
+
+    group_hash(diversifier, DIVERSIFIED_BASE_PERSONALIZATION)
+}
+
+impl IncomingViewKey {
+    /// Cipher that maps the indexes of diversified addresses to their
+    /// diversifiers
+    fn diversifier_cipher(&self) -> FF1<Aes256> {
+        let key = Blake2b::new()
+            .hash_length(32)
+            .personal(DIVERSIFIER_KEY_PERSONALIZATION)
+            .hash(&self.view_key.to_bytes());
+
+        FF1::<Aes256>::new(key.as_bytes(), 2).expect("radix 2 is valid")
+    }
+
+    /// Diversifier of the address at `index`.
+    ///
+    /// About half of the diversifiers do not hash to a valid base, so there is
+    /// no address at the corresponding indexes. This fails with
+    /// [`IronfishErrorKind::InvalidDiversificationPoint`] for those indexes,
+    /// see [`ViewKey::next_diversified_address`].
+    pub fn diversifier(&self, index: u64) -> Result<[u8; DIVERSIFIER_SIZE], IronfishError> {
+        if index == 0 {
+            return Ok([0; DIVERSIFIER_SIZE]);
+        }
+
+        let mut index_bytes = [0; DIVERSIFIER_SIZE];
+        index_bytes[..8].copy_from_slice(&index.to_le_bytes());
+
+        let encrypted = self
+            .diversifier_cipher()
+            .encrypt(&[], &BinaryNumeralString::from_bytes_le(&index_bytes))
+            .expect("the index is a valid binary string");
+        let mut diversifier = [0; DIVERSIFIER_SIZE];
+        diversifier.copy_from_slice(&encrypted.to_bytes_le());
+
+        // The zero diversifier is reserved for the regular public address
+        if diversifier == [0; DIVERSIFIER_SIZE] || diversified_base(&diversifier).is_none() {
+            return Err(IronfishError::new(
+                IronfishErrorKind::InvalidDiversificationPoint,
+            ));
+        }
+
+        Ok(diversifier)
+    }
+
+    /// Index of the address with the given diversifier, or `None` if the
+    /// diversifier was not derived from this key
+    pub fn diversifier_index(&self, diversifier: &[u8; DIVERSIFIER_SIZE]) -> Option<u64> {
+        if diversifier == &[0; DIVERSIFIER_SIZE] {
+            return Some(0);
+        }
+
+        let decrypted = self
+            .diversifier_cipher()
+            .decrypt(&[], &BinaryNumeralString::from_bytes_le(diversifier))
+            .ok()?
+            .to_bytes_le();
+
+        // Indexes are 64 bits long, so the remaining bits must be zero
+        if decrypted[8..].iter().any(|byte| *byte != 0) {
+            return None;
+        }
+
+        let mut index_bytes = [0; 8];
+        index_bytes.copy_from_slice(&decrypted[..8]);
+        match u64::from_le_bytes(index_bytes) {
+            0 => None,
+            index => Some(index),
+        }
+    }
+
+    /// Diversifier and transmission key `pk_d = ivk * g_d` of the address at
+    /// `index`
+    pub fn diversified_transmission_key(
+        &self,
+        index: u64,
+    ) -> Result<([u8; DIVERSIFIER_SIZE], SubgroupPoint), IronfishError> {
+        let diversifier = self.diversifier(index)?;
+        let base = diversified_base(&diversifier)
+            .ok_or_else(|| IronfishError::new(IronfishErrorKind::InvalidDiversificationPoint))?;
+
+        Ok((diversifier, base * self.view_key))
+    }
+}
+
+impl ViewKey {
+    /// View key of the owner of the diversified address at `index`.
+    /// Nullifiers of notes received on that address are derived with this
+    /// key.
+    pub fn diversified(&self, index: u64) -> ViewKey {
+        let offset = diversifier_offset(self, index);
+
+        ViewKey {
+            authorizing_key: self.authorizing_key,
+            nullifier_deriving_key: self.nullifier_deriving_key
+                + *PROOF_GENERATION_KEY_GENERATOR * offset,
+        }
+    }
+
+    /// Owner of the notes sent to the diversified address at `index`. The
+    /// owner at index 0 is the same as [`ViewKey::public_address`].
+    pub fn diversified_owner(&self, index: u64) -> Result<PublicAddress, IronfishError> {
+        self.diversified(index).public_address()
+    }
+
+    /// Diversified address at `index`. Fails with
+    /// [`IronfishErrorKind::InvalidDiversificationPoint`] if there is no
+    /// address at that index, see [`IncomingViewKey::diversifier`].
+    pub fn diversified_address(&self, index: u64) -> Result<DiversifiedAddress, IronfishError> {
+        let (diversifier, transmission_key) = self
+            .incoming_view_key()?
+            .diversified_transmission_key(index)?;
+
+        Ok(DiversifiedAddress {
+            owner: self.diversified_owner(index)?,
+            diversifier,
+            transmission_key,
+        })
+    }
+
+    /// First diversified address at `index` or after it, along with its index
+    pub fn next_diversified_address(
+        &self,
+        index: u64,
+    ) -> Result<(u64, DiversifiedAddress), IronfishError> {
+        for index in index..=u64::MAX {
+            match self.diversified_address(index) {
+                Ok(address) => return Ok((index, address)),
+                Err(e) if e.kind == IronfishErrorKind::InvalidDiversificationPoint => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(IronfishError::new(
+            IronfishErrorKind::InvalidDiversificationPoint,
+        ))
+    }
+}
+
+impl SaplingKey {
+    /// Diversified address at `index`, see [`ViewKey::diversified_address`]
+    pub fn diversified_address(&self, index: u64) -> Result<DiversifiedAddress, IronfishError> {
+        self.view_key.diversified_address(index)
+    }
+}
+
+/// Proof generation key used to spend notes received on the diversified
+/// address at `index`, given the proof generation key and view key of the
+/// account.
+pub(crate) fn diversified_proof_generation_key(
+    proof_generation_key: &ProofGenerationKey,
+    view_key: &ViewKey,
+    index: u64,
+) -> ProofGenerationKey {
+    ProofGenerationKey {
+        ak: proof_generation_key.ak,
+        nsk: proof_generation_key.nsk + diversifier_offset(view_key, index),
+    }
+}
+
+/// An address that can be handed out to receive a single payment. Notes sent
+/// to it are owned by [`DiversifiedAddress::owner`], and can only be sent in
+/// transactions of a version that has
+/// [`has_diversified_notes`](crate::transaction::TransactionVersion::has_diversified_notes).
+///
+/// A [`PublicAddress`] converts to the diversified address at index 0.
+#[derive(Clone, Copy)]
+pub struct DiversifiedAddress {
+    pub(crate) owner: PublicAddress,
+    pub(crate) diversifier: [u8; DIVERSIFIER_SIZE],
+    pub(crate) transmission_key: SubgroupPoint,
+}
+
+impl DiversifiedAddress {
+    /// Owner of the notes sent to this address
+    pub fn owner(&self) -> PublicAddress {
+        self.owner
+    }
+
+    pub fn diversifier(&self) -> [u8; DIVERSIFIER_SIZE] {
+        self.diversifier
+    }
+
+    /// Base `g_d` of the transmission key
+    pub(crate) fn diversified_base(&self) -> SubgroupPoint {
+        diversified_base(&self.diversifier).expect("diversifiers are checked when created")
+    }
+
+    /// Load an address stored with [`DiversifiedAddress::write`]
+    pub fn read<R: io::Read>(mut reader: R) -> Result<Self, IronfishError> {
+        let owner = PublicAddress::read(&mut reader)?;
+
+        let mut diversifier = [0; DIVERSIFIER_SIZE];
+        reader.read_exact(&mut diversifier)?;
+        if diversified_base(&diversifier).is_none() {
+            return Err(IronfishError::new(
+                IronfishErrorKind::InvalidDiversificationPoint,
+            ));
+        }
+
+        let transmission_key = PublicAddress::read(&mut reader)?.0;
+
+        Ok(DiversifiedAddress {
+            owner,
+            diversifier,
+            transmission_key,
+        })
+    }
+
+    pub fn write<W: io::Write>(&self, mut writer: W) -> Result<(), IronfishError> {
+        self.owner.write(&mut writer)?;
+        writer.write_all(&self.diversifier)?;
+        writer.write_all(&self.transmission_key.to_bytes())?;
+
+        Ok(())
+    }
+}
+
+impl From<PublicAddress> for DiversifiedAddress {
+    fn from(address: PublicAddress) -> Self {
+        DiversifiedAddress {
+            owner: address,
+            diversifier: [0; DIVERSIFIER_SIZE],
+            transmission_key: address.0,
+        }
+    }
+}
+
+impl std::fmt::Debug for DiversifiedAddress {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter
+            .debug_struct("DiversifiedAddress")
+            .field("owner", &self.owner)
+            .field("diversifier", &self.diversifier)
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for DiversifiedAddress {
+    fn eq(&self, other: &Self) -> bool {
+        self.owner == other.owner
+            && self.diversifier == other.diversifier
+            && self.transmission_key == other.transmission_key
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{diversified_proof_generation_key, DiversifiedAddress, DIVERSIFIED_ADDRESS_SIZE};
+    use crate::{errors::IronfishErrorKind, SaplingKey};
+    use ironfish_zkp::constants::PROOF_GENERATION_KEY_GENERATOR;
+
+    #[test]
+    fn test_diversified_addresses() {
+        let key = SaplingKey::generate_key();
+
+        let regular = key.diversified_address(0).unwrap();
+        assert_eq!(regular, DiversifiedAddress::from(key.public_address()));
+
+        let mut addresses = vec![regular];
+        let mut index = 1;
+        while addresses.len() < 5 {
+            let (next_index, address) = key.view_key().next_diversified_address(index).unwrap();
+            assert_eq!(key.diversified_address(next_index).unwrap(), address);
+            addresses.push(address);
+            index = next_index + 1;
+        }
+
+        for (i, a) in addresses.iter().enumerate() {
+            for b in addresses[i + 1..].iter() {
+                assert_ne!(a.owner, b.owner);
+                assert_ne!(a.diversifier, b.diversifier);
+                assert_ne!(a.transmission_key, b.transmission_key);
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_diversifiers() {
+        let key = SaplingKey::generate_key();
+
+        // About half of the indexes have no address
+        let invalid = (1..64)
+            .filter(|index| key.diversified_address(*index).is_err())
+            .count();
+        assert!(invalid > 0 && invalid < 63);
+
+        let (index, _) = key.view_key().next_diversified_address(1).unwrap();
+        for skipped in 1..index {
+            assert!(matches!(
+                key.diversified_address(skipped),
+                Err(e) if e.kind == IronfishErrorKind::InvalidDiversificationPoint
+            ));
+        }
+    }
+
+    #[test]
+    fn test_diversifier_index() {
+        let key = SaplingKey::generate_key();
+        let other_key = SaplingKey::generate_key();
+        let incoming_view_key = key.incoming_view_key();
+
+        let (index, address) = key.view_key().next_diversified_address(1000).unwrap();
+        assert_eq!(
+            incoming_view_key.diversifier_index(&address.diversifier),
+            Some(index)
+        );
+        assert_eq!(incoming_view_key.diversifier_index(&[0; 11]), Some(0));
+        assert_ne!(
+            other_key
+                .incoming_view_key()
+                .diversifier_index(&address.diversifier),
+            Some(index)
+        );
+
+        // The transmission key is derived from the incoming view key alone
+        assert_eq!(
+            incoming_view_key
+                .diversified_transmission_key(index)
+                .unwrap(),
+            (address.diversifier, address.transmission_key)
+        );
+    }
+
+    #[test]
+    fn test_diversified_address_serialization() {
+        let key = SaplingKey::generate_key();
+        let (_, address) = key.view_key().next_diversified_address(1).unwrap();
+
+        let mut serialized = vec![];
+        address.write(&mut serialized).unwrap();
+        assert_eq!(serialized.len(), DIVERSIFIED_ADDRESS_SIZE);
+
+        let deserialized = DiversifiedAddress::read(&serialized[..]).unwrap();
+        assert_eq!(deserialized, address);
+    }
+
+    #[test]
+    fn test_diversified_proof_generation_key() {
+        let key = SaplingKey::generate_key();
+        let proof_generation_key = key.sapling_proof_generation_key();
+
+        let diversified =
+            diversified_proof_generation_key(&proof_generation_key, key.view_key(), 7);
+        let view_key = key.view_key().diversified(7);
+
+        assert_eq!(diversified.ak, view_key.authorizing_key);
+        assert_eq!(
+            *PROOF_GENERATION_KEY_GENERATOR * diversified.nsk,
+            view_key.nullifier_deriving_key
+        );
+        assert_eq!(
+            view_key.public_address().unwrap(),
+            key.view_key().diversified_owner(7).unwrap()
+        );
+    }
+}
@@ -0,0 +1,234 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::io;
+
+use super::{IncomingViewKey, OutgoingViewKey, PublicAddress, SaplingKey, ViewKey};
+use crate::{
+    errors::{IronfishError, IronfishErrorKind},
+    serializing::{bytes_to_hex, hex_to_bytes},
+};
+
+/// Size of a serialized [`FullViewingKey`]: view key, incoming view key,
+/// outgoing view key and public address.
+pub const FULL_VIEWING_KEY_SIZE: usize = 64 + 32 + 32 + 32;
+
+/// All the keys needed to read the transactions of an account without being
+/// able to spend its notes, for example to give to an auditor or to a
+/// watch-only node.
+///
+/// The keys are checked to belong together when the bundle is constructed or
+/// loaded: the incoming view key must be derived from the view key, and the
+/// public address from the incoming view key.
+#[derive(Clone)]
+pub struct FullViewingKey {
+    view_key: ViewKey,
+    incoming_view_key: IncomingViewKey,
+    outgoing_view_key: OutgoingViewKey,
+    public_address: PublicAddress,
+}
+
+impl FullViewingKey {
+    /// Create the bundle from a view key and outgoing view key, deriving the
+    /// incoming view key and public address.
+    pub fn new(
+        view_key: ViewKey,
+        outgoing_view_key: OutgoingViewKey,
+    ) -> Result<Self, IronfishError> {
+        let incoming_view_key = IncomingViewKey {
+            view_key: SaplingKey::hash_viewing_key(
+                &view_key.authorizing_key,
+                &view_key.nullifier_deriving_key,
+            )?,
+        };
+        let public_address = incoming_view_key.public_address();
+
+        Ok(FullViewingKey {
+            view_key,
+            incoming_view_key,
+            outgoing_view_key,
+            public_address,
+        })
+    }
+
+    /// Create the bundle from separately stored keys, checking that they
+    /// belong to the same account.
+    pub fn from_parts(
+        view_key: ViewKey,
+        incoming_view_key: IncomingViewKey,
+        outgoing_view_key: OutgoingViewKey,
+        public_address: PublicAddress,
+    ) -> Result<Self, IronfishError> {
+        let full_viewing_key = Self::new(view_key, outgoing_view_key)?;
+
+        if full_viewing_key.incoming_view_key.view_key != incoming_view_key.view_key {
+            return Err(IronfishError::new(IronfishErrorKind::InvalidViewingKey));
+        }
+        if full_viewing_key.public_address != public_address {
+            return Err(IronfishError::new(IronfishErrorKind::InvalidPublicAddress));
+        }
+
+        Ok(full_viewing_key)
+    }
+
+    /// Watch-only keys of the given spending key
+    pub fn from_key(key: &SaplingKey) -> Self {
+        FullViewingKey {
+            view_key: key.view_key().clone(),
+            incoming_view_key: key.incoming_view_key().clone(),
+            outgoing_view_key: key.outgoing_view_key().clone(),
+            public_address: key.public_address(),
+        }
+    }
+
+    pub fn view_key(&self) -> &ViewKey {
+        &self.view_key
+    }
+
+    pub fn incoming_view_key(&self) -> &IncomingViewKey {
+        &self.incoming_view_key
+    }
+
+    pub fn outgoing_view_key(&self) -> &OutgoingViewKey {
+        &self.outgoing_view_key
+    }
+
+    pub fn public_address(&self) -> PublicAddress {
+        self.public_address
+    }
+
+    /// Load the bundle from a Read implementation, checking that the keys
+    /// belong together.
+    pub fn read<R: io::Read>(mut reader: R) -> Result<Self, IronfishError> {
+        let mut view_key_bytes = [0; 64];
+        reader.read_exact(&mut view_key_bytes)?;
+        let view_key = ViewKey::from_bytes(&view_key_bytes)?;
+
+        let incoming_view_key = IncomingViewKey::read(&mut reader)?;
+
+        let mut outgoing_view_key = [0; 32];
+        reader.read_exact(&mut outgoing_view_key)?;
+        let outgoing_view_key = OutgoingViewKey {
+            view_key: outgoing_view_key,
+        };
+
+        let public_address = PublicAddress::read(&mut reader)?;
+
+        Self::from_parts(
+            view_key,
+            incoming_view_key,
+            outgoing_view_key,
+            public_address,
+        )
+    }
+
+    pub fn write<W: io::Write>(&self, mut writer: W) -> Result<(), IronfishError> {
+        writer.write_all(&self.view_key.to_bytes())?;
+        writer.write_all(&self.incoming_view_key.view_key.to_bytes())?;
+        writer.write_all(&self.outgoing_view_key.view_key)?;
+        self.public_address.write(&mut writer)?;
+
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> [u8; FULL_VIEWING_KEY_SIZE] {
+        let mut bytes = [0; FULL_VIEWING_KEY_SIZE];
+        self.write(&mut bytes[..])
+            .expect("the buffer is large enough");
+        bytes
+    }
+
+    /// Load the bundle from a string of hexadecimal digits
+    pub fn from_hex(value: &str) -> Result<Self, IronfishError> {
+        match hex_to_bytes::<FULL_VIEWING_KEY_SIZE>(value) {
+            Err(_) => Err(IronfishError::new(IronfishErrorKind::InvalidViewingKey)),
+            Ok(bytes) => Self::read(&bytes[..]),
+        }
+    }
+
+    /// The bundle as hexadecimal
+    pub fn hex_key(&self) -> String {
+        bytes_to_hex(&self.to_bytes())
+    }
+}
+
+impl SaplingKey {
+    /// Retrieve all the keys needed for watch-only access to the account
+    pub fn full_viewing_key(&self) -> FullViewingKey {
+        FullViewingKey::from_key(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FullViewingKey, FULL_VIEWING_KEY_SIZE};
+    use crate::{errors::IronfishErrorKind, SaplingKey};
+
+    #[test]
+    fn test_round_trip() {
+        let key = SaplingKey::generate_key();
+        let full_viewing_key = key.full_viewing_key();
+
+        let mut serialized = vec![];
+        full_viewing_key.write(&mut serialized).unwrap();
+        assert_eq!(serialized.len(), FULL_VIEWING_KEY_SIZE);
+        assert_eq!(serialized, full_viewing_key.to_bytes());
+
+        let deserialized = FullViewingKey::read(&serialized[..]).unwrap();
+        assert_eq!(
+            deserialized.view_key().to_bytes(),
+            key.view_key().to_bytes()
+        );
+        assert_eq!(
+            deserialized.incoming_view_key().hex_key(),
+            key.incoming_view_key().hex_key()
+        );
+        assert_eq!(
+            deserialized.outgoing_view_key().hex_key(),
+            key.outgoing_view_key().hex_key()
+        );
+        assert_eq!(deserialized.public_address(), key.public_address());
+
+        let from_hex = FullViewingKey::from_hex(&full_viewing_key.hex_key()).unwrap();
+        assert_eq!(from_hex.to_bytes(), full_viewing_key.to_bytes());
+
+        let derived =
+            FullViewingKey::new(key.view_key().clone(), key.outgoing_view_key().clone()).unwrap();
+        assert_eq!(derived.to_bytes(), full_viewing_key.to_bytes());
+    }
+
+    #[test]
+    fn test_inconsistent_keys() {
+        let key = SaplingKey::generate_key();
+        let other_key = SaplingKey::generate_key();
+
+        assert!(matches!(
+            FullViewingKey::from_parts(
+                key.view_key().clone(),
+                other_key.incoming_view_key().clone(),
+                key.outgoing_view_key().clone(),
+                key.public_address(),
+            ),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidViewingKey)
+        ));
+
+        assert!(matches!(
+            FullViewingKey::from_parts(
+                key.view_key().clone(),
+                key.incoming_view_key().clone(),
+                key.outgoing_view_key().clone(),
+                other_key.public_address(),
+            ),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidPublicAddress)
+        ));
+
+        // Replace the public address in a serialized bundle
+        let mut serialized = key.full_viewing_key().to_bytes();
+        serialized[128..].copy_from_slice(&other_key.public_address().public_address());
+        assert!(matches!(
+            FullViewingKey::read(&serialized[..]),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidPublicAddress)
+        ));
+    }
+}
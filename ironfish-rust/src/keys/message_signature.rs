@@ -0,0 +1,497 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Signatures over arbitrary messages, to authenticate an account off-chain.
+//!
+//! A [`MessageSignature`] proves that the signer holds the keys of a public
+//! address without revealing them. It holds a spend proof of a marker note of
+//! zero value owned by the address, proven against a marker Merkle tree as in
+//! [`crate::transaction::reserves`]. The marker note and the marker tree are
+//! derived from a hash of the message and of the address, so the proof can
+//! neither be used in a transaction nor for another message or address.
+//!
+//! The spend proof is signed like the spends of a transaction, with the spend
+//! authorizing key `ask` randomized with a random `ar`, over a hash of the
+//! message and of the proof. It can also be signed by the participants of a
+//! multisig account, see [`UnsignedMessage`].
+//!
+//! The signature only reveals the randomized public key and the nullifier of
+//! the marker note, which cannot be linked to the view keys of the account.
+//! The message hashes use their own personalizations, so that message
+//! signatures can never be mistaken for transaction signatures.
+
+use std::{collections::BTreeMap, io};
+
+use blake2b_simd::Params as Blake2b;
+use ff::Field;
+use group::GroupEncoding;
+use ironfish_frost::{
+    frost::{
+        aggregate, keys::PublicKeyPackage, round1::SigningCommitments, round2::SignatureShare,
+        Identifier, RandomizedParams, Randomizer, SigningPackage as FrostSigningPackage,
+    },
+    participant::Identity,
+};
+use ironfish_zkp::{
+    constants::SPENDING_KEY_GENERATOR,
+    redjubjub::{self, Signature},
+    ProofGenerationKey,
+};
+use rand::thread_rng;
+
+use super::{PublicAddress, SaplingKey, ViewKey};
+use crate::{
+    assets::asset_identifier::NATIVE_ASSET,
+    errors::{IronfishError, IronfishErrorKind},
+    note::{Memo, Note},
+    transaction::{
+        reserves::marker_witness,
+        spends::{
+            SpendBuilder, SpendDescription, UnsignedSpendDescription, SPEND_DESCRIPTION_SIZE,
+        },
+        verify_spend_proof, TRANSACTION_PUBLIC_KEY_SIZE,
+    },
+};
+
+const MESSAGE_HASH_PERSONALIZATION: &[u8; 16] = b"Iron Fish MsgHsh";
+const MARKER_NOTE_PERSONALIZATION: &[u8; 16] = b"Iron Fish MsgNte";
+const MESSAGE_SIGNATURE_PERSONALIZATION: &[u8; 16] = b"Iron Fish MsgSig";
+const MESSAGE_SIGNATURE_VERSION: &[u8; 1] = &[1];
+
+/// Size of a serialized [`MessageSignature`]: randomized public key and
+/// signed spend proof of the marker note.
+pub const MESSAGE_SIGNATURE_SIZE: usize = TRANSACTION_PUBLIC_KEY_SIZE + SPEND_DESCRIPTION_SIZE;
+
+/// Signature of a message by an account
+#[derive(Clone)]
+pub struct MessageSignature {
+    randomized_public_key: redjubjub::PublicKey,
+
+    /// Spend proof of the marker note, signed with the randomized spend
+    /// authorizing key
+    ownership_proof: SpendDescription,
+}
+
+/// Key that a message signature is verified against
+pub enum MessageSigner<'a> {
+    PublicAddress(&'a PublicAddress),
+    ViewKey(&'a ViewKey),
+}
+
+impl<'a> From<&'a PublicAddress> for MessageSigner<'a> {
+    fn from(public_address: &'a PublicAddress) -> Self {
+        MessageSigner::PublicAddress(public_address)
+    }
+}
+
+impl<'a> From<&'a ViewKey> for MessageSigner<'a> {
+    fn from(view_key: &'a ViewKey) -> Self {
+        MessageSigner::ViewKey(view_key)
+    }
+}
+
+/// A message signature that still needs its spend signature, used to sign
+/// messages with multisig accounts created with
+/// [`crate::frost_utils::split_spender_key::split_spender_key`].
+///
+/// The coordinator creates the [`UnsignedMessage`], which needs the proof
+/// authorizing key of the account, and a signing package from the
+/// commitments of the participants. Each participant signs the package with
+/// the randomizer returned by [`UnsignedMessage::public_key_randomness`], and
+/// the coordinator aggregates the signature shares.
+#[derive(Clone)]
+pub struct UnsignedMessage {
+    public_key_randomness: jubjub::Fr,
+    randomized_public_key: redjubjub::PublicKey,
+    ownership_proof: UnsignedSpendDescription,
+    signing_hash: [u8; 32],
+}
+
+impl UnsignedMessage {
+    /// Prove that the account with `view_key` owns the marker note of
+    /// `message`. Fails if `proof_authorizing_key` does not belong to the
+    /// account.
+    pub fn new(
+        proof_authorizing_key: jubjub::Fr,
+        view_key: &ViewKey,
+        message: &[u8],
+    ) -> Result<Self, IronfishError> {
+        let public_address = view_key.public_address()?;
+        let message_hash = hash_message(&public_address, message);
+
+        let public_key_randomness = jubjub::Fr::random(thread_rng());
+        let randomized_public_key = redjubjub::PublicKey(view_key.authorizing_key.into())
+            .randomize(public_key_randomness, *SPENDING_KEY_GENERATOR);
+
+        let proof_generation_key = ProofGenerationKey {
+            ak: view_key.authorizing_key,
+            nsk: proof_authorizing_key,
+        };
+
+        let note = marker_note(&public_address, &message_hash);
+        let marker_witness = marker_witness(&message_hash, 0, note.commitment_point());
+        let ownership_proof = SpendBuilder::new(note, &marker_witness).build(
+            &proof_generation_key,
+            view_key,
+            &public_key_randomness,
+            &randomized_public_key,
+        )?;
+
+        let signing_hash = hash_signature(&message_hash, &ownership_proof.description)?;
+
+        Ok(UnsignedMessage {
+            public_key_randomness,
+            randomized_public_key,
+            ownership_proof,
+            signing_hash,
+        })
+    }
+
+    /// Randomness applied to the spend authorizing key, used as the
+    /// randomizer of the FROST signature shares
+    pub fn public_key_randomness(&self) -> jubjub::Fr {
+        self.public_key_randomness
+    }
+
+    /// Data to be signed by the participants of a multisig account
+    pub fn signing_hash(&self) -> [u8; 32] {
+        self.signing_hash
+    }
+
+    /// Create the FROST signing package from the commitments of the signers
+    pub fn signing_package<Iter>(&self, commitments: Iter) -> FrostSigningPackage
+    where
+        Iter: IntoIterator<Item = (Identity, SigningCommitments)>,
+    {
+        let commitments_map = commitments
+            .into_iter()
+            .map(|(identity, commitments)| (identity.to_frost_identifier(), commitments))
+            .collect::<BTreeMap<_, _>>();
+
+        FrostSigningPackage::new(commitments_map, &self.signing_hash)
+    }
+
+    /// Sign with the spend authorizing key of a single-signer account
+    pub fn sign(self, spender_key: &SaplingKey) -> Result<MessageSignature, IronfishError> {
+        let ownership_proof = self.ownership_proof.sign(spender_key, &self.signing_hash)?;
+
+        Ok(MessageSignature {
+            randomized_public_key: self.randomized_public_key,
+            ownership_proof,
+        })
+    }
+
+    /// Aggregate the signature shares of the participants of a multisig
+    /// account into the spend signature
+    pub fn aggregate_signature_shares(
+        self,
+        public_key_package: &PublicKeyPackage,
+        signing_package: &FrostSigningPackage,
+        signature_shares: BTreeMap<Identifier, SignatureShare>,
+    ) -> Result<MessageSignature, IronfishError> {
+        let randomizer = Randomizer::deserialize(&self.public_key_randomness.to_bytes())
+            .map_err(|e| IronfishError::new_with_source(IronfishErrorKind::InvalidRandomizer, e))?;
+        let randomized_params =
+            RandomizedParams::from_randomizer(public_key_package.verifying_key(), randomizer);
+
+        let group_signature = aggregate(
+            signing_package,
+            &signature_shares,
+            public_key_package,
+            &randomized_params,
+        )
+        .map_err(|e| {
+            IronfishError::new_with_source(IronfishErrorKind::FailedSignatureAggregation, e)
+        })?;
+
+        randomized_params
+            .randomized_verifying_key()
+            .verify(&self.signing_hash, &group_signature)
+            .map_err(|e| {
+                IronfishError::new_with_source(IronfishErrorKind::FailedSignatureVerification, e)
+            })?;
+
+        let spend_signature = Signature::read(&mut group_signature.serialize().as_ref())?;
+        let ownership_proof = self.ownership_proof.add_signature(spend_signature);
+
+        // The account must be the one that made the ownership proof
+        ownership_proof
+            .verify_signature(&self.signing_hash, &self.randomized_public_key)
+            .map_err(|_| IronfishError::new(IronfishErrorKind::InvalidSigningKey))?;
+
+        Ok(MessageSignature {
+            randomized_public_key: self.randomized_public_key,
+            ownership_proof,
+        })
+    }
+}
+
+impl MessageSignature {
+    pub fn read<R: io::Read>(mut reader: R) -> Result<Self, IronfishError> {
+        let randomized_public_key = redjubjub::PublicKey::read(&mut reader)?;
+        let ownership_proof = SpendDescription::read(&mut reader)?;
+
+        Ok(MessageSignature {
+            randomized_public_key,
+            ownership_proof,
+        })
+    }
+
+    pub fn write<W: io::Write>(&self, mut writer: W) -> Result<(), IronfishError> {
+        writer.write_all(&self.randomized_public_key.0.to_bytes())?;
+        self.ownership_proof.write(&mut writer)?;
+
+        Ok(())
+    }
+}
+
+impl SaplingKey {
+    /// Sign an arbitrary message, see [`verify_message`]
+    pub fn sign_message(&self, message: &[u8]) -> Result<MessageSignature, IronfishError> {
+        UnsignedMessage::new(self.proof_authorizing_key, &self.view_key, message)?.sign(self)
+    }
+}
+
+/// Check that `signature` was made over `message` by the account with the
+/// given public address, or with the public address of the given view key.
+pub fn verify_message<'a>(
+    signer: impl Into<MessageSigner<'a>>,
+    message: &[u8],
+    signature: &MessageSignature,
+) -> Result<(), IronfishError> {
+    let public_address = match signer.into() {
+        MessageSigner::PublicAddress(public_address) => *public_address,
+        MessageSigner::ViewKey(view_key) => view_key.public_address()?,
+    };
+
+    let randomized_public_key = &signature.randomized_public_key;
+    if randomized_public_key.0.is_small_order().into() {
+        return Err(IronfishError::new(IronfishErrorKind::IsSmallOrder));
+    }
+
+    let message_hash = hash_message(&public_address, message);
+    let signing_hash = hash_signature(&message_hash, &signature.ownership_proof)?;
+    signature
+        .ownership_proof
+        .verify_signature(&signing_hash, randomized_public_key)
+        .map_err(|_| IronfishError::new(IronfishErrorKind::InvalidSignature))?;
+
+    // The proof must be made against the marker tree of the marker note of
+    // the address, which only the owner of the address can spend
+    let note = marker_note(&public_address, &message_hash);
+    let marker_witness = marker_witness(&message_hash, 0, note.commitment_point());
+    if signature.ownership_proof.root_hash != marker_witness.root_hash {
+        return Err(IronfishError::new(IronfishErrorKind::InvalidSpendProof));
+    }
+
+    signature.ownership_proof.partial_verify()?;
+    verify_spend_proof(
+        &signature.ownership_proof.proof,
+        &signature
+            .ownership_proof
+            .public_inputs(randomized_public_key),
+    )
+}
+
+fn hash_message(public_address: &PublicAddress, message: &[u8]) -> [u8; 32] {
+    let hash = Blake2b::new()
+        .hash_length(32)
+        .personal(MESSAGE_HASH_PERSONALIZATION)
+        .to_state()
+        .update(MESSAGE_SIGNATURE_VERSION)
+        .update(&public_address.public_address())
+        .update(message)
+        .finalize();
+
+    let mut message_hash = [0; 32];
+    message_hash.copy_from_slice(hash.as_bytes());
+    message_hash
+}
+
+/// Note of zero value owned and sent by `public_address`, with a randomness
+/// derived from the message hash so that the verifier can recompute its
+/// commitment
+fn marker_note(public_address: &PublicAddress, message_hash: &[u8; 32]) -> Note {
+    let hash = Blake2b::new()
+        .hash_length(64)
+        .personal(MARKER_NOTE_PERSONALIZATION)
+        .hash(message_hash);
+
+    Note {
+        asset_id: NATIVE_ASSET,
+        owner: *public_address,
+        value: 0,
+        randomness: jubjub::Fr::from_bytes_wide(hash.as_array()),
+        memo: Memo::default(),
+        sender: *public_address,
+    }
+}
+
+/// Hash signed with the randomized spend authorizing key: the message hash,
+/// followed by the fields of the ownership proof
+fn hash_signature(
+    message_hash: &[u8; 32],
+    ownership_proof: &SpendDescription,
+) -> Result<[u8; 32], IronfishError> {
+    let mut hasher = Blake2b::new()
+        .hash_length(32)
+        .personal(MESSAGE_SIGNATURE_PERSONALIZATION)
+        .to_state();
+    hasher.update(message_hash);
+    ownership_proof.serialize_signature_fields(&mut hasher)?;
+
+    let mut signing_hash = [0; 32];
+    signing_hash.copy_from_slice(hasher.finalize().as_bytes());
+    Ok(signing_hash)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{BTreeMap, HashMap};
+
+    use group::GroupEncoding;
+    use ironfish_frost::{
+        frost::{round2, Randomizer},
+        nonces::deterministic_signing_nonces,
+    };
+
+    use super::{verify_message, MessageSignature, UnsignedMessage, MESSAGE_SIGNATURE_SIZE};
+    use crate::{
+        errors::{IronfishError, IronfishErrorKind},
+        frost_utils::split_spender_key::split_spender_key,
+        test_util::create_multisig_identities,
+        SaplingKey,
+    };
+
+    fn is_invalid(result: Result<(), IronfishError>) -> bool {
+        matches!(result, Err(e) if matches!(e.kind, IronfishErrorKind::InvalidSignature))
+    }
+
+    #[test]
+    fn test_sign_message() {
+        let key = SaplingKey::generate_key();
+        let message = b"login to example.com at 2026-10-16T12:00:00Z";
+
+        let signature = key.sign_message(message).unwrap();
+
+        let mut serialized = vec![];
+        signature.write(&mut serialized).unwrap();
+        assert_eq!(serialized.len(), MESSAGE_SIGNATURE_SIZE);
+        let signature = MessageSignature::read(&serialized[..]).unwrap();
+
+        verify_message(key.view_key(), message, &signature).unwrap();
+        verify_message(&key.public_address(), message, &signature).unwrap();
+
+        // The view keys of the account are not part of the signature
+        let view_key = key.view_key();
+        for key_bytes in [
+            view_key.authorizing_key.to_bytes(),
+            view_key.nullifier_deriving_key.to_bytes(),
+        ] {
+            assert!(!serialized.windows(32).any(|window| window == key_bytes));
+        }
+    }
+
+    #[test]
+    fn test_verify_message_failures() {
+        let key = SaplingKey::generate_key();
+        let other_key = SaplingKey::generate_key();
+        let message = b"message";
+        let signature = key.sign_message(message).unwrap();
+
+        assert!(is_invalid(verify_message(
+            key.view_key(),
+            b"other message",
+            &signature
+        )));
+        assert!(is_invalid(verify_message(
+            other_key.view_key(),
+            message,
+            &signature
+        )));
+        assert!(is_invalid(verify_message(
+            &other_key.public_address(),
+            message,
+            &signature
+        )));
+
+        // The ownership proof of another message cannot be reused
+        let other_signature = key.sign_message(b"other message").unwrap();
+        let mut tampered = signature.clone();
+        tampered.ownership_proof = other_signature.ownership_proof;
+        assert!(is_invalid(verify_message(
+            &key.public_address(),
+            message,
+            &tampered
+        )));
+
+        // The ownership proof cannot be made without the proof authorizing
+        // key of the account
+        assert!(
+            UnsignedMessage::new(other_key.proof_authorizing_key, key.view_key(), message).is_err()
+        );
+
+        // The signing key must match the unsigned message
+        let unsigned =
+            UnsignedMessage::new(key.proof_authorizing_key, key.view_key(), message).unwrap();
+        assert!(matches!(
+            unsigned.sign(&other_key),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidSigningKey)
+        ));
+    }
+
+    #[test]
+    fn test_sign_message_multisig() {
+        let spender_key = SaplingKey::generate_key();
+        let identities = create_multisig_identities(5);
+        let key_packages = split_spender_key(&spender_key, 3, identities.clone()).unwrap();
+        let message = b"multisig message";
+
+        let unsigned = UnsignedMessage::new(
+            key_packages.proof_authorizing_key,
+            &key_packages.view_key,
+            message,
+        )
+        .unwrap();
+        let signing_hash = unsigned.signing_hash();
+
+        // Round 1
+        let mut commitments = HashMap::new();
+        for (identity, key_package) in key_packages.key_packages.iter() {
+            let nonces = deterministic_signing_nonces(
+                key_package.signing_share(),
+                &signing_hash,
+                &identities,
+            );
+            commitments.insert(identity.clone(), (&nonces).into());
+        }
+        let signing_package = unsigned.signing_package(commitments);
+
+        // Round 2
+        let randomizer = Randomizer::deserialize(&unsigned.public_key_randomness().to_bytes())
+            .expect("should be able to deserialize randomizer");
+        let mut signature_shares = BTreeMap::new();
+        for (identity, key_package) in key_packages.key_packages.iter() {
+            let nonces = deterministic_signing_nonces(
+                key_package.signing_share(),
+                &signing_hash,
+                &identities,
+            );
+            let signature_share =
+                round2::sign(&signing_package, &nonces, key_package, randomizer).unwrap();
+            signature_shares.insert(identity.to_frost_identifier(), signature_share);
+        }
+
+        let signature = unsigned
+            .aggregate_signature_shares(
+                &key_packages.public_key_package,
+                &signing_package,
+                signature_shares,
+            )
+            .unwrap();
+
+        verify_message(&key_packages.view_key, message, &signature).unwrap();
+        verify_message(&key_packages.public_address, message, &signature).unwrap();
+    }
+}
@@ -19,8 +19,19 @@ use rand::prelude::*;
 
 use std::io;
 
+mod bech32m;
+pub use bech32m::*;
+mod derivation;
+pub use derivation::*;
+mod diversifier;
+pub(crate) use diversifier::{diversified_base, diversified_proof_generation_key};
+pub use diversifier::{DiversifiedAddress, DIVERSIFIED_ADDRESS_SIZE, DIVERSIFIER_SIZE};
 mod ephemeral;
 pub use ephemeral::EphemeralKeyPair;
+mod full_viewing_key;
+pub use full_viewing_key::*;
+mod message_signature;
+pub use message_signature::*;
 mod public_address;
 pub use public_address::*;
 mod view_keys;
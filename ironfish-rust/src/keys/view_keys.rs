@@ -106,7 +106,11 @@ impl ViewKey {
     /// Load a key from a string of hexadecimal digits
     pub fn from_hex(value: &str) -> Result<Self, IronfishError> {
         let bytes: [u8; 64] = hex_to_bytes(value)?;
+        Self::from_bytes(&bytes)
+    }
 
+    /// Load a key from its 64 byte representation, as returned by `to_bytes`
+    pub fn from_bytes(bytes: &[u8; 64]) -> Result<Self, IronfishError> {
         let mut authorizing_key_bytes = [0; 32];
         let mut nullifier_deriving_key_bytes = [0; 32];
 
@@ -139,14 +143,18 @@ impl ViewKey {
     }
 
     pub fn public_address(&self) -> Result<PublicAddress, IronfishError> {
-        let ivk = IncomingViewKey {
+        Ok(self.incoming_view_key()?.public_address())
+    }
+
+    /// Incoming view key of the account, see
+    /// [`SaplingKey::hash_viewing_key`]
+    pub fn incoming_view_key(&self) -> Result<IncomingViewKey, IronfishError> {
+        Ok(IncomingViewKey {
             view_key: SaplingKey::hash_viewing_key(
                 &self.authorizing_key,
                 &self.nullifier_deriving_key,
             )?,
-        };
-
-        Ok(ivk.public_address())
+        })
     }
 }
 
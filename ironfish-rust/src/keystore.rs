@@ -0,0 +1,512 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Password protected storage of keys.
+//!
+//! An [`EncryptedKeystore`] contains either a spending key, or only the view
+//! keys of an account for watch-only wallets. The key material is encrypted
+//! with ChaCha20Poly1305, using a key derived from the password with scrypt.
+//!
+//! The serialized format is:
+//!
+//! | field            | size |
+//! |------------------|------|
+//! | magic (`IFKS`)   | 4    |
+//! | format version   | 1    |
+//! | key kind         | 1    |
+//! | scrypt `log_n`   | 1    |
+//! | scrypt `r`       | 4    |
+//! | scrypt `p`       | 4    |
+//! | salt             | 16   |
+//! | nonce            | 12   |
+//! | ciphertext + MAC | depends on the key kind |
+//!
+//! All the fields before the ciphertext are authenticated as associated data,
+//! so the header cannot be tampered with.
+
+use std::io;
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use chacha20poly1305::aead::{Aead, NewAead, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::{thread_rng, RngCore};
+
+use crate::{
+    errors::{IronfishError, IronfishErrorKind},
+    keys::{FullViewingKey, SaplingKey, FULL_VIEWING_KEY_SIZE, SPEND_KEY_SIZE},
+    serializing::aead::MAC_SIZE,
+};
+
+const KEYSTORE_MAGIC: &[u8; 4] = b"IFKS";
+const KEYSTORE_FORMAT_VERSION: u8 = 1;
+const SALT_SIZE: usize = 16;
+const NONCE_SIZE: usize = 12;
+const HEADER_SIZE: usize = 4 + 1 + 1 + 1 + 4 + 4 + SALT_SIZE + NONCE_SIZE;
+
+/// Upper bounds of the scrypt parameters. Keystores are read from untrusted
+/// files, and unbounded parameters would let a file make the key derivation
+/// use an arbitrary amount of memory and time.
+const MAX_LOG_N: u8 = 20;
+const MAX_R: u32 = 32;
+const MAX_P: u32 = 16;
+/// Upper bound of the memory used by scrypt, `128 * r * 2^log_n` bytes
+const MAX_MEMORY: u64 = 256 * 1024 * 1024;
+/// Upper bound of `p * r`, which scales the time taken by scrypt along with
+/// the memory
+const MAX_P_TIMES_R: u32 = 64;
+
+/// The kind of key stored in an [`EncryptedKeystore`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyKind {
+    SpendingKey,
+    WatchOnly,
+}
+
+impl KeyKind {
+    fn from_u8(value: u8) -> Result<Self, IronfishError> {
+        match value {
+            0 => Ok(KeyKind::SpendingKey),
+            1 => Ok(KeyKind::WatchOnly),
+            _ => Err(IronfishError::new(IronfishErrorKind::InvalidData)),
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            KeyKind::SpendingKey => 0,
+            KeyKind::WatchOnly => 1,
+        }
+    }
+
+    fn plaintext_size(self) -> usize {
+        match self {
+            KeyKind::SpendingKey => SPEND_KEY_SIZE,
+            KeyKind::WatchOnly => FULL_VIEWING_KEY_SIZE,
+        }
+    }
+}
+
+/// A key that can be stored in an [`EncryptedKeystore`]
+#[derive(Clone)]
+pub enum KeystoreKey {
+    /// Full access to the account
+    SpendingKey(SaplingKey),
+    /// Read access to the notes received and sent by the account, without the
+    /// ability to spend them
+    WatchOnly(FullViewingKey),
+}
+
+impl KeystoreKey {
+    /// Watch-only export of the given spending key
+    pub fn watch_only(key: &SaplingKey) -> Self {
+        KeystoreKey::WatchOnly(key.full_viewing_key())
+    }
+
+    pub fn kind(&self) -> KeyKind {
+        match self {
+            KeystoreKey::SpendingKey(_) => KeyKind::SpendingKey,
+            KeystoreKey::WatchOnly(_) => KeyKind::WatchOnly,
+        }
+    }
+
+    /// Watch-only keys of the stored key
+    pub fn full_viewing_key(&self) -> FullViewingKey {
+        match self {
+            KeystoreKey::SpendingKey(key) => key.full_viewing_key(),
+            KeystoreKey::WatchOnly(full_viewing_key) => full_viewing_key.clone(),
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            KeystoreKey::SpendingKey(key) => key.spending_key().to_vec(),
+            KeystoreKey::WatchOnly(full_viewing_key) => full_viewing_key.to_bytes().to_vec(),
+        }
+    }
+
+    fn from_bytes(kind: KeyKind, bytes: &[u8]) -> Result<Self, IronfishError> {
+        match kind {
+            KeyKind::SpendingKey => {
+                Ok(KeystoreKey::SpendingKey(SaplingKey::read(&mut &bytes[..])?))
+            }
+            KeyKind::WatchOnly => Ok(KeystoreKey::WatchOnly(FullViewingKey::read(bytes)?)),
+        }
+    }
+}
+
+/// Parameters of the scrypt key derivation function. They are stored in the
+/// keystore, so they can be strengthened over time without breaking existing
+/// keystores.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KdfParams {
+    /// Base 2 logarithm of the CPU/memory cost
+    pub log_n: u8,
+    /// Block size
+    pub r: u32,
+    /// Parallelization
+    pub p: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            log_n: 15,
+            r: 8,
+            p: 1,
+        }
+    }
+}
+
+impl KdfParams {
+    /// Fails with [`IronfishErrorKind::InvalidData`] if the parameters are out
+    /// of the supported bounds, on their own or combined
+    fn validate(&self) -> Result<(), IronfishError> {
+        if self.log_n == 0
+            || self.log_n > MAX_LOG_N
+            || self.r == 0
+            || self.r > MAX_R
+            || self.p == 0
+            || self.p > MAX_P
+        {
+            return Err(IronfishError::new(IronfishErrorKind::InvalidData));
+        }
+
+        // Cannot overflow with the bounds above
+        if 128 * u64::from(self.r) * (1 << self.log_n) > MAX_MEMORY
+            || self.p * self.r > MAX_P_TIMES_R
+        {
+            return Err(IronfishError::new(IronfishErrorKind::InvalidData));
+        }
+
+        Ok(())
+    }
+
+    fn derive_key(&self, password: &[u8], salt: &[u8]) -> Result<[u8; 32], IronfishError> {
+        self.validate()?;
+
+        let params = scrypt::Params::new(self.log_n, self.r, self.p)
+            .map_err(|_| IronfishError::new(IronfishErrorKind::InvalidData))?;
+
+        let mut key = [0; 32];
+        scrypt::scrypt(password, salt, &params, &mut key)
+            .map_err(|_| IronfishError::new(IronfishErrorKind::InvalidData))?;
+
+        Ok(key)
+    }
+}
+
+/// A password protected key. See the [module documentation](self) for the
+/// format.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EncryptedKeystore {
+    kind: KeyKind,
+    params: KdfParams,
+    salt: [u8; SALT_SIZE],
+    nonce: [u8; NONCE_SIZE],
+    ciphertext: Vec<u8>,
+}
+
+impl EncryptedKeystore {
+    /// Encrypt `key` with a key derived from `password`.
+    pub fn encrypt(
+        key: &KeystoreKey,
+        password: &[u8],
+        params: KdfParams,
+    ) -> Result<Self, IronfishError> {
+        let mut salt = [0; SALT_SIZE];
+        let mut nonce = [0; NONCE_SIZE];
+        thread_rng().fill_bytes(&mut salt);
+        thread_rng().fill_bytes(&mut nonce);
+
+        let mut keystore = EncryptedKeystore {
+            kind: key.kind(),
+            params,
+            salt,
+            nonce,
+            ciphertext: vec![],
+        };
+
+        let encryption_key = params.derive_key(password, &salt)?;
+        let header = keystore.header();
+        keystore.ciphertext = ChaCha20Poly1305::new(Key::from_slice(&encryption_key))
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &key.to_bytes(),
+                    aad: &header,
+                },
+            )
+            .map_err(|_| IronfishError::new(IronfishErrorKind::InvalidData))?;
+
+        Ok(keystore)
+    }
+
+    /// Decrypt the stored key. Fails with
+    /// [`IronfishErrorKind::InvalidDecryptionKey`] if the password is wrong or
+    /// the keystore was modified.
+    pub fn decrypt(&self, password: &[u8]) -> Result<KeystoreKey, IronfishError> {
+        let encryption_key = self.params.derive_key(password, &self.salt)?;
+        let plaintext = ChaCha20Poly1305::new(Key::from_slice(&encryption_key))
+            .decrypt(
+                Nonce::from_slice(&self.nonce),
+                Payload {
+                    msg: &self.ciphertext,
+                    aad: &self.header(),
+                },
+            )
+            .map_err(|_| IronfishError::new(IronfishErrorKind::InvalidDecryptionKey))?;
+
+        KeystoreKey::from_bytes(self.kind, &plaintext)
+    }
+
+    /// The kind of key stored, which can be known without the password
+    pub fn kind(&self) -> KeyKind {
+        self.kind
+    }
+
+    pub fn params(&self) -> KdfParams {
+        self.params
+    }
+
+    pub fn read<R: io::Read>(mut reader: R) -> Result<Self, IronfishError> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != KEYSTORE_MAGIC {
+            return Err(IronfishError::new(IronfishErrorKind::InvalidData));
+        }
+
+        if reader.read_u8()? != KEYSTORE_FORMAT_VERSION {
+            return Err(IronfishError::new(IronfishErrorKind::InvalidData));
+        }
+
+        let kind = KeyKind::from_u8(reader.read_u8()?)?;
+        let params = KdfParams {
+            log_n: reader.read_u8()?,
+            r: reader.read_u32::<LittleEndian>()?,
+            p: reader.read_u32::<LittleEndian>()?,
+        };
+        params.validate()?;
+
+        let mut salt = [0; SALT_SIZE];
+        reader.read_exact(&mut salt)?;
+        let mut nonce = [0; NONCE_SIZE];
+        reader.read_exact(&mut nonce)?;
+
+        let mut ciphertext = vec![0; kind.plaintext_size() + MAC_SIZE];
+        reader.read_exact(&mut ciphertext)?;
+
+        Ok(EncryptedKeystore {
+            kind,
+            params,
+            salt,
+            nonce,
+            ciphertext,
+        })
+    }
+
+    pub fn write<W: io::Write>(&self, mut writer: W) -> Result<(), IronfishError> {
+        writer.write_all(&self.header())?;
+        writer.write_all(&self.ciphertext)?;
+
+        Ok(())
+    }
+
+    fn header(&self) -> Vec<u8> {
+        let mut header = Vec::with_capacity(HEADER_SIZE);
+        header.extend_from_slice(KEYSTORE_MAGIC);
+        header.push(KEYSTORE_FORMAT_VERSION);
+        header.push(self.kind.to_u8());
+        header.push(self.params.log_n);
+        header.extend_from_slice(&self.params.r.to_le_bytes());
+        header.extend_from_slice(&self.params.p.to_le_bytes());
+        header.extend_from_slice(&self.salt);
+        header.extend_from_slice(&self.nonce);
+        header
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{EncryptedKeystore, KdfParams, KeyKind, KeystoreKey, HEADER_SIZE};
+    use crate::{errors::IronfishErrorKind, SaplingKey};
+
+    /// Cheap parameters to keep the tests fast
+    const TEST_PARAMS: KdfParams = KdfParams {
+        log_n: 4,
+        r: 8,
+        p: 1,
+    };
+
+    #[test]
+    fn test_spending_key_round_trip() {
+        let key = SaplingKey::generate_key();
+        let keystore = EncryptedKeystore::encrypt(
+            &KeystoreKey::SpendingKey(key.clone()),
+            b"password",
+            TEST_PARAMS,
+        )
+        .unwrap();
+
+        let mut serialized = vec![];
+        keystore.write(&mut serialized).unwrap();
+        assert_eq!(serialized.len(), HEADER_SIZE + 32 + 16);
+
+        let deserialized = EncryptedKeystore::read(&serialized[..]).unwrap();
+        assert_eq!(deserialized, keystore);
+        assert_eq!(deserialized.kind(), KeyKind::SpendingKey);
+        assert_eq!(deserialized.params(), TEST_PARAMS);
+
+        match deserialized.decrypt(b"password").unwrap() {
+            KeystoreKey::SpendingKey(decrypted) => {
+                assert_eq!(decrypted.spending_key(), key.spending_key())
+            }
+            _ => panic!("expected a spending key"),
+        }
+    }
+
+    #[test]
+    fn test_watch_only_round_trip() {
+        let key = SaplingKey::generate_key();
+        let keystore =
+            EncryptedKeystore::encrypt(&KeystoreKey::watch_only(&key), b"password", TEST_PARAMS)
+                .unwrap();
+
+        let mut serialized = vec![];
+        keystore.write(&mut serialized).unwrap();
+        let deserialized = EncryptedKeystore::read(&serialized[..]).unwrap();
+        assert_eq!(deserialized.kind(), KeyKind::WatchOnly);
+
+        let decrypted = deserialized.decrypt(b"password").unwrap();
+        assert_eq!(decrypted.kind(), KeyKind::WatchOnly);
+        assert_eq!(
+            decrypted.full_viewing_key().to_bytes(),
+            key.full_viewing_key().to_bytes()
+        );
+    }
+
+    #[test]
+    fn test_wrong_password() {
+        let key = SaplingKey::generate_key();
+        let keystore =
+            EncryptedKeystore::encrypt(&KeystoreKey::SpendingKey(key), b"password", TEST_PARAMS)
+                .unwrap();
+
+        assert!(matches!(
+            keystore.decrypt(b"passw0rd"),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidDecryptionKey)
+        ));
+    }
+
+    #[test]
+    fn test_tampered_header() {
+        let key = SaplingKey::generate_key();
+        let keystore =
+            EncryptedKeystore::encrypt(&KeystoreKey::SpendingKey(key), b"password", TEST_PARAMS)
+                .unwrap();
+
+        let mut serialized = vec![];
+        keystore.write(&mut serialized).unwrap();
+
+        // Changing the salt breaks the authentication
+        let mut tampered = serialized.clone();
+        tampered[15] ^= 1;
+        let tampered = EncryptedKeystore::read(&tampered[..]).unwrap();
+        assert!(matches!(
+            tampered.decrypt(b"password"),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidDecryptionKey)
+        ));
+
+        // Unknown format versions are rejected
+        let mut tampered = serialized;
+        tampered[4] = 2;
+        assert!(matches!(
+            EncryptedKeystore::read(&tampered[..]),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidData)
+        ));
+    }
+
+    #[test]
+    fn test_kdf_params_bounds() {
+        let key = SaplingKey::generate_key();
+        let keystore =
+            EncryptedKeystore::encrypt(&KeystoreKey::SpendingKey(key), b"password", TEST_PARAMS)
+                .unwrap();
+
+        let mut serialized = vec![];
+        keystore.write(&mut serialized).unwrap();
+
+        // log_n, r and p follow the magic, format version and key kind
+        let out_of_bounds: [(usize, &[u8]); 3] = [
+            (6, &[21]),
+            (7, &33u32.to_le_bytes()),
+            (11, &0u32.to_le_bytes()),
+        ];
+        for (offset, value) in out_of_bounds {
+            let mut tampered = serialized.clone();
+            tampered[offset..offset + value.len()].copy_from_slice(value);
+            assert!(matches!(
+                EncryptedKeystore::read(&tampered[..]),
+                Err(e) if matches!(e.kind, IronfishErrorKind::InvalidData)
+            ));
+        }
+
+        let params = KdfParams {
+            log_n: 32,
+            ..TEST_PARAMS
+        };
+        assert!(EncryptedKeystore::encrypt(
+            &KeystoreKey::SpendingKey(SaplingKey::generate_key()),
+            b"password",
+            params
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_kdf_params_combined_bounds() {
+        assert!(KdfParams::default().validate().is_ok());
+        assert!(KdfParams {
+            log_n: 20,
+            r: 2,
+            p: 1
+        }
+        .validate()
+        .is_ok());
+
+        // Every parameter is in bounds on its own, but the combination would
+        // use 4 GiB of memory, or take too long
+        let too_expensive = [
+            KdfParams {
+                log_n: 20,
+                r: 32,
+                p: 1,
+            },
+            KdfParams {
+                log_n: 4,
+                r: 32,
+                p: 16,
+            },
+        ];
+        for params in too_expensive {
+            assert!(matches!(
+                params.validate(),
+                Err(e) if matches!(e.kind, IronfishErrorKind::InvalidData)
+            ));
+        }
+
+        // Keystores with such parameters cannot be read
+        let key = SaplingKey::generate_key();
+        let keystore =
+            EncryptedKeystore::encrypt(&KeystoreKey::SpendingKey(key), b"password", TEST_PARAMS)
+                .unwrap();
+        let mut serialized = vec![];
+        keystore.write(&mut serialized).unwrap();
+        serialized[6] = 20;
+        serialized[7..11].copy_from_slice(&32u32.to_le_bytes());
+        assert!(matches!(
+            EncryptedKeystore::read(&serialized[..]),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidData)
+        ));
+    }
+}
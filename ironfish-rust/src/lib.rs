@@ -7,12 +7,16 @@ use blstrs::Bls12;
 pub mod assets;
 pub mod errors;
 pub mod frost_utils;
+pub mod incremental_witness;
 pub mod keys;
+pub mod keystore;
 pub mod merkle_note;
 pub mod merkle_note_hash;
 pub mod mining;
 pub mod nacl;
 pub mod note;
+pub mod note_commitment_tree;
+pub mod payment_request;
 pub mod rolling_filter;
 pub mod sapling_bls12;
 pub mod serializing;
@@ -21,12 +25,14 @@ pub mod transaction;
 pub mod util;
 pub mod witness;
 pub use {
+    incremental_witness::IncrementalWitness,
     ironfish_frost::frost,
     ironfish_frost::participant,
-    keys::{IncomingViewKey, OutgoingViewKey, PublicAddress, SaplingKey, ViewKey},
+    keys::{FullViewingKey, IncomingViewKey, OutgoingViewKey, PublicAddress, SaplingKey, ViewKey},
     merkle_note::MerkleNote,
     merkle_note_hash::MerkleNoteHash,
     note::Note,
+    note_commitment_tree::NoteCommitmentTree,
     transaction::{
         outputs::OutputDescription, spends::SpendDescription, ProposedTransaction, Transaction,
     },
@@ -2,12 +2,20 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-use crate::{errors::IronfishError, keys::EphemeralKeyPair, serializing::read_point};
+use crate::{
+    errors::{IronfishError, IronfishErrorKind},
+    keys::{diversified_base, DiversifiedAddress, EphemeralKeyPair, DIVERSIFIER_SIZE},
+    serializing::read_point,
+    transaction::TransactionVersion,
+};
 
 /// Implement a merkle note to store all the values that need to go into a merkle tree.
 /// A tree containing these values can serve as a snapshot of the entire chain.
 use super::{
-    keys::{shared_secret, IncomingViewKey, OutgoingViewKey, PublicAddress},
+    keys::{
+        shared_secret, IncomingViewKey, OutgoingViewKey, PublicAddress, ViewKey,
+        PUBLIC_ADDRESS_SIZE,
+    },
     note::{Note, ENCRYPTED_NOTE_SIZE},
     serializing::{aead, read_scalar},
     witness::{WitnessNode, WitnessTrait},
@@ -16,10 +24,11 @@ use super::{
 
 use blake2b_simd::Params as Blake2b;
 use blstrs::Scalar;
-use ff::PrimeField;
+use ff::{Field, PrimeField};
 use group::GroupEncoding;
-use ironfish_zkp::primitives::ValueCommitment;
+use ironfish_zkp::{constants::PUBLIC_KEY_GENERATOR, primitives::ValueCommitment};
 use jubjub::{ExtendedPoint, SubgroupPoint};
+use rand::thread_rng;
 
 use std::{convert::TryInto, io};
 
@@ -37,7 +46,63 @@ pub const NOTE_ENCRYPTION_KEY_SIZE: usize = ENCRYPTED_SHARED_KEY_SIZE + aead::MA
 /// to be on the first transaction in a block.
 pub const NOTE_ENCRYPTION_MINER_KEYS: &[u8; NOTE_ENCRYPTION_KEY_SIZE] =
     b"Iron Fish note encryption miner key000000000000000000000000000000000000000000000";
+/// Size of a serialized [`MerkleNote`]: value commitment, note commitment,
+/// ephemeral public key, encrypted note and encrypted note encryption keys.
+pub const MERKLE_NOTE_SIZE: usize =
+    32 + 32 + 32 + ENCRYPTED_NOTE_SIZE + aead::MAC_SIZE + NOTE_ENCRYPTION_KEY_SIZE;
 const SHARED_KEY_PERSONALIZATION: &[u8; 16] = b"Iron Fish Keyenc";
+const RECIPIENT_KEY_PERSONALIZATION: &[u8; 16] = b"Iron Fish Recipt";
+
+/// Size of the recipient encrypted in a [`DiversifiedEncryption`]: diversifier
+/// and owner address.
+const RECIPIENT_SIZE: usize = DIVERSIFIER_SIZE + PUBLIC_ADDRESS_SIZE;
+
+/// Size of the fields added to a [`MerkleNote`] by
+/// [`TransactionVersion::V3`]: diversified ephemeral public key and encrypted
+/// recipient.
+pub const DIVERSIFIED_ENCRYPTION_SIZE: usize = 32 + RECIPIENT_SIZE + aead::MAC_SIZE;
+
+/// Size of a serialized [`MerkleNote`] in a transaction of the given version
+pub fn merkle_note_size(version: TransactionVersion) -> usize {
+    match version.has_diversified_notes() {
+        true => MERKLE_NOTE_SIZE + DIVERSIFIED_ENCRYPTION_SIZE,
+        false => MERKLE_NOTE_SIZE,
+    }
+}
+
+/// Encryption of a note to the diversified address that received it, used by
+/// transactions of a version that has
+/// [`TransactionVersion::has_diversified_notes`].
+///
+/// The note is encrypted with the shared secret of `esk_d * g_d` and of the
+/// transmission key `pk_d = ivk * g_d` of the address, rather than the one of
+/// the ephemeral public key checked by the output circuit. The diversifier
+/// and the owner of the address are encrypted along with it, so that the
+/// incoming view key can recover the index of the address.
+#[derive(Clone)]
+pub(crate) struct DiversifiedEncryption {
+    /// Ephemeral public key `esk_d * g_d`
+    pub(crate) ephemeral_public_key: SubgroupPoint,
+
+    /// Diversifier and owner address of the recipient
+    pub(crate) encrypted_recipient: [u8; RECIPIENT_SIZE + aead::MAC_SIZE],
+}
+
+impl DiversifiedEncryption {
+    fn decrypt_recipient(
+        &self,
+        shared_secret: &[u8; 32],
+    ) -> Result<([u8; DIVERSIFIER_SIZE], PublicAddress), IronfishError> {
+        let recipient: [u8; RECIPIENT_SIZE] =
+            aead::decrypt(&recipient_key(shared_secret), &self.encrypted_recipient)?;
+
+        let mut diversifier = [0; DIVERSIFIER_SIZE];
+        diversifier.copy_from_slice(&recipient[..DIVERSIFIER_SIZE]);
+        let owner = PublicAddress::read(&recipient[DIVERSIFIER_SIZE..])?;
+
+        Ok((diversifier, owner))
+    }
+}
 
 #[derive(Clone)]
 pub struct MerkleNote {
@@ -62,6 +127,11 @@ pub struct MerkleNote {
     /// decrypt it. The receiver (owner) doesn't need these, as they can decrypt
     /// the note directly using their incoming viewing key.
     pub(crate) note_encryption_keys: [u8; NOTE_ENCRYPTION_KEY_SIZE],
+
+    /// Encryption of the note to a diversified address, since
+    /// [`TransactionVersion::V3`]. The note encryption keys then hold the
+    /// transmission key of the address and the diversified ephemeral secret.
+    pub(crate) diversified: Option<DiversifiedEncryption>,
 }
 
 impl PartialEq for MerkleNote {
@@ -78,72 +148,130 @@ impl MerkleNote {
         value_commitment: &ValueCommitment,
         diffie_hellman_keys: &EphemeralKeyPair,
     ) -> MerkleNote {
-        let secret_key = diffie_hellman_keys.secret();
-        let public_key = diffie_hellman_keys.public();
-
-        let mut key_bytes = [0; 64];
-        key_bytes[..32].copy_from_slice(&note.owner.0.to_bytes());
-        key_bytes[32..].clone_from_slice(secret_key.to_repr().as_ref());
-
-        let encryption_key = calculate_key_for_encryption_keys(
-            outgoing_view_key,
-            &value_commitment.commitment().into(),
-            &note.commitment_point(),
-            public_key,
-        );
-        let note_encryption_keys: [u8; NOTE_ENCRYPTION_KEY_SIZE] =
-            aead::encrypt(&encryption_key, &key_bytes).unwrap();
-
         Self::construct(
+            Some(outgoing_view_key),
             note,
+            None,
             value_commitment,
             diffie_hellman_keys,
-            note_encryption_keys,
         )
     }
 
-    /// Helper function to instantiate a MerkleNote with pre-set
-    /// note_encryption_keys. Should only be used for miners fee transactions.
-    pub(crate) fn new_for_miners_fee(
+    /// Create a note encrypted to the diversified address `recipient`, for
+    /// transactions of a version that has
+    /// [`TransactionVersion::has_diversified_notes`]. The owner of `note` must
+    /// be the owner of `recipient`.
+    pub fn new_diversified(
+        outgoing_view_key: &OutgoingViewKey,
+        recipient: &DiversifiedAddress,
         note: &Note,
         value_commitment: &ValueCommitment,
         diffie_hellman_keys: &EphemeralKeyPair,
     ) -> MerkleNote {
-        let note_encryption_keys = *NOTE_ENCRYPTION_MINER_KEYS;
-
         Self::construct(
+            Some(outgoing_view_key),
             note,
+            Some(recipient),
             value_commitment,
             diffie_hellman_keys,
-            note_encryption_keys,
         )
     }
 
-    /// Helper function to cut down on duplicated code between
-    /// `MerkleNote::new` and `MerkleNote::new_for_miners_fee`. Should not
-    /// be used directly.
+    /// Helper function to instantiate a MerkleNote with pre-set
+    /// note_encryption_keys. Should only be used for miners fee transactions.
+    pub(crate) fn new_for_miners_fee(
+        note: &Note,
+        recipient: Option<&DiversifiedAddress>,
+        value_commitment: &ValueCommitment,
+        diffie_hellman_keys: &EphemeralKeyPair,
+    ) -> MerkleNote {
+        Self::construct(None, note, recipient, value_commitment, diffie_hellman_keys)
+    }
+
+    /// Helper function to cut down on duplicated code between the
+    /// constructors. The note encryption keys are encrypted with
+    /// `outgoing_view_key`, or set to [`NOTE_ENCRYPTION_MINER_KEYS`] without
+    /// it. Should not be used directly.
     fn construct(
+        outgoing_view_key: Option<&OutgoingViewKey>,
         note: &Note,
+        recipient: Option<&DiversifiedAddress>,
         value_commitment: &ValueCommitment,
         diffie_hellman_keys: &EphemeralKeyPair,
-        note_encryption_keys: [u8; NOTE_ENCRYPTION_KEY_SIZE],
     ) -> MerkleNote {
-        let secret_key = diffie_hellman_keys.secret();
         let public_key = diffie_hellman_keys.public();
+        let value_commitment_point: ExtendedPoint = value_commitment.commitment().into();
+        let note_commitment = note.commitment_point();
+
+        let (encrypted_note, diversified, key_bytes) = match recipient {
+            None => {
+                let secret_key = diffie_hellman_keys.secret();
+                let shared_key = shared_secret(secret_key, &note.owner.0, public_key);
+
+                (
+                    note.encrypt(&shared_key),
+                    None,
+                    encryption_key_bytes(&note.owner.0, secret_key),
+                )
+            }
+            Some(recipient) => {
+                // A separate ephemeral key, since the one of the output
+                // circuit is always derived from `PUBLIC_KEY_GENERATOR`
+                let secret_key = jubjub::Fr::random(thread_rng());
+                let ephemeral_public_key = recipient.diversified_base() * secret_key;
+                let shared_key = shared_secret(
+                    &secret_key,
+                    &recipient.transmission_key,
+                    &ephemeral_public_key,
+                );
+
+                let mut recipient_bytes = [0; RECIPIENT_SIZE];
+                recipient_bytes[..DIVERSIFIER_SIZE].copy_from_slice(&recipient.diversifier);
+                recipient_bytes[DIVERSIFIER_SIZE..]
+                    .copy_from_slice(&recipient.owner.public_address());
+                let encrypted_recipient =
+                    aead::encrypt(&recipient_key(&shared_key), &recipient_bytes).unwrap();
+
+                (
+                    note.encrypt(&shared_key),
+                    Some(DiversifiedEncryption {
+                        ephemeral_public_key,
+                        encrypted_recipient,
+                    }),
+                    encryption_key_bytes(&recipient.transmission_key, &secret_key),
+                )
+            }
+        };
 
-        let encrypted_note = note.encrypt(&shared_secret(secret_key, &note.owner.0, public_key));
+        let note_encryption_keys = match outgoing_view_key {
+            Some(outgoing_view_key) => {
+                let encryption_key = calculate_key_for_encryption_keys(
+                    outgoing_view_key,
+                    &value_commitment_point,
+                    &note_commitment,
+                    public_key,
+                );
+                aead::encrypt(&encryption_key, &key_bytes).unwrap()
+            }
+            None => *NOTE_ENCRYPTION_MINER_KEYS,
+        };
 
         MerkleNote {
-            value_commitment: value_commitment.commitment().into(),
-            note_commitment: note.commitment_point(),
+            value_commitment: value_commitment_point,
+            note_commitment,
             ephemeral_public_key: (*public_key),
             encrypted_note,
             note_encryption_keys,
+            diversified,
         }
     }
 
-    /// Load a MerkleNote from the given stream
-    pub fn read<R: io::Read>(mut reader: R) -> Result<Self, IronfishError> {
+    /// Load a MerkleNote of a transaction of the given version from the given
+    /// stream
+    pub fn read<R: io::Read>(
+        mut reader: R,
+        version: TransactionVersion,
+    ) -> Result<Self, IronfishError> {
         let value_commitment = read_point(&mut reader)?;
         let note_commitment = read_scalar(&mut reader)?;
         let ephemeral_public_key = read_point(&mut reader)?;
@@ -153,12 +281,26 @@ impl MerkleNote {
         let mut note_encryption_keys = [0; NOTE_ENCRYPTION_KEY_SIZE];
         reader.read_exact(&mut note_encryption_keys[..])?;
 
+        let diversified = if version.has_diversified_notes() {
+            let ephemeral_public_key = read_point(&mut reader)?;
+            let mut encrypted_recipient = [0; RECIPIENT_SIZE + aead::MAC_SIZE];
+            reader.read_exact(&mut encrypted_recipient[..])?;
+
+            Some(DiversifiedEncryption {
+                ephemeral_public_key,
+                encrypted_recipient,
+            })
+        } else {
+            None
+        };
+
         Ok(MerkleNote {
             value_commitment,
             note_commitment,
             ephemeral_public_key,
             encrypted_note,
             note_encryption_keys,
+            diversified,
         })
     }
 
@@ -169,6 +311,11 @@ impl MerkleNote {
         writer.write_all(&self.encrypted_note)?;
         writer.write_all(&self.note_encryption_keys)?;
 
+        if let Some(diversified) = &self.diversified {
+            writer.write_all(&diversified.ephemeral_public_key.to_bytes())?;
+            writer.write_all(&diversified.encrypted_recipient)?;
+        }
+
         Ok(())
     }
 
@@ -176,21 +323,95 @@ impl MerkleNote {
         MerkleNoteHash::new(self.note_commitment)
     }
 
+    /// Decrypt the note with the view key of its owner, returning the index
+    /// of the diversified address that received it along with the note.
+    /// Notes of transactions before [`TransactionVersion::V3`] are always
+    /// received on the regular public address, at index 0.
+    ///
+    /// Fails if the owner sent along with a note received on a diversified
+    /// address is not the owner of that address, since the note could not be
+    /// spent by the account.
     pub fn decrypt_note_for_owner(
+        &self,
+        owner_view_key: &ViewKey,
+    ) -> Result<(u64, Note), IronfishError> {
+        let incoming_view_key = owner_view_key.incoming_view_key()?;
+        self.decrypt_note_for_incoming_keys(&incoming_view_key, Some(owner_view_key))
+    }
+
+    /// Decrypt the note with the incoming view key of its owner. Only notes
+    /// received on the regular public address can be decrypted this way: the
+    /// owners of diversified addresses cannot be derived from the incoming
+    /// view key, see [`MerkleNote::decrypt_note_for_owner`].
+    pub fn decrypt_note_for_incoming_view_key(
         &self,
         owner_view_key: &IncomingViewKey,
     ) -> Result<Note, IronfishError> {
-        let shared_secret = owner_view_key.shared_secret(&self.ephemeral_public_key);
-        let note =
-            Note::from_owner_encrypted(owner_view_key, &shared_secret, &self.encrypted_note)?;
-        note.verify_commitment(self.note_commitment)?;
+        let (_, note) = self.decrypt_note_for_incoming_keys(owner_view_key, None)?;
         Ok(note)
     }
 
+    /// Decrypt the note with `incoming_view_key`, checking the owner of notes
+    /// received on diversified addresses against `view_key`, which must be
+    /// the view key of the same account. Without a view key, only notes
+    /// received at index 0 are accepted.
+    pub(crate) fn decrypt_note_for_incoming_keys(
+        &self,
+        incoming_view_key: &IncomingViewKey,
+        view_key: Option<&ViewKey>,
+    ) -> Result<(u64, Note), IronfishError> {
+        let diversified = match &self.diversified {
+            None => {
+                let shared_secret = incoming_view_key.shared_secret(&self.ephemeral_public_key);
+                let note = Note::from_owner_encrypted(
+                    incoming_view_key,
+                    &shared_secret,
+                    &self.encrypted_note,
+                )?;
+                note.verify_commitment(self.note_commitment)?;
+                return Ok((0, note));
+            }
+            Some(diversified) => diversified,
+        };
+
+        let shared_secret = incoming_view_key.shared_secret(&diversified.ephemeral_public_key);
+        let (diversifier, owner) = diversified.decrypt_recipient(&shared_secret)?;
+        let index = incoming_view_key
+            .diversifier_index(&diversifier)
+            .ok_or_else(|| IronfishError::new(IronfishErrorKind::InvalidDecryptionKey))?;
+
+        // The owner is chosen by the sender, who could otherwise send a note
+        // that the account detects but cannot spend
+        let expected_owner = match (index, view_key) {
+            (0, _) => incoming_view_key.public_address(),
+            (_, Some(view_key)) => view_key.diversified_owner(index)?,
+            (_, None) => return Err(IronfishError::new(IronfishErrorKind::InvalidDecryptionKey)),
+        };
+        if owner != expected_owner {
+            return Err(IronfishError::new(IronfishErrorKind::InvalidDecryptionKey));
+        }
+
+        let note = Note::from_spender_encrypted(owner.0, &shared_secret, &self.encrypted_note)?;
+        note.verify_commitment(self.note_commitment)?;
+        Ok((index, note))
+    }
+
     pub fn decrypt_note_for_spender(
         &self,
         spender_key: &OutgoingViewKey,
     ) -> Result<Note, IronfishError> {
+        let (public_address, secret_key) = self.decrypt_note_encryption_keys(spender_key)?;
+        self.decrypt_note_with_ephemeral_secret(&public_address, &secret_key)
+    }
+
+    /// Decrypt the key the note was encrypted to and the ephemeral secret key
+    /// that the spender used to encrypt it. The key is the owner address, or
+    /// the transmission key of the diversified address that received the
+    /// note since [`TransactionVersion::V3`].
+    pub(crate) fn decrypt_note_encryption_keys(
+        &self,
+        spender_key: &OutgoingViewKey,
+    ) -> Result<(PublicAddress, jubjub::Fr), IronfishError> {
         let encryption_key = calculate_key_for_encryption_keys(
             spender_key,
             &self.value_commitment,
@@ -202,14 +423,74 @@ impl MerkleNote {
             aead::decrypt(&encryption_key, &self.note_encryption_keys)?;
         let public_address = PublicAddress::new(&note_encryption_keys[..32].try_into().unwrap())?;
         let secret_key = read_scalar(&note_encryption_keys[32..])?;
-        let shared_key = shared_secret(&secret_key, &public_address.0, &self.ephemeral_public_key);
-        let note =
-            Note::from_spender_encrypted(public_address.0, &shared_key, &self.encrypted_note)?;
+        Ok((public_address, secret_key))
+    }
+
+    /// Decrypt the note given the key and the ephemeral secret key returned by
+    /// [`MerkleNote::decrypt_note_encryption_keys`]. Fails if the secret key
+    /// does not match the ephemeral public key of the note.
+    pub(crate) fn decrypt_note_with_ephemeral_secret(
+        &self,
+        public_address: &PublicAddress,
+        secret_key: &jubjub::Fr,
+    ) -> Result<Note, IronfishError> {
+        let note = match &self.diversified {
+            None => {
+                if *PUBLIC_KEY_GENERATOR * secret_key != self.ephemeral_public_key {
+                    return Err(IronfishError::new(IronfishErrorKind::InvalidDecryptionKey));
+                }
+
+                let shared_key =
+                    shared_secret(secret_key, &public_address.0, &self.ephemeral_public_key);
+                Note::from_spender_encrypted(public_address.0, &shared_key, &self.encrypted_note)?
+            }
+            Some(diversified) => {
+                let shared_key = shared_secret(
+                    secret_key,
+                    &public_address.0,
+                    &diversified.ephemeral_public_key,
+                );
+                let (diversifier, owner) = diversified.decrypt_recipient(&shared_key)?;
+
+                let base = diversified_base(&diversifier)
+                    .ok_or_else(|| IronfishError::new(IronfishErrorKind::InvalidDecryptionKey))?;
+                if base * secret_key != diversified.ephemeral_public_key {
+                    return Err(IronfishError::new(IronfishErrorKind::InvalidDecryptionKey));
+                }
+
+                Note::from_spender_encrypted(owner.0, &shared_key, &self.encrypted_note)?
+            }
+        };
+
         note.verify_commitment(self.note_commitment)?;
         Ok(note)
     }
 }
 
+/// Plaintext of the note encryption keys: the key the note is encrypted to
+/// and the ephemeral secret key
+fn encryption_key_bytes(
+    public_key: &SubgroupPoint,
+    secret_key: &jubjub::Fr,
+) -> [u8; ENCRYPTED_SHARED_KEY_SIZE] {
+    let mut key_bytes = [0; ENCRYPTED_SHARED_KEY_SIZE];
+    key_bytes[..32].copy_from_slice(&public_key.to_bytes());
+    key_bytes[32..].clone_from_slice(secret_key.to_repr().as_ref());
+    key_bytes
+}
+
+/// Key used to encrypt the recipient of a [`DiversifiedEncryption`], derived
+/// from the shared secret of the note so that the note key is not reused
+fn recipient_key(shared_secret: &[u8; 32]) -> [u8; 32] {
+    Blake2b::new()
+        .hash_length(32)
+        .personal(RECIPIENT_KEY_PERSONALIZATION)
+        .hash(shared_secret)
+        .as_bytes()
+        .try_into()
+        .expect("hash has incorrect length")
+}
+
 pub(crate) fn sapling_auth_path(witness: &dyn WitnessTrait) -> Vec<Option<(Scalar, bool)>> {
     let mut auth_path = vec![];
     for element in &witness.get_auth_path() {
@@ -271,10 +552,11 @@ fn calculate_key_for_encryption_keys(
 
 #[cfg(test)]
 mod test {
-    use super::MerkleNote;
-    use super::NOTE_ENCRYPTION_MINER_KEYS;
+    use super::{merkle_note_size, MerkleNote, NOTE_ENCRYPTION_MINER_KEYS};
     use crate::assets::asset_identifier::NATIVE_ASSET;
-    use crate::keys::EphemeralKeyPair;
+    use crate::errors::IronfishErrorKind;
+    use crate::keys::{DiversifiedAddress, EphemeralKeyPair};
+    use crate::transaction::TransactionVersion;
     use crate::{keys::SaplingKey, note::Note};
 
     use blstrs::Scalar;
@@ -329,7 +611,7 @@ mod test {
         let value_commitment = ValueCommitment::new(note.value, note.asset_generator());
 
         let merkle_note =
-            MerkleNote::new_for_miners_fee(&note, &value_commitment, &diffie_hellman_keys);
+            MerkleNote::new_for_miners_fee(&note, None, &value_commitment, &diffie_hellman_keys);
 
         assert_eq!(
             &merkle_note.note_encryption_keys,
@@ -359,20 +641,196 @@ mod test {
             &diffie_hellman_keys,
         );
         merkle_note
-            .decrypt_note_for_owner(receiver_key.incoming_view_key())
+            .decrypt_note_for_owner(receiver_key.view_key())
             .expect("should be able to decrypt note for owner");
         merkle_note
             .decrypt_note_for_spender(spender_key.outgoing_view_key())
             .expect("should be able to decrypt note for spender");
 
         assert!(merkle_note
-            .decrypt_note_for_owner(spender_key.incoming_view_key())
+            .decrypt_note_for_owner(spender_key.view_key())
             .is_err());
         assert!(merkle_note
             .decrypt_note_for_spender(receiver_key.outgoing_view_key())
             .is_err());
     }
 
+    #[test]
+    fn test_diversified_note_decryption() {
+        let spender_key = SaplingKey::generate_key();
+        let receiver_key = SaplingKey::generate_key();
+        let (index, recipient) = receiver_key.view_key().next_diversified_address(3).unwrap();
+        let note = Note::new(
+            recipient.owner(),
+            42,
+            "",
+            NATIVE_ASSET,
+            spender_key.public_address(),
+        );
+        let diffie_hellman_keys = EphemeralKeyPair::new();
+
+        let value_commitment = ValueCommitment::new(note.value, note.asset_generator());
+
+        let merkle_note = MerkleNote::new_diversified(
+            spender_key.outgoing_view_key(),
+            &recipient,
+            &note,
+            &value_commitment,
+            &diffie_hellman_keys,
+        );
+
+        // A single view key detects notes sent to any of its addresses
+        let (decrypted_index, decrypted) = merkle_note
+            .decrypt_note_for_owner(receiver_key.view_key())
+            .expect("should be able to decrypt diversified note");
+        assert_eq!(decrypted_index, index);
+        assert_eq!(decrypted.owner, note.owner);
+        assert_eq!(decrypted.value, 42);
+
+        let decrypted = merkle_note
+            .decrypt_note_for_spender(spender_key.outgoing_view_key())
+            .expect("should be able to decrypt diversified note for spender");
+        assert_eq!(decrypted.owner, note.owner);
+
+        assert!(merkle_note
+            .decrypt_note_for_owner(spender_key.view_key())
+            .is_err());
+        assert!(merkle_note
+            .decrypt_note_for_spender(receiver_key.outgoing_view_key())
+            .is_err());
+
+        let mut serialized = vec![];
+        merkle_note.write(&mut serialized).unwrap();
+        assert_eq!(serialized.len(), merkle_note_size(TransactionVersion::V3));
+        let deserialized = MerkleNote::read(&serialized[..], TransactionVersion::V3).unwrap();
+        let (decrypted_index, _) = deserialized
+            .decrypt_note_for_owner(receiver_key.view_key())
+            .unwrap();
+        assert_eq!(decrypted_index, index);
+    }
+
+    #[test]
+    fn test_diversified_note_regular_address() {
+        let spender_key = SaplingKey::generate_key();
+        let receiver_key = SaplingKey::generate_key();
+        let recipient = DiversifiedAddress::from(receiver_key.public_address());
+        let note = Note::new(
+            receiver_key.public_address(),
+            42,
+            "",
+            NATIVE_ASSET,
+            spender_key.public_address(),
+        );
+        let diffie_hellman_keys = EphemeralKeyPair::new();
+
+        let value_commitment = ValueCommitment::new(note.value, note.asset_generator());
+
+        let merkle_note = MerkleNote::new_diversified(
+            spender_key.outgoing_view_key(),
+            &recipient,
+            &note,
+            &value_commitment,
+            &diffie_hellman_keys,
+        );
+
+        // The note is not encrypted with the ephemeral key of the circuit
+        let diversified = merkle_note.diversified.as_ref().unwrap();
+        assert_ne!(
+            diversified.ephemeral_public_key,
+            merkle_note.ephemeral_public_key
+        );
+
+        let (index, decrypted) = merkle_note
+            .decrypt_note_for_owner(receiver_key.view_key())
+            .unwrap();
+        assert_eq!(index, 0);
+        assert_eq!(decrypted.owner, receiver_key.public_address());
+    }
+
+    #[test]
+    fn test_diversified_note_incoming_view_key() {
+        let spender_key = SaplingKey::generate_key();
+        let receiver_key = SaplingKey::generate_key();
+        let (_, recipient) = receiver_key.view_key().next_diversified_address(1).unwrap();
+
+        let encrypt = |recipient: &DiversifiedAddress| {
+            let note = Note::new(
+                recipient.owner(),
+                42,
+                "",
+                NATIVE_ASSET,
+                spender_key.public_address(),
+            );
+            let value_commitment = ValueCommitment::new(note.value, note.asset_generator());
+            MerkleNote::new_diversified(
+                spender_key.outgoing_view_key(),
+                recipient,
+                &note,
+                &value_commitment,
+                &EphemeralKeyPair::new(),
+            )
+        };
+
+        // The incoming view key alone cannot check the owner of diversified
+        // addresses, so it only accepts notes sent to the regular address
+        let regular = encrypt(&DiversifiedAddress::from(receiver_key.public_address()));
+        let decrypted = regular
+            .decrypt_note_for_incoming_view_key(receiver_key.incoming_view_key())
+            .unwrap();
+        assert_eq!(decrypted.owner, receiver_key.public_address());
+
+        let diversified = encrypt(&recipient);
+        assert!(diversified
+            .decrypt_note_for_incoming_view_key(receiver_key.incoming_view_key())
+            .is_err());
+        diversified
+            .decrypt_note_for_owner(receiver_key.view_key())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_diversified_note_forged_owner() {
+        let spender_key = SaplingKey::generate_key();
+        let receiver_key = SaplingKey::generate_key();
+
+        for index in [0, 1] {
+            let (_, recipient) = receiver_key
+                .view_key()
+                .next_diversified_address(index)
+                .unwrap();
+
+            // The sender encrypts the note to the transmission key of the
+            // receiver, but makes it spendable by itself
+            let forged_recipient = DiversifiedAddress {
+                owner: spender_key.public_address(),
+                ..recipient
+            };
+            let note = Note::new(
+                forged_recipient.owner(),
+                42,
+                "",
+                NATIVE_ASSET,
+                spender_key.public_address(),
+            );
+            let value_commitment = ValueCommitment::new(note.value, note.asset_generator());
+            let merkle_note = MerkleNote::new_diversified(
+                spender_key.outgoing_view_key(),
+                &forged_recipient,
+                &note,
+                &value_commitment,
+                &EphemeralKeyPair::new(),
+            );
+
+            assert!(matches!(
+                merkle_note.decrypt_note_for_owner(receiver_key.view_key()),
+                Err(e) if e.kind == IronfishErrorKind::InvalidDecryptionKey
+            ));
+            assert!(merkle_note
+                .decrypt_note_for_incoming_view_key(receiver_key.incoming_view_key())
+                .is_err());
+        }
+    }
+
     #[test]
     fn test_view_key_encryption_with_other_key() {
         let spender_key = SaplingKey::generate_key();
@@ -397,7 +855,7 @@ mod test {
         );
 
         assert!(merkle_note
-            .decrypt_note_for_owner(third_party_key.incoming_view_key())
+            .decrypt_note_for_owner(third_party_key.view_key())
             .is_err());
         assert!(merkle_note
             .decrypt_note_for_spender(third_party_key.outgoing_view_key())
@@ -425,7 +883,7 @@ mod test {
             &diffie_hellman_keys,
         );
         merkle_note
-            .decrypt_note_for_owner(spender_key.incoming_view_key())
+            .decrypt_note_for_owner(spender_key.view_key())
             .expect("should be able to decrypt note for owner");
         merkle_note
             .decrypt_note_for_spender(spender_key.outgoing_view_key())
@@ -435,7 +893,7 @@ mod test {
         let note_randomness: u64 = random();
         merkle_note.note_commitment = Scalar::from(note_randomness);
         assert!(merkle_note
-            .decrypt_note_for_owner(spender_key.incoming_view_key())
+            .decrypt_note_for_owner(spender_key.view_key())
             .is_err());
         assert!(merkle_note
             .decrypt_note_for_spender(spender_key.outgoing_view_key())
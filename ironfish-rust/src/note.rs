@@ -65,6 +65,110 @@ impl fmt::Display for Memo {
     }
 }
 
+/// Tag byte that starts a binary memo. Bytes from 0xF5 to 0xFF never start a
+/// valid UTF-8 string, so tagged memos cannot be confused with text memos.
+pub const MEMO_TAG_BINARY: u8 = 0xF5;
+
+/// Maximum size of the data of a binary memo, after the tag and length bytes
+pub const MEMO_BINARY_MAX_SIZE: usize = MEMO_SIZE - 2;
+
+/// Decoded content of a [`Memo`].
+///
+/// * An empty memo is all zeros, which is also the default memo.
+/// * A text memo is UTF-8 padded with zeros, as written by `Memo::from`, so
+///   text memos created before typed memos existed are decoded as text.
+/// * A binary memo is [`MEMO_TAG_BINARY`], the length of the data, the data,
+///   and zero padding.
+///
+/// Other tag bytes from 0xF6 to 0xFF are reserved, and decode as binary memos
+/// of all the [`MEMO_SIZE`] bytes until they are assigned.
+///
+/// Two of the requested memo types are deliberately left out:
+///
+/// * Reply-to addresses: an address takes all the 32 bytes of a memo, leaving
+///   no room for a tag, and the note already carries the address of its
+///   sender.
+/// * An explicit "empty" tag: the all-zero memo is already empty, and is what
+///   every client writes when there is no memo, so a second encoding of the
+///   same content would only let the two disagree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MemoContent {
+    Empty,
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+impl Memo {
+    /// Create a text memo. Unlike `Memo::from`, fails if the text does not
+    /// fit in [`MEMO_SIZE`] bytes instead of truncating it. The text cannot
+    /// contain NUL characters, which are used as padding.
+    pub fn from_text(text: &str) -> Result<Self, IronfishError> {
+        if text.len() > MEMO_SIZE || text.contains('\0') {
+            return Err(IronfishError::new(IronfishErrorKind::InvalidMemo));
+        }
+
+        Ok(Memo(str_to_array(text)))
+    }
+
+    /// Create a binary memo holding up to [`MEMO_BINARY_MAX_SIZE`] bytes
+    pub fn from_binary(data: &[u8]) -> Result<Self, IronfishError> {
+        if data.len() > MEMO_BINARY_MAX_SIZE {
+            return Err(IronfishError::new(IronfishErrorKind::InvalidMemo));
+        }
+
+        let mut memo = [0; MEMO_SIZE];
+        memo[0] = MEMO_TAG_BINARY;
+        memo[1] = data.len() as u8;
+        memo[2..2 + data.len()].copy_from_slice(data);
+
+        Ok(Memo(memo))
+    }
+
+    /// Create a memo from its decoded content
+    pub fn encode(content: &MemoContent) -> Result<Self, IronfishError> {
+        match content {
+            MemoContent::Empty => Ok(Memo::default()),
+            MemoContent::Text(text) => Self::from_text(text),
+            MemoContent::Binary(data) => Self::from_binary(data),
+        }
+    }
+
+    /// Decode the content of the memo.
+    ///
+    /// Memos that are not valid text and do not follow a known encoding were
+    /// written by clients that predate typed memos, or use a tag reserved for
+    /// a later encoding. They are returned as binary memos of all the
+    /// [`MEMO_SIZE`] bytes, so that no memo fails to decode.
+    pub fn decode(&self) -> MemoContent {
+        match self.0[0] {
+            MEMO_TAG_BINARY => {
+                let length = self.0[1] as usize;
+                if length > MEMO_BINARY_MAX_SIZE || self.0[2 + length..].iter().any(|b| *b != 0) {
+                    return MemoContent::Binary(self.0.to_vec());
+                }
+
+                MemoContent::Binary(self.0[2..2 + length].to_vec())
+            }
+            0xF6..=0xFF => MemoContent::Binary(self.0.to_vec()),
+            _ => {
+                let length = self
+                    .0
+                    .iter()
+                    .rposition(|b| *b != 0)
+                    .map_or(0, |position| position + 1);
+                if length == 0 {
+                    return MemoContent::Empty;
+                }
+
+                match std::str::from_utf8(&self.0[..length]) {
+                    Ok(text) if !text.contains('\0') => MemoContent::Text(text.to_string()),
+                    _ => MemoContent::Binary(self.0.to_vec()),
+                }
+            }
+        }
+    }
+}
+
 /// A note (think bank note) represents a value in the owner's "account".
 /// When spending, proof that the note exists in the tree needs to be provided,
 /// along with a nullifier key that is made public so the owner cannot attempt
@@ -199,7 +303,9 @@ impl<'a> Note {
     /// using a shared secret derived from the owner's public key.
     ///
     /// This function allows the owner to decrypt the note using the derived
-    /// shared secret and their own view key.
+    /// shared secret and their own view key. It is also used by the owner of
+    /// notes received on diversified addresses, whose owner address is
+    /// encrypted along with the note.
     pub(crate) fn from_spender_encrypted(
         public_address: SubgroupPoint,
         shared_secret: &[u8; 32],
@@ -361,9 +467,10 @@ impl<'a> Note {
 
 #[cfg(test)]
 mod test {
-    use super::{Memo, Note};
+    use super::{Memo, MemoContent, Note, MEMO_TAG_BINARY};
     use crate::{
         assets::asset_identifier::NATIVE_ASSET,
+        errors::IronfishErrorKind,
         keys::{shared_secret, EphemeralKeyPair, SaplingKey},
     };
 
@@ -459,4 +566,74 @@ mod test {
         let memo = Memo::from(string);
         assert_eq!(&memo.0[..6], b"a memo");
     }
+
+    #[test]
+    fn test_memo_round_trip() {
+        let contents = [
+            MemoContent::Empty,
+            MemoContent::Text("hello".to_string()),
+            MemoContent::Text("ünïcödé".to_string()),
+            MemoContent::Text("a".repeat(32)),
+            MemoContent::Binary(vec![]),
+            MemoContent::Binary(vec![0, 1, 0xff, 0]),
+            MemoContent::Binary(vec![0xff; 30]),
+        ];
+
+        for content in contents {
+            let memo = Memo::encode(&content).unwrap();
+            assert_eq!(memo.decode(), content);
+        }
+
+        assert_eq!(Memo::default().decode(), MemoContent::Empty);
+        assert_eq!(Memo::from_text("").unwrap(), Memo::default());
+    }
+
+    #[test]
+    fn test_memo_legacy() {
+        // Memos written with `From<&str>` decode as text
+        assert_eq!(
+            Memo::from("serialize me").decode(),
+            MemoContent::Text("serialize me".to_string())
+        );
+
+        // Untagged memos that are not UTF-8 decode as raw binary
+        let mut bytes = [0; 32];
+        bytes[0] = b'a';
+        bytes[1] = 0xc3;
+        assert_eq!(Memo(bytes).decode(), MemoContent::Binary(bytes.to_vec()));
+
+        bytes[1] = 0;
+        bytes[2] = b'b';
+        assert_eq!(Memo(bytes).decode(), MemoContent::Binary(bytes.to_vec()));
+    }
+
+    #[test]
+    fn test_memo_errors() {
+        let invalid_memo = |result: Result<_, crate::errors::IronfishError>| matches!(result, Err(e) if matches!(e.kind, IronfishErrorKind::InvalidMemo));
+
+        // Too long, where `From<&str>` would truncate
+        assert!(invalid_memo(Memo::from_text(&"a".repeat(33))));
+        assert!(invalid_memo(Memo::from_text("a\0b")));
+        assert!(invalid_memo(Memo::from_binary(&[1; 31])));
+    }
+
+    #[test]
+    fn test_memo_unknown_encodings() {
+        // Reserved tags
+        let mut bytes = [0; 32];
+        for tag in 0xF6..=0xFF {
+            bytes[0] = tag;
+            assert_eq!(Memo(bytes).decode(), MemoContent::Binary(bytes.to_vec()));
+        }
+
+        // Length past the end of the memo
+        bytes[0] = MEMO_TAG_BINARY;
+        bytes[1] = 31;
+        assert_eq!(Memo(bytes).decode(), MemoContent::Binary(bytes.to_vec()));
+
+        // Data after the declared length
+        bytes[1] = 2;
+        bytes[10] = 1;
+        assert_eq!(Memo(bytes).decode(), MemoContent::Binary(bytes.to_vec()));
+    }
 }
@@ -0,0 +1,303 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use blstrs::Scalar;
+use ironfish_zkp::constants::TREE_DEPTH;
+
+use crate::{
+    witness::{Witness, WitnessNode},
+    MerkleNoteHash,
+};
+
+/// Append-only Merkle tree of note commitments, with the same shape and
+/// hashing as the note tree maintained by the node: a binary tree of depth
+/// [`TREE_DEPTH`] where a node without a right sibling is hashed with itself.
+///
+/// Besides the leaves, the tree stores the hash of every node whose subtree is
+/// complete. Those hashes never change when new leaves are appended, so the
+/// root hash and the witnesses at any size can be computed by hashing at most
+/// one incomplete node per level.
+#[derive(Clone, Debug)]
+pub struct NoteCommitmentTree {
+    /// `levels[depth][index]` is the hash of the complete subtree of height
+    /// `depth` that starts at leaf `index << depth`. `levels[0]` contains the
+    /// leaves.
+    levels: Vec<Vec<Scalar>>,
+}
+
+impl Default for NoteCommitmentTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NoteCommitmentTree {
+    pub fn new() -> Self {
+        Self {
+            levels: vec![vec![]; TREE_DEPTH + 1],
+        }
+    }
+
+    /// Number of leaves in the tree
+    pub fn size(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size() == 0
+    }
+
+    /// Get the leaf at the given position
+    pub fn get(&self, position: usize) -> Option<MerkleNoteHash> {
+        self.levels[0]
+            .get(position)
+            .map(|hash| MerkleNoteHash(*hash))
+    }
+
+    /// Add a note commitment to the end of the tree, returning its position.
+    pub fn append(&mut self, note_hash: MerkleNoteHash) -> usize {
+        let position = self.size();
+        self.levels[0].push(note_hash.0);
+
+        // Store the hashes of the subtrees that were just completed
+        for depth in 0..TREE_DEPTH {
+            let level = &self.levels[depth];
+            if level.len() % 2 != 0 {
+                break;
+            }
+
+            let parent = MerkleNoteHash::combine_hash(
+                depth,
+                &level[level.len() - 2],
+                &level[level.len() - 1],
+            );
+            self.levels[depth + 1].push(parent);
+        }
+
+        position
+    }
+
+    /// Remove all the leaves after the first `size` ones, for example to undo
+    /// the notes added by blocks that were disconnected from the chain.
+    pub fn truncate(&mut self, size: usize) {
+        for (depth, level) in self.levels.iter_mut().enumerate() {
+            level.truncate(size >> depth);
+        }
+    }
+
+    /// Root hash of the tree with its current leaves, or `None` if the tree is
+    /// empty.
+    pub fn root_hash(&self) -> Option<Scalar> {
+        self.past_root_hash(self.size())
+    }
+
+    /// Root hash of the tree when it only contained the first `size` leaves.
+    /// Returns `None` if `size` is 0 or larger than the current size.
+    pub fn past_root_hash(&self, size: usize) -> Option<Scalar> {
+        if size == 0 || size > self.size() {
+            return None;
+        }
+
+        Some(self.node_hash(TREE_DEPTH, 0, size))
+    }
+
+    /// Witness proving that the leaf at `position` is part of the tree with its
+    /// current leaves. Returns `None` if there is no leaf at `position`.
+    pub fn witness(&self, position: usize) -> Option<Witness> {
+        self.past_witness(position, self.size())
+    }
+
+    /// Witness proving that the leaf at `position` was part of the tree when
+    /// it contained the first `size` leaves.
+    pub fn past_witness(&self, position: usize, size: usize) -> Option<Witness> {
+        if position >= size || size > self.size() {
+            return None;
+        }
+
+        // Hash of the node that contains the leaf at the current depth
+        let mut current = self.levels[0][position];
+        let mut auth_path = Vec::with_capacity(TREE_DEPTH);
+
+        for depth in 0..TREE_DEPTH {
+            let index = position >> depth;
+
+            let node = if index % 2 == 0 {
+                let sibling = if (index + 1) << depth < size {
+                    self.node_hash(depth, index + 1, size)
+                } else {
+                    current
+                };
+                current = MerkleNoteHash::combine_hash(depth, &current, &sibling);
+                WitnessNode::Left(sibling)
+            } else {
+                let sibling = self.node_hash(depth, index - 1, size);
+                current = MerkleNoteHash::combine_hash(depth, &sibling, &current);
+                WitnessNode::Right(sibling)
+            };
+
+            auth_path.push(node);
+        }
+
+        Some(Witness {
+            tree_size: size,
+            root_hash: current,
+            auth_path,
+        })
+    }
+
+    /// The frontier of the tree: `frontier[depth]` is the complete node at
+    /// `depth` that does not have a right sibling yet, if any. This is all
+    /// that is needed to compute the hashes of the nodes added by the next
+    /// leaves, see [`crate::IncrementalWitness::from_frontier`].
+    pub fn frontier(&self) -> Vec<Option<Scalar>> {
+        let size = self.size();
+        (0..TREE_DEPTH)
+            .map(|depth| match (size >> depth) % 2 {
+                1 => self.complete_node_hash(depth, (size >> depth) - 1),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Hash of the node at the given depth and index, if all the leaves of its
+    /// subtree are in the tree.
+    pub(crate) fn complete_node_hash(&self, depth: usize, index: usize) -> Option<Scalar> {
+        self.levels[depth].get(index).copied()
+    }
+
+    /// Hash of the node at the given depth and index, in the tree that
+    /// contains the first `size` leaves. The node must contain at least one of
+    /// those leaves.
+    fn node_hash(&self, depth: usize, index: usize, size: usize) -> Scalar {
+        let first_leaf = index << depth;
+        debug_assert!(first_leaf < size);
+
+        // Complete subtrees have their hash stored
+        if first_leaf + (1 << depth) <= size {
+            return self.levels[depth][index];
+        }
+
+        let left = self.node_hash(depth - 1, index * 2, size);
+        let right = if (index * 2 + 1) << (depth - 1) < size {
+            self.node_hash(depth - 1, index * 2 + 1, size)
+        } else {
+            left
+        };
+
+        MerkleNoteHash::combine_hash(depth - 1, &left, &right)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use blstrs::Scalar;
+    use ironfish_zkp::constants::TREE_DEPTH;
+
+    use super::NoteCommitmentTree;
+    use crate::{
+        witness::{WitnessNode, WitnessTrait},
+        MerkleNoteHash,
+    };
+
+    fn make_tree(size: u64) -> NoteCommitmentTree {
+        let mut tree = NoteCommitmentTree::new();
+        for leaf in 0..size {
+            tree.append(MerkleNoteHash(Scalar::from(leaf + 1)));
+        }
+        tree
+    }
+
+    #[test]
+    fn test_empty_tree() {
+        let tree = NoteCommitmentTree::new();
+
+        assert!(tree.is_empty());
+        assert_eq!(tree.root_hash(), None);
+        assert!(tree.witness(0).is_none());
+    }
+
+    #[test]
+    fn test_root_hash() {
+        let leaf = Scalar::from(1);
+        let tree = make_tree(1);
+
+        let mut expected = leaf;
+        for depth in 0..TREE_DEPTH {
+            expected = MerkleNoteHash::combine_hash(depth, &expected, &expected);
+        }
+        assert_eq!(tree.root_hash(), Some(expected));
+
+        let tree = make_tree(3);
+        let left = MerkleNoteHash::combine_hash(0, &Scalar::from(1), &Scalar::from(2));
+        let right = MerkleNoteHash::combine_hash(0, &Scalar::from(3), &Scalar::from(3));
+        let mut expected = MerkleNoteHash::combine_hash(1, &left, &right);
+        for depth in 2..TREE_DEPTH {
+            expected = MerkleNoteHash::combine_hash(depth, &expected, &expected);
+        }
+        assert_eq!(tree.root_hash(), Some(expected));
+    }
+
+    #[test]
+    fn test_past_root_hash() {
+        let tree = make_tree(7);
+
+        for size in 1..=7 {
+            assert_eq!(
+                tree.past_root_hash(size),
+                make_tree(size as u64).root_hash()
+            );
+        }
+        assert_eq!(tree.past_root_hash(0), None);
+        assert_eq!(tree.past_root_hash(8), None);
+    }
+
+    #[test]
+    fn test_witness() {
+        let tree = make_tree(7);
+
+        for position in 0..7 {
+            let witness = tree.witness(position).unwrap();
+            let leaf = tree.get(position).unwrap();
+
+            assert_eq!(witness.auth_path.len(), TREE_DEPTH);
+            assert_eq!(witness.tree_size(), 7);
+            assert_eq!(witness.root_hash(), tree.root_hash().unwrap());
+            assert!(witness.verify(&leaf));
+            assert!(!witness.verify(&MerkleNoteHash(Scalar::from(100))));
+        }
+
+        // The last leaf has no sibling, so it is its own sibling
+        let witness = tree.witness(6).unwrap();
+        assert_eq!(witness.auth_path[0], WitnessNode::Left(Scalar::from(7)));
+
+        for size in 1..7 {
+            for position in 0..size {
+                let witness = tree.past_witness(position, size).unwrap();
+                assert_eq!(witness.root_hash(), tree.past_root_hash(size).unwrap());
+                assert!(witness.verify(&tree.get(position).unwrap()));
+            }
+        }
+
+        assert!(tree.witness(7).is_none());
+        assert!(tree.past_witness(3, 3).is_none());
+    }
+
+    #[test]
+    fn test_truncate() {
+        let mut tree = make_tree(9);
+        tree.truncate(5);
+
+        assert_eq!(tree.size(), 5);
+        assert_eq!(tree.root_hash(), make_tree(5).root_hash());
+
+        // Appending after truncating gives the same tree as never having
+        // appended the removed leaves
+        tree.append(MerkleNoteHash(Scalar::from(6)));
+        assert_eq!(tree.root_hash(), make_tree(6).root_hash());
+        assert_eq!(tree.witness(5), make_tree(6).witness(5));
+
+        tree.truncate(0);
+        assert!(tree.is_empty());
+    }
+}
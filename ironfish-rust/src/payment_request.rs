@@ -0,0 +1,481 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Payment requests, used by merchants to ask for a specific amount of an
+//! asset to be sent to one of their addresses.
+//!
+//! A request can be shared as a URI, for example in a QR code:
+//!
+//! ```text
+//! ironfish:ifaddr1...?amount=100&asset=51f3...&memo=order%2042&label=Coffee&expiration=120000
+//! ```
+//!
+//! The address is Bech32m encoded for the expected network. `amount` is
+//! required, and the other parameters are optional: `asset` is the hex asset
+//! identifier (the native asset if omitted), `memo` and `label` are
+//! percent-encoded, and `expiration` is the block sequence after which the
+//! request should no longer be paid. Unknown parameters are ignored, unless
+//! their name starts with `req-`, in which case the request is rejected.
+//!
+//! Requests also have a compact binary form, see [`PaymentRequest::write`].
+//!
+//! Requests are always paid to a regular [`PublicAddress`].
+//! [`DiversifiedAddress`](crate::keys::DiversifiedAddress)es are not
+//! supported: they have no Bech32m encoding, and can only be paid in
+//! transactions of a version that has diversified notes, which payers cannot
+//! be assumed to support yet.
+
+use std::io;
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::{
+    assets::asset_identifier::{AssetIdentifier, NATIVE_ASSET},
+    errors::{IronfishError, IronfishErrorKind},
+    keys::{Network, PublicAddress},
+    note::{Memo, Note, MEMO_SIZE},
+    serializing::{bytes_to_hex, hex_to_bytes},
+};
+
+pub const PAYMENT_REQUEST_URI_SCHEME: &str = "ironfish";
+
+/// Maximum size in bytes of the label of a request
+pub const LABEL_MAX_SIZE: usize = 64;
+
+const PAYMENT_REQUEST_FORMAT_VERSION: u8 = 1;
+
+const FLAG_LABEL: u8 = 1 << 0;
+const FLAG_EXPIRATION: u8 = 1 << 1;
+
+/// A request for a payment of `value` of `asset_id` to `recipient`
+#[derive(Clone, Debug, PartialEq)]
+pub struct PaymentRequest {
+    /// Regular public address of the merchant, diversified addresses are not
+    /// supported
+    pub recipient: PublicAddress,
+    pub value: u64,
+    pub asset_id: AssetIdentifier,
+    pub memo: Memo,
+    /// Human-readable description of the request, shown to the payer
+    pub label: Option<String>,
+    /// Block sequence after which the request should no longer be paid
+    pub expiration: Option<u32>,
+}
+
+impl PaymentRequest {
+    /// Create a request for `value` of the native asset, with an empty memo
+    pub fn new(recipient: PublicAddress, value: u64) -> Self {
+        PaymentRequest {
+            recipient,
+            value,
+            asset_id: NATIVE_ASSET,
+            memo: Memo::default(),
+            label: None,
+            expiration: None,
+        }
+    }
+
+    /// Check that the fields of the request are valid: the value is not zero,
+    /// the label fits in [`LABEL_MAX_SIZE`] bytes and the expiration is not
+    /// zero.
+    pub fn validate(&self) -> Result<(), IronfishError> {
+        if self.value == 0 {
+            return Err(IronfishError::new(IronfishErrorKind::IllegalValue));
+        }
+
+        if let Some(label) = &self.label {
+            if label.len() > LABEL_MAX_SIZE {
+                return Err(IronfishError::new(IronfishErrorKind::InvalidData));
+            }
+        }
+
+        if self.expiration == Some(0) {
+            return Err(IronfishError::new(IronfishErrorKind::InvalidData));
+        }
+
+        Ok(())
+    }
+
+    /// Whether the request should no longer be paid at the given block
+    /// sequence
+    pub fn is_expired(&self, sequence: u32) -> bool {
+        matches!(self.expiration, Some(expiration) if sequence > expiration)
+    }
+
+    /// Create the note that pays this request, to be added to a transaction
+    /// with [`crate::ProposedTransaction::add_output`].
+    pub fn to_note(&self, sender: PublicAddress) -> Result<Note, IronfishError> {
+        self.validate()?;
+
+        Ok(Note::new(
+            self.recipient,
+            self.value,
+            self.memo,
+            self.asset_id,
+            sender,
+        ))
+    }
+
+    /// Encode the request as a URI, with the recipient address encoded for
+    /// `network`.
+    pub fn to_uri(&self, network: Network) -> Result<String, IronfishError> {
+        self.validate()?;
+
+        let mut uri = format!(
+            "{}:{}?amount={}",
+            PAYMENT_REQUEST_URI_SCHEME,
+            self.recipient.to_bech32(network),
+            self.value
+        );
+
+        if self.asset_id != NATIVE_ASSET {
+            uri.push_str("&asset=");
+            uri.push_str(&bytes_to_hex(self.asset_id.as_bytes()));
+        }
+
+        // Trailing zeros are padding, and are restored when parsing
+        let memo_length = self
+            .memo
+            .0
+            .iter()
+            .rposition(|byte| *byte != 0)
+            .map_or(0, |position| position + 1);
+        if memo_length > 0 {
+            uri.push_str("&memo=");
+            uri.push_str(&percent_encode(&self.memo.0[..memo_length]));
+        }
+
+        if let Some(label) = &self.label {
+            uri.push_str("&label=");
+            uri.push_str(&percent_encode(label.as_bytes()));
+        }
+
+        if let Some(expiration) = self.expiration {
+            uri.push_str(&format!("&expiration={}", expiration));
+        }
+
+        Ok(uri)
+    }
+
+    /// Parse a request from a URI. Fails if the recipient address is not
+    /// encoded for `network`.
+    pub fn from_uri(uri: &str, network: Network) -> Result<Self, IronfishError> {
+        let invalid = || IronfishError::new(IronfishErrorKind::InvalidData);
+
+        let rest = uri
+            .strip_prefix(PAYMENT_REQUEST_URI_SCHEME)
+            .and_then(|rest| rest.strip_prefix(':'))
+            .ok_or_else(invalid)?;
+        let (address, query) = rest.split_once('?').unwrap_or((rest, ""));
+
+        let mut request = PaymentRequest::new(PublicAddress::from_bech32(address, network)?, 0);
+        let mut seen = vec![];
+
+        for parameter in query.split('&').filter(|parameter| !parameter.is_empty()) {
+            let (name, value) = parameter.split_once('=').ok_or_else(invalid)?;
+            if seen.contains(&name) {
+                return Err(invalid());
+            }
+            seen.push(name);
+
+            match name {
+                "amount" => request.value = value.parse().map_err(|_| invalid())?,
+                "asset" => {
+                    let bytes = hex_to_bytes(value).map_err(|_| {
+                        IronfishError::new(IronfishErrorKind::InvalidAssetIdentifier)
+                    })?;
+                    request.asset_id = AssetIdentifier::new(bytes)?;
+                }
+                "memo" => {
+                    let bytes = percent_decode(value)?;
+                    if bytes.len() > MEMO_SIZE {
+                        return Err(IronfishError::new(IronfishErrorKind::InvalidMemo));
+                    }
+                    request.memo.0[..bytes.len()].copy_from_slice(&bytes);
+                }
+                "label" => {
+                    let label = String::from_utf8(percent_decode(value)?)?;
+                    request.label = Some(label);
+                }
+                "expiration" => {
+                    request.expiration = Some(value.parse().map_err(|_| invalid())?);
+                }
+                _ if name.starts_with("req-") => return Err(invalid()),
+                _ => {}
+            }
+        }
+
+        if !seen.contains(&"amount") {
+            return Err(invalid());
+        }
+
+        request.validate()?;
+        Ok(request)
+    }
+
+    /// Load a request stored with [`PaymentRequest::write`]
+    pub fn read<R: io::Read>(mut reader: R) -> Result<Self, IronfishError> {
+        let format_version = reader.read_u8()?;
+        if format_version != PAYMENT_REQUEST_FORMAT_VERSION {
+            return Err(IronfishError::new(IronfishErrorKind::InvalidData));
+        }
+
+        let recipient = PublicAddress::read(&mut reader)?;
+        let value = reader.read_u64::<LittleEndian>()?;
+        let asset_id = AssetIdentifier::read(&mut reader)?;
+        let mut memo = Memo::default();
+        reader.read_exact(&mut memo.0)?;
+
+        let flags = reader.read_u8()?;
+        if flags & !(FLAG_LABEL | FLAG_EXPIRATION) != 0 {
+            return Err(IronfishError::new(IronfishErrorKind::InvalidData));
+        }
+
+        let label = if flags & FLAG_LABEL != 0 {
+            let length = reader.read_u8()? as usize;
+            let mut label = vec![0; length];
+            reader.read_exact(&mut label)?;
+            Some(String::from_utf8(label)?)
+        } else {
+            None
+        };
+
+        let expiration = if flags & FLAG_EXPIRATION != 0 {
+            Some(reader.read_u32::<LittleEndian>()?)
+        } else {
+            None
+        };
+
+        let request = PaymentRequest {
+            recipient,
+            value,
+            asset_id,
+            memo,
+            label,
+            expiration,
+        };
+        request.validate()?;
+
+        Ok(request)
+    }
+
+    /// Store the request in a compact binary form: format version, recipient,
+    /// value, asset id, memo, a byte of flags for the optional fields, then
+    /// the length-prefixed label and the expiration, if present.
+    pub fn write<W: io::Write>(&self, mut writer: W) -> Result<(), IronfishError> {
+        self.validate()?;
+
+        writer.write_u8(PAYMENT_REQUEST_FORMAT_VERSION)?;
+        self.recipient.write(&mut writer)?;
+        writer.write_u64::<LittleEndian>(self.value)?;
+        self.asset_id.write(&mut writer)?;
+        writer.write_all(&self.memo.0)?;
+
+        let mut flags = 0;
+        if self.label.is_some() {
+            flags |= FLAG_LABEL;
+        }
+        if self.expiration.is_some() {
+            flags |= FLAG_EXPIRATION;
+        }
+        writer.write_u8(flags)?;
+
+        if let Some(label) = &self.label {
+            writer.write_u8(label.len() as u8)?;
+            writer.write_all(label.as_bytes())?;
+        }
+        if let Some(expiration) = self.expiration {
+            writer.write_u32::<LittleEndian>(expiration)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Percent-encode all the bytes except the unreserved characters of RFC 3986
+fn percent_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len());
+    for byte in bytes {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(*byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn percent_decode(value: &str) -> Result<Vec<u8>, IronfishError> {
+    let invalid = || IronfishError::new(IronfishErrorKind::InvalidData);
+
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%' {
+            // `from_str_radix` alone would accept a sign, as in `%+1`
+            let hex = value.get(index + 1..index + 3).ok_or_else(invalid)?;
+            if !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+                return Err(invalid());
+            }
+            decoded.push(u8::from_str_radix(hex, 16).map_err(|_| invalid())?);
+            index += 3;
+        } else {
+            decoded.push(bytes[index]);
+            index += 1;
+        }
+    }
+
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod test {
+    use super::PaymentRequest;
+    use crate::{
+        assets::{asset::Asset, asset_identifier::NATIVE_ASSET},
+        errors::IronfishErrorKind,
+        keys::Network,
+        note::Memo,
+        transaction::{ProposedTransaction, TransactionVersion},
+        SaplingKey,
+    };
+
+    fn make_request() -> PaymentRequest {
+        let merchant = SaplingKey::generate_key();
+        let asset = Asset::new(merchant.public_address(), "Testcoin", "").unwrap();
+
+        PaymentRequest {
+            recipient: merchant.public_address(),
+            value: 1234,
+            asset_id: *asset.id(),
+            memo: Memo::from("order #42"),
+            label: Some("Coffee & cake".to_string()),
+            expiration: Some(120_000),
+        }
+    }
+
+    #[test]
+    fn test_uri_round_trip() {
+        let request = make_request();
+
+        let uri = request.to_uri(Network::Mainnet).unwrap();
+        assert!(uri.starts_with("ironfish:ifaddr1"));
+        assert!(uri.contains("&memo=order%20%2342&"));
+        assert!(uri.contains("&label=Coffee%20%26%20cake&"));
+
+        let parsed = PaymentRequest::from_uri(&uri, Network::Mainnet).unwrap();
+        assert_eq!(parsed, request);
+
+        // Only the amount is required
+        let minimal = PaymentRequest::new(request.recipient, 5);
+        let uri = minimal.to_uri(Network::Testnet).unwrap();
+        assert_eq!(
+            uri,
+            format!(
+                "ironfish:{}?amount=5",
+                request.recipient.to_bech32(Network::Testnet)
+            )
+        );
+        let parsed = PaymentRequest::from_uri(&uri, Network::Testnet).unwrap();
+        assert_eq!(parsed.asset_id, NATIVE_ASSET);
+        assert_eq!(parsed, minimal);
+    }
+
+    #[test]
+    fn test_binary_round_trip() {
+        let request = make_request();
+
+        let mut serialized = vec![];
+        request.write(&mut serialized).unwrap();
+        let deserialized = PaymentRequest::read(&serialized[..]).unwrap();
+        assert_eq!(deserialized, request);
+
+        let minimal = PaymentRequest::new(request.recipient, 5);
+        let mut serialized = vec![];
+        minimal.write(&mut serialized).unwrap();
+        assert_eq!(serialized.len(), 1 + 32 + 8 + 32 + 32 + 1);
+        assert_eq!(PaymentRequest::read(&serialized[..]).unwrap(), minimal);
+    }
+
+    #[test]
+    fn test_invalid_uris() {
+        let request = make_request();
+        let address = request.recipient.to_bech32(Network::Mainnet);
+
+        let invalid_uris = [
+            // Missing amount
+            format!("ironfish:{}", address),
+            format!("ironfish:{}?amount=0", address),
+            format!("ironfish:{}?amount=-1", address),
+            format!("ironfish:{}?amount=1&amount=2", address),
+            format!("bitcoin:{}?amount=1", address),
+            // Memo longer than MEMO_SIZE
+            format!("ironfish:{}?amount=1&memo={}", address, "a".repeat(33)),
+            format!("ironfish:{}?amount=1&memo=%4", address),
+            format!("ironfish:{}?amount=1&memo=%+1", address),
+            format!("ironfish:{}?amount=1&label={}", address, "a".repeat(65)),
+            format!("ironfish:{}?amount=1&expiration=0", address),
+            format!("ironfish:{}?amount=1&req-unknown=1", address),
+        ];
+        for uri in invalid_uris.iter() {
+            assert!(
+                PaymentRequest::from_uri(uri, Network::Mainnet).is_err(),
+                "{}",
+                uri
+            );
+        }
+
+        // Unknown optional parameters are ignored
+        let uri = format!("ironfish:{}?amount=1&unknown=1", address);
+        assert!(PaymentRequest::from_uri(&uri, Network::Mainnet).is_ok());
+
+        // The address must be for the expected network
+        let uri = format!("ironfish:{}?amount=1", address);
+        assert!(matches!(
+            PaymentRequest::from_uri(&uri, Network::Testnet),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidBech32)
+        ));
+
+        // Asset identifiers must be valid
+        let uri = format!("ironfish:{}?amount=1&asset={}", address, "00".repeat(32));
+        assert!(matches!(
+            PaymentRequest::from_uri(&uri, Network::Mainnet),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidAssetIdentifier)
+        ));
+    }
+
+    #[test]
+    fn test_expiration() {
+        let request = make_request();
+
+        assert!(!request.is_expired(119_999));
+        assert!(!request.is_expired(120_000));
+        assert!(request.is_expired(120_001));
+        assert!(!PaymentRequest::new(request.recipient, 1).is_expired(u32::MAX));
+    }
+
+    #[test]
+    fn test_to_note() {
+        let request = make_request();
+        let payer = SaplingKey::generate_key();
+
+        let note = request.to_note(payer.public_address()).unwrap();
+        assert_eq!(note.owner(), request.recipient);
+        assert_eq!(note.value(), request.value);
+        assert_eq!(note.asset_id(), &request.asset_id);
+        assert_eq!(note.memo(), request.memo);
+        assert_eq!(note.sender(), payer.public_address());
+
+        let mut transaction = ProposedTransaction::new(TransactionVersion::latest());
+        transaction.add_output(note).unwrap();
+
+        let zero = PaymentRequest::new(request.recipient, 0);
+        assert!(matches!(
+            zero.to_note(payer.public_address()),
+            Err(e) if matches!(e.kind, IronfishErrorKind::IllegalValue)
+        ));
+    }
+}
@@ -0,0 +1,173 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use bellperson::groth16::PreparedVerifyingKey;
+use blstrs::Bls12;
+
+use crate::{errors::IronfishError, sapling_bls12::SAPLING};
+
+use super::{proof_batch::ProofBatch, Transaction};
+
+/// Incremental version of [`super::batch_verify_transactions`].
+///
+/// Transactions can be queued one at a time as they become available (for
+/// example, as they arrive from the network or while a block is being
+/// received). Each call to [`TransactionBatchVerifier::queue`] immediately
+/// runs the checks that are cheap to perform on a single transaction, while
+/// the expensive zero knowledge proof verification is deferred to
+/// [`TransactionBatchVerifier::finalize`], which verifies the proofs of all the
+/// queued transactions in one batch per circuit.
+#[derive(Default)]
+pub struct TransactionBatchVerifier {
+    batch: ProofBatch,
+
+    /// Number of transactions that were successfully queued
+    transaction_count: usize,
+}
+
+impl TransactionBatchVerifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check the signatures and all the other values of this transaction that
+    /// can be verified without its proofs, then queue the proofs to be
+    /// verified in [`TransactionBatchVerifier::finalize`].
+    ///
+    /// If an error is returned the transaction is invalid, and it is not
+    /// added to the batch, so the verifier can keep being used for other
+    /// transactions.
+    pub fn queue(&mut self, transaction: &Transaction) -> Result<(), IronfishError> {
+        self.batch.add_transaction(transaction)?;
+        self.transaction_count += 1;
+
+        Ok(())
+    }
+
+    /// Number of transactions queued so far
+    pub fn len(&self) -> usize {
+        self.transaction_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transaction_count == 0
+    }
+
+    /// Verify the spend, output and mint proofs of all the queued
+    /// transactions. Succeeds only if every proof is valid.
+    pub fn finalize(self) -> Result<(), IronfishError> {
+        self.internal_finalize(
+            &SAPLING.spend_verifying_key,
+            &SAPLING.output_verifying_key,
+            &SAPLING.mint_verifying_key,
+        )
+    }
+
+    fn internal_finalize(
+        self,
+        spend_verifying_key: &PreparedVerifyingKey<Bls12>,
+        output_verifying_key: &PreparedVerifyingKey<Bls12>,
+        mint_verifying_key: &PreparedVerifyingKey<Bls12>,
+    ) -> Result<(), IronfishError> {
+        self.batch.verify(
+            spend_verifying_key,
+            output_verifying_key,
+            mint_verifying_key,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TransactionBatchVerifier;
+    use crate::{
+        assets::{asset::Asset, asset_identifier::NATIVE_ASSET},
+        errors::IronfishErrorKind,
+        note::Note,
+        sapling_bls12::SAPLING,
+        test_util::make_fake_witness,
+        transaction::{ProposedTransaction, TransactionVersion},
+        SaplingKey,
+    };
+    use ironfish_zkp::proofs::MintAsset;
+    use rand::thread_rng;
+
+    #[test]
+    fn test_batch_verifier() {
+        let key = SaplingKey::generate_key();
+        let other_key = SaplingKey::generate_key();
+
+        let in_note = Note::new(
+            key.public_address(),
+            42,
+            "",
+            NATIVE_ASSET,
+            key.public_address(),
+        );
+        let out_note = Note::new(
+            other_key.public_address(),
+            40,
+            "",
+            NATIVE_ASSET,
+            key.public_address(),
+        );
+        let witness = make_fake_witness(&in_note);
+
+        let mut proposed_transaction1 = ProposedTransaction::new(TransactionVersion::latest());
+        proposed_transaction1.add_spend(in_note, &witness).unwrap();
+        proposed_transaction1.add_output(out_note).unwrap();
+        let transaction1 = proposed_transaction1.post(&key, None, 1).unwrap();
+
+        let asset = Asset::new(other_key.public_address(), "Othercoin", "").unwrap();
+        let mut proposed_transaction2 = ProposedTransaction::new(TransactionVersion::latest());
+        proposed_transaction2.add_mint(asset, 5).unwrap();
+        let transaction2 = proposed_transaction2.post(&other_key, None, 0).unwrap();
+
+        let mut invalid_transaction = transaction1.clone();
+        invalid_transaction.randomized_public_key = transaction2.randomized_public_key.clone();
+
+        let mut verifier = TransactionBatchVerifier::new();
+        assert!(verifier.is_empty());
+
+        verifier
+            .queue(&transaction1)
+            .expect("should be able to queue a valid transaction");
+        assert!(matches!(
+            verifier.queue(&invalid_transaction),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidSpendSignature)
+        ));
+        verifier
+            .queue(&transaction2)
+            .expect("should be able to queue a valid transaction");
+
+        assert_eq!(verifier.len(), 2);
+        verifier
+            .finalize()
+            .expect("should be able to verify the queued transactions");
+
+        // Proofs are only checked when finalizing
+        let wrong_mint_params =
+            bellperson::groth16::generate_random_parameters::<blstrs::Bls12, _, _>(
+                MintAsset {
+                    proof_generation_key: None,
+                    public_key_randomness: None,
+                },
+                &mut thread_rng(),
+            )
+            .unwrap();
+        let wrong_mint_vk = bellperson::groth16::prepare_verifying_key(&wrong_mint_params.vk);
+
+        let mut verifier = TransactionBatchVerifier::new();
+        verifier.queue(&transaction1).unwrap();
+        verifier.queue(&transaction2).unwrap();
+        assert!(matches!(
+            verifier.internal_finalize(
+                &SAPLING.spend_verifying_key,
+                &SAPLING.output_verifying_key,
+                &wrong_mint_vk,
+            ),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidMintProof)
+        ));
+    }
+}
@@ -6,8 +6,13 @@ use std::io;
 
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 
+use ironfish_zkp::constants::ASSET_ID_LENGTH;
+
 use crate::{assets::asset_identifier::AssetIdentifier, errors::IronfishError};
 
+/// Size of a serialized [`BurnDescription`]: asset identifier and value.
+pub const BURN_DESCRIPTION_SIZE: usize = ASSET_ID_LENGTH + 8;
+
 /// Parameters used to build a burn description
 pub struct BurnBuilder {
     /// Identifier of the Asset to be burned
@@ -28,6 +33,16 @@ impl BurnBuilder {
             value: self.value,
         }
     }
+
+    pub fn read<R: io::Read>(reader: R) -> Result<Self, IronfishError> {
+        let description = BurnDescription::read(reader)?;
+
+        Ok(Self::new(description.asset_id, description.value))
+    }
+
+    pub fn write<W: io::Write>(&self, writer: W) -> Result<(), IronfishError> {
+        self.build().write(writer)
+    }
 }
 
 /// This description represents an action to decrease the supply of an existing
@@ -19,15 +19,16 @@ use jubjub::ExtendedPoint;
 use rand::thread_rng;
 
 use crate::{
-    assets::asset::Asset,
+    assets::asset::{Asset, ASSET_LENGTH},
     errors::{IronfishError, IronfishErrorKind},
+    keys::PUBLIC_ADDRESS_SIZE,
     sapling_bls12::SAPLING,
     serializing::read_scalar,
     transaction::TransactionVersion,
     PublicAddress, SaplingKey,
 };
 
-use super::utils::verify_mint_proof;
+use super::{outputs::PROOF_SIZE, utils::verify_mint_proof, TRANSACTION_SIGNATURE_SIZE};
 
 /// Parameters used to build a circuit that verifies an asset can be minted with
 /// a given key
@@ -57,6 +58,52 @@ impl MintBuilder {
         self
     }
 
+    pub fn read<R: io::Read>(mut reader: R) -> Result<Self, IronfishError> {
+        let asset = Asset::read(&mut reader)?;
+        let value = reader.read_u64::<LittleEndian>()?;
+        let transfer_ownership_to = match reader.read_u8()? {
+            0 => None,
+            1 => Some(PublicAddress::read(&mut reader)?),
+            _ => return Err(IronfishError::new(IronfishErrorKind::InvalidData)),
+        };
+
+        Ok(Self {
+            asset,
+            value,
+            transfer_ownership_to,
+        })
+    }
+
+    pub fn write<W: io::Write>(&self, mut writer: W) -> Result<(), IronfishError> {
+        self.asset.write(&mut writer)?;
+        writer.write_u64::<LittleEndian>(self.value)?;
+        if let Some(ref transfer_ownership_to) = self.transfer_ownership_to {
+            writer.write_u8(1)?;
+            transfer_ownership_to.write(&mut writer)?;
+        } else {
+            writer.write_u8(0)?;
+        }
+
+        Ok(())
+    }
+
+    /// Size of the [`MintDescription`] built from this builder, once
+    /// serialized in a transaction of the given version.
+    pub fn description_size(&self, version: TransactionVersion) -> usize {
+        let mut size = PROOF_SIZE as usize + ASSET_LENGTH + 8 + TRANSACTION_SIGNATURE_SIZE;
+
+        if version.has_mint_transfer_ownership_to() {
+            // Owner, and a flag for whether ownership is transferred
+            size += PUBLIC_ADDRESS_SIZE + 1;
+
+            if self.transfer_ownership_to.is_some() {
+                size += PUBLIC_ADDRESS_SIZE;
+            }
+        }
+
+        size
+    }
+
     pub fn build(
         &self,
         proof_generation_key: &ProofGenerationKey,
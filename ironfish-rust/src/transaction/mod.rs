@@ -14,16 +14,17 @@ use crate::{
         asset_identifier::{AssetIdentifier, NATIVE_ASSET},
     },
     errors::{IronfishError, IronfishErrorKind},
-    keys::{PublicAddress, SaplingKey},
+    keys::{DiversifiedAddress, PublicAddress, SaplingKey},
     note::Note,
     sapling_bls12::SAPLING,
+    serializing::read_scalar,
     witness::WitnessTrait,
     OutgoingViewKey, OutputDescription, SpendDescription, ViewKey,
 };
 
 use rand::{rngs::OsRng, thread_rng};
 
-use bellperson::groth16::{verify_proofs_batch, PreparedVerifyingKey};
+use bellperson::groth16::PreparedVerifyingKey;
 use blake2b_simd::Params as Blake2b;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use group::GroupEncoding;
@@ -39,23 +40,36 @@ use ironfish_zkp::{
 };
 
 use std::{
+    collections::{HashMap, HashSet},
     io::{self, Write},
     iter,
     slice::Iter,
+    thread,
 };
 
 use self::{
-    burns::{BurnBuilder, BurnDescription},
+    burns::{BurnBuilder, BurnDescription, BURN_DESCRIPTION_SIZE},
     mints::{MintBuilder, MintDescription, UnsignedMintDescription},
+    note_selection::{select_notes, SelectionStrategy, SpendableNote},
+    outputs::output_description_size,
+    proof_batch::ProofBatch,
+    spends::SPEND_DESCRIPTION_SIZE,
     unsigned::UnsignedTransaction,
 };
 
 pub mod burns;
 pub mod mints;
+pub mod note_decryption;
+pub mod note_selection;
 pub mod outputs;
+pub mod payment_disclosure;
+pub mod reserves;
 pub mod spends;
 pub mod unsigned;
 
+mod batch_verifier;
+mod proof_batch;
+mod summary;
 mod utils;
 mod value_balances;
 mod version;
@@ -63,8 +77,12 @@ mod version;
 #[cfg(test)]
 mod tests;
 
+pub use batch_verifier::TransactionBatchVerifier;
+pub use summary::{BurnSummary, MintSummary, OutputSummary, SpendSummary, TransactionSummary};
 pub use version::TransactionVersion;
 
+pub(crate) use utils::verify_spend_proof;
+
 const SIGNATURE_HASH_PERSONALIZATION: &[u8; 8] = b"IFsighsh";
 const TRANSACTION_SIGNATURE_VERSION: &[u8; 1] = &[0];
 pub const TRANSACTION_SIGNATURE_SIZE: usize = 64;
@@ -72,6 +90,11 @@ pub const TRANSACTION_PUBLIC_KEY_SIZE: usize = 32;
 pub const TRANSACTION_EXPIRATION_SIZE: usize = 4;
 pub const TRANSACTION_FEE_SIZE: usize = 8;
 
+/// Version of the format used by [`ProposedTransaction::write`]. This is
+/// independent from [`TransactionVersion`], and should be incremented when
+/// the serialization of the builders changes.
+const PROPOSED_TRANSACTION_FORMAT_VERSION: u8 = 1;
+
 /// A collection of spend and output proofs that can be signed and verified.
 /// In general, all the spent values should add up to all the output values.
 ///
@@ -107,6 +130,11 @@ pub struct ProposedTransaction {
     /// is the fee paid to the miner for mining the transaction.
     value_balances: ValueBalances,
 
+    /// Addresses that receive the change of specific assets, including the
+    /// native asset. The change of other assets goes to the address passed
+    /// when building the transaction.
+    change_destinations: HashMap<AssetIdentifier, PublicAddress>,
+
     /// This is the sequence in the chain the transaction will expire at and be
     /// removed from the mempool. A value of 0 indicates the transaction will
     /// not expire.
@@ -117,6 +145,11 @@ pub struct ProposedTransaction {
     // Used to add randomness to signature generation without leaking the
     // key. Referred to as `ar` in the literature.
     public_key_randomness: jubjub::Fr,
+
+    /// Maximum number of threads used to generate the spend, output and mint
+    /// proofs when building the transaction. Proofs are generated on the
+    /// calling thread when this is 1.
+    proving_threads: usize,
     // NOTE: If adding fields here, you may need to add fields to
     // signature hash method, and also to Transaction.
 }
@@ -130,9 +163,121 @@ impl ProposedTransaction {
             mints: vec![],
             burns: vec![],
             value_balances: ValueBalances::new(),
+            change_destinations: HashMap::new(),
             expiration: 0,
             public_key_randomness: jubjub::Fr::random(thread_rng()),
+            proving_threads: 1,
+        }
+    }
+
+    /// Load a [`ProposedTransaction`] stored with
+    /// [`ProposedTransaction::write`].
+    pub fn read<R: io::Read>(mut reader: R) -> Result<Self, IronfishError> {
+        let format_version = reader.read_u8()?;
+        if format_version != PROPOSED_TRANSACTION_FORMAT_VERSION {
+            return Err(IronfishError::new(IronfishErrorKind::InvalidData));
+        }
+
+        let version = TransactionVersion::read(&mut reader)?;
+        let expiration = reader.read_u32::<LittleEndian>()?;
+        let public_key_randomness = read_scalar(&mut reader)?;
+
+        let num_spends = reader.read_u64::<LittleEndian>()?;
+        let num_outputs = reader.read_u64::<LittleEndian>()?;
+        let num_mints = reader.read_u64::<LittleEndian>()?;
+        let num_burns = reader.read_u64::<LittleEndian>()?;
+        let num_change_destinations = reader.read_u64::<LittleEndian>()?;
+
+        let mut transaction = ProposedTransaction::new(version);
+        transaction.expiration = expiration;
+        transaction.public_key_randomness = public_key_randomness;
+
+        for _ in 0..num_spends {
+            let spend = SpendBuilder::read(&mut reader)?;
+            transaction
+                .value_balances
+                .add(spend.note.asset_id(), spend.note.value().try_into()?)?;
+            transaction.spends.push(spend);
+        }
+
+        for _ in 0..num_outputs {
+            let output = OutputBuilder::read(&mut reader)?;
+            transaction
+                .value_balances
+                .subtract(output.note.asset_id(), output.note.value().try_into()?)?;
+            transaction.outputs.push(output);
+        }
+
+        for _ in 0..num_mints {
+            let mint = MintBuilder::read(&mut reader)?;
+            transaction
+                .value_balances
+                .add(mint.asset.id(), mint.value.try_into()?)?;
+            transaction.mints.push(mint);
+        }
+
+        for _ in 0..num_burns {
+            let burn = BurnBuilder::read(&mut reader)?;
+            transaction
+                .value_balances
+                .subtract(&burn.asset_id, burn.value.try_into()?)?;
+            transaction.burns.push(burn);
+        }
+
+        for _ in 0..num_change_destinations {
+            let asset_id = AssetIdentifier::read(&mut reader)?;
+            let address = PublicAddress::read(&mut reader)?;
+            transaction.change_destinations.insert(asset_id, address);
         }
+
+        Ok(transaction)
+    }
+
+    /// Store everything needed to build this transaction: the notes and
+    /// witnesses of the spends, the notes of the outputs, the mints, the burns,
+    /// the change destinations, the expiration and the public key randomness.
+    ///
+    /// This allows a transaction to be assembled by one process (for example,
+    /// one that only has access to the view keys of an account), then proven
+    /// and signed by another one. The serialized data contains plaintext notes
+    /// and should be handled as sensitive.
+    pub fn write<W: io::Write>(&self, mut writer: W) -> Result<(), IronfishError> {
+        writer.write_u8(PROPOSED_TRANSACTION_FORMAT_VERSION)?;
+        self.version.write(&mut writer)?;
+        writer.write_u32::<LittleEndian>(self.expiration)?;
+        writer.write_all(&self.public_key_randomness.to_bytes())?;
+
+        writer.write_u64::<LittleEndian>(self.spends.len() as u64)?;
+        writer.write_u64::<LittleEndian>(self.outputs.len() as u64)?;
+        writer.write_u64::<LittleEndian>(self.mints.len() as u64)?;
+        writer.write_u64::<LittleEndian>(self.burns.len() as u64)?;
+        writer.write_u64::<LittleEndian>(self.change_destinations.len() as u64)?;
+
+        for spend in self.spends.iter() {
+            spend.write(&mut writer)?;
+        }
+
+        for output in self.outputs.iter() {
+            output.write(&mut writer)?;
+        }
+
+        for mint in self.mints.iter() {
+            mint.write(&mut writer)?;
+        }
+
+        for burn in self.burns.iter() {
+            burn.write(&mut writer)?;
+        }
+
+        // Sorted so that the same transaction is always written the same way
+        let mut change_destinations: Vec<_> = self.change_destinations.iter().collect();
+        change_destinations.sort_by_key(|(asset_id, _)| *asset_id.as_bytes());
+        for (asset_id, address) in change_destinations {
+            asset_id.write(&mut writer)?;
+            address.write(&mut writer)?;
+        }
+
+        Ok(())
     }
 
     /// Spend the note owned by spender_key at the given witness location.
@@ -140,11 +285,25 @@ impl ProposedTransaction {
         &mut self,
         note: Note,
         witness: &dyn WitnessTrait,
+    ) -> Result<(), IronfishError> {
+        self.add_diversified_spend(note, witness, 0)
+    }
+
+    /// Spend a note that was sent to the diversified address of spender_key
+    /// at `diversifier_index`, as reported by
+    /// [`crate::MerkleNote::decrypt_note_for_owner`].
+    pub fn add_diversified_spend(
+        &mut self,
+        note: Note,
+        witness: &dyn WitnessTrait,
+        diversifier_index: u64,
     ) -> Result<(), IronfishError> {
         self.value_balances
             .add(note.asset_id(), note.value().try_into()?)?;
 
-        self.spends.push(SpendBuilder::new(note, witness));
+        let mut spend = SpendBuilder::new(note, witness);
+        spend.diversifier_index = diversifier_index;
+        self.spends.push(spend);
 
         Ok(())
     }
@@ -160,6 +319,33 @@ impl ProposedTransaction {
         Ok(())
     }
 
+    /// Create a proof of a new note sent to the diversified address
+    /// `recipient`, which must own the note. Only transactions of a version
+    /// that has [`TransactionVersion::has_diversified_notes`] can encrypt notes
+    /// to diversified addresses.
+    pub fn add_diversified_output(
+        &mut self,
+        note: Note,
+        recipient: DiversifiedAddress,
+    ) -> Result<(), IronfishError> {
+        if !self.version.has_diversified_notes() {
+            return Err(IronfishError::new(
+                IronfishErrorKind::InvalidTransactionVersion,
+            ));
+        }
+        if note.owner != recipient.owner() {
+            return Err(IronfishError::new(IronfishErrorKind::InvalidPublicAddress));
+        }
+
+        self.value_balances
+            .subtract(note.asset_id(), note.value().try_into()?)?;
+
+        self.outputs
+            .push(OutputBuilder::new_diversified(note, recipient));
+
+        Ok(())
+    }
+
     pub fn add_mint(&mut self, asset: Asset, value: u64) -> Result<(), IronfishError> {
         self.value_balances.add(asset.id(), value.try_into()?)?;
 
@@ -191,6 +377,183 @@ impl ProposedTransaction {
         Ok(())
     }
 
+    /// Add several mints at once, which may transfer the ownership of their
+    /// assets. Either all the mints are added, or none of them if the balance
+    /// of one of the assets overflows.
+    pub fn add_mints(&mut self, mints: Vec<MintBuilder>) -> Result<(), IronfishError> {
+        let mut value_balances = self.value_balances.clone();
+        for mint in mints.iter() {
+            value_balances.add(mint.asset.id(), mint.value.try_into()?)?;
+        }
+
+        self.value_balances = value_balances;
+        self.mints.extend(mints);
+
+        Ok(())
+    }
+
+    /// Add several burns at once. Either all the burns are added, or none of
+    /// them if the balance of one of the assets overflows.
+    pub fn add_burns(&mut self, burns: Vec<BurnBuilder>) -> Result<(), IronfishError> {
+        let mut value_balances = self.value_balances.clone();
+        for burn in burns.iter() {
+            value_balances.subtract(&burn.asset_id, burn.value.try_into()?)?;
+        }
+
+        self.value_balances = value_balances;
+        self.burns.extend(burns);
+
+        Ok(())
+    }
+
+    /// Send the change of `asset_id` to `address` instead of the default
+    /// change address. This also applies to the native asset.
+    pub fn set_change_destination(&mut self, asset_id: AssetIdentifier, address: PublicAddress) {
+        self.change_destinations.insert(asset_id, address);
+    }
+
+    /// Pick notes from `candidates` to cover the outputs, mints and burns
+    /// already added to this transaction plus `intended_transaction_fee`, and
+    /// add them as spends.
+    ///
+    /// Notes are selected independently for each asset, using `strategy`.
+    /// Candidates that are already spent by this transaction are skipped, so
+    /// this can be called again after adding more outputs. Returns the indexes
+    /// of the candidates that were spent.
+    pub fn select_spends(
+        &mut self,
+        candidates: &[SpendableNote],
+        intended_transaction_fee: u64,
+        strategy: SelectionStrategy,
+    ) -> Result<Vec<usize>, IronfishError> {
+        let intended_transaction_fee: i64 = intended_transaction_fee.try_into()?;
+
+        let mut targets = HashMap::new();
+        for (asset_id, value) in self.value_balances.iter() {
+            // Computed in i128 since the balance can be as low as i64::MIN
+            let needed = match asset_id == &NATIVE_ASSET {
+                true => intended_transaction_fee as i128 - *value as i128,
+                false => -(*value as i128),
+            };
+
+            if needed > 0 {
+                targets.insert(*asset_id, u64::try_from(needed)?);
+            }
+        }
+
+        let spent: HashSet<[u8; 32]> = self
+            .spends
+            .iter()
+            .map(|spend| spend.note.commitment())
+            .collect();
+        let (unspent_indexes, unspent): (Vec<usize>, Vec<SpendableNote>) = candidates
+            .iter()
+            .enumerate()
+            .filter(|(_, candidate)| !spent.contains(&candidate.note.commitment()))
+            .map(|(index, candidate)| {
+                (
+                    index,
+                    SpendableNote::new_diversified(
+                        candidate.note.clone(),
+                        candidate.witness,
+                        candidate.diversifier_index,
+                    ),
+                )
+            })
+            .unzip();
+
+        let selected: Vec<usize> = select_notes(&unspent, &targets, strategy)?
+            .into_iter()
+            .map(|index| unspent_indexes[index])
+            .collect();
+        for index in selected.iter() {
+            let candidate = &candidates[*index];
+            self.add_diversified_spend(
+                candidate.note.clone(),
+                candidate.witness,
+                candidate.diversifier_index,
+            )?;
+        }
+
+        Ok(selected)
+    }
+
+    /// Size in bytes of the [`Transaction`] that will be posted from this
+    /// proposed transaction, if it is posted with `intended_transaction_fee`.
+    ///
+    /// This includes the change notes that will be added when the
+    /// transaction is built, so it can be computed before paying the cost of
+    /// generating any proof.
+    pub fn estimated_size(&self, intended_transaction_fee: u64) -> usize {
+        let native_change = *self.value_balances.fee() as i128 - intended_transaction_fee as i128;
+
+        let mut outputs = self.outputs.len() + self.custom_asset_change_notes();
+        if !self.is_miners_fee() && native_change > 0 {
+            outputs += 1;
+        }
+
+        self.size_with_outputs(outputs)
+    }
+
+    /// Fee to pay for this transaction at `fee_rate` per byte, based on
+    /// [`ProposedTransaction::estimated_size`].
+    ///
+    /// Creating a native change note makes the transaction larger, so when the
+    /// surplus of native asset is too small to cover the fee of that change
+    /// note, the whole surplus is used as fee instead.
+    pub fn estimate_fee(&self, fee_rate: u64) -> u64 {
+        let native_balance = *self.value_balances.fee() as i128;
+        let outputs = self.outputs.len() + self.custom_asset_change_notes();
+
+        let fee_without_change = (self.size_with_outputs(outputs) as u64).saturating_mul(fee_rate);
+        if self.is_miners_fee() || native_balance <= fee_without_change as i128 {
+            return fee_without_change;
+        }
+
+        let fee_with_change = (self.size_with_outputs(outputs + 1) as u64).saturating_mul(fee_rate);
+        if native_balance > fee_with_change as i128 {
+            fee_with_change
+        } else {
+            native_balance as u64
+        }
+    }
+
+    fn is_miners_fee(&self) -> bool {
+        self.outputs.iter().any(|output| output.get_is_miners_fee())
+    }
+
+    /// Number of change notes that will be created for assets other than the
+    /// native asset
+    fn custom_asset_change_notes(&self) -> usize {
+        if self.is_miners_fee() {
+            return 0;
+        }
+
+        self.value_balances
+            .iter()
+            .filter(|(asset_id, value)| *asset_id != &NATIVE_ASSET && **value > 0)
+            .count()
+    }
+
+    fn size_with_outputs(&self, outputs: usize) -> usize {
+        let mints: usize = self
+            .mints
+            .iter()
+            .map(|mint| mint.description_size(self.version))
+            .sum();
+
+        // Version, number of spends, outputs, mints and burns
+        1 + 8 * 4
+            + TRANSACTION_FEE_SIZE
+            + TRANSACTION_EXPIRATION_SIZE
+            + TRANSACTION_PUBLIC_KEY_SIZE
+            + self.spends.len() * SPEND_DESCRIPTION_SIZE
+            + outputs * output_description_size(self.version)
+            + mints
+            + self.burns.len() * BURN_DESCRIPTION_SIZE
+            + TRANSACTION_SIGNATURE_SIZE
+    }
+
     fn add_change_notes(
         &mut self,
         change_goes_to: Option<PublicAddress>,
@@ -199,26 +562,16 @@ impl ProposedTransaction {
     ) -> Result<(), IronfishError> {
         let mut change_notes = vec![];
 
-        for (asset_id, value) in self.value_balances.iter() {
-            let is_native_asset = asset_id == &NATIVE_ASSET;
-
-            let change_amount = match is_native_asset {
-                true => *value - intended_transaction_fee,
-                false => *value,
-            };
-
-            if change_amount < 0 {
-                return Err(IronfishError::new(IronfishErrorKind::InvalidBalance));
-            }
+        for (asset_id, change_amount) in self.value_balances.change(intended_transaction_fee)? {
             if change_amount > 0 {
-                let change_address = change_goes_to.unwrap_or(public_address);
-                let change_note = Note::new(
-                    change_address,
-                    change_amount as u64, // we checked it was positive
-                    "",
-                    *asset_id,
-                    public_address,
-                );
+                let change_address = self
+                    .change_destinations
+                    .get(&asset_id)
+                    .copied()
+                    .or(change_goes_to)
+                    .unwrap_or(public_address);
+                let change_note =
+                    Note::new(change_address, change_amount, "", asset_id, public_address);
 
                 change_notes.push(change_note);
             }
@@ -245,8 +598,7 @@ impl ProposedTransaction {
         };
 
         // skip adding change notes if this is special case of a miners fee transaction
-        let is_miners_fee = self.outputs.iter().any(|output| output.get_is_miners_fee());
-        if !is_miners_fee {
+        if !self.is_miners_fee() {
             self.add_change_notes(change_goes_to, public_address, intended_transaction_fee)?;
         }
 
@@ -256,35 +608,34 @@ impl ProposedTransaction {
         let randomized_public_key = redjubjub::PublicKey(view_key.authorizing_key.into())
             .randomize(self.public_key_randomness, *SPENDING_KEY_GENERATOR);
 
-        let mut unsigned_spends = Vec::with_capacity(self.spends.len());
-        for spend in &self.spends {
-            unsigned_spends.push(spend.build(
+        let unsigned_spends = build_in_parallel(&self.spends, self.proving_threads, |spend| {
+            spend.build(
                 &proof_generation_key,
                 &view_key,
                 &self.public_key_randomness,
                 &randomized_public_key,
-            )?);
-        }
-
-        let mut output_descriptions = Vec::with_capacity(self.outputs.len());
-        for output in &self.outputs {
-            output_descriptions.push(output.build(
-                &proof_generation_key,
-                &outgoing_view_key,
-                &self.public_key_randomness,
-                &randomized_public_key,
-            )?);
-        }
-
-        let mut unsigned_mints = Vec::with_capacity(self.mints.len());
-        for mint in &self.mints {
-            unsigned_mints.push(mint.build(
+            )
+        })?;
+
+        let output_descriptions =
+            build_in_parallel(&self.outputs, self.proving_threads, |output| {
+                output.build(
+                    self.version,
+                    &proof_generation_key,
+                    &outgoing_view_key,
+                    &self.public_key_randomness,
+                    &randomized_public_key,
+                )
+            })?;
+
+        let unsigned_mints = build_in_parallel(&self.mints, self.proving_threads, |mint| {
+            mint.build(
                 &proof_generation_key,
                 &public_address,
                 &self.public_key_randomness,
                 &randomized_public_key,
-            )?);
-        }
+            )
+        })?;
 
         let mut burn_descriptions = Vec::with_capacity(self.burns.len());
         for burn in &self.burns {
@@ -300,8 +651,12 @@ impl ProposedTransaction {
         )?;
 
         // Create and verify binding signature keys
-        let (binding_signature_private_key, binding_signature_public_key) =
-            self.binding_signature_keys(&unsigned_mints, &burn_descriptions)?;
+        let (binding_signature_private_key, binding_signature_public_key) = self
+            .binding_signature_keys(
+                &unsigned_mints,
+                &burn_descriptions,
+                intended_transaction_fee,
+            )?;
 
         let binding_signature = self.binding_signature(
             &binding_signature_private_key,
@@ -396,11 +751,29 @@ impl ProposedTransaction {
         self.expiration
     }
 
+    /// Randomness used to randomize the spend authorizing key of the
+    /// transaction. It is not part of the posted [`Transaction`], so it must
+    /// be stored by the sender to later create a
+    /// [`PaymentDisclosure`](payment_disclosure::PaymentDisclosure) for one of
+    /// its outputs.
+    pub fn public_key_randomness(&self) -> jubjub::Fr {
+        self.public_key_randomness
+    }
+
     /// Set the sequence to expire the transaction from the mempool.
     pub fn set_expiration(&mut self, sequence: u32) {
         self.expiration = sequence;
     }
 
+    /// Set the maximum number of threads used to generate proofs in
+    /// [`ProposedTransaction::build`]. Spends, outputs and mints keep the
+    /// order in which they were added regardless of the number of threads,
+    /// so the resulting transaction is the same as the one built on a single
+    /// thread.
+    pub fn set_proving_threads(&mut self, threads: usize) {
+        self.proving_threads = threads.max(1);
+    }
+
     /// Calculate a hash of the transaction data. This hash is what gets signed
     /// by the private keys to verify that the transaction actually happened.
     ///
@@ -479,6 +852,7 @@ impl ProposedTransaction {
         &self,
         mints: &[UnsignedMintDescription],
         burns: &[BurnDescription],
+        intended_transaction_fee: i64,
     ) -> Result<(redjubjub::PrivateKey, redjubjub::PublicKey), IronfishError> {
         // A "private key" manufactured from a bunch of randomness added for each
         // spend and output.
@@ -502,13 +876,21 @@ impl ProposedTransaction {
         let public_key =
             PublicKey::from_private(&private_key, *VALUE_COMMITMENT_RANDOMNESS_GENERATOR);
 
-        let value_balance =
-            self.calculate_value_balance(&binding_verification_key, mints, burns)?;
+        let value_balance = self.calculate_value_balance(
+            &binding_verification_key,
+            intended_transaction_fee,
+            mints,
+            burns,
+        )?;
 
         // Confirm that the public key derived from the binding signature key matches
         // the final value balance point. The binding verification key is how verifiers
         // check the consistency of the values in a transaction.
         if value_balance != public_key.0 {
+            // Report the asset that does not balance, if the value balances
+            // can tell which one it is
+            self.value_balances
+                .check_balanced(intended_transaction_fee)?;
             return Err(IronfishError::new(IronfishErrorKind::InvalidBalance));
         }
 
@@ -519,18 +901,14 @@ impl ProposedTransaction {
     fn calculate_value_balance(
         &self,
         binding_verification_key: &ExtendedPoint,
+        fee: i64,
         mints: &[UnsignedMintDescription],
         burns: &[BurnDescription],
     ) -> Result<ExtendedPoint, IronfishError> {
         let mints_descriptions: Vec<MintDescription> =
             mints.iter().map(|m| m.description.clone()).collect();
 
-        calculate_value_balance(
-            binding_verification_key,
-            *self.value_balances.fee(),
-            &mints_descriptions,
-            burns,
-        )
+        calculate_value_balance(binding_verification_key, fee, &mints_descriptions, burns)
     }
 }
 
@@ -598,7 +976,7 @@ impl Transaction {
 
         let mut outputs = Vec::with_capacity(num_outputs as usize);
         for _ in 0..num_outputs {
-            outputs.push(OutputDescription::read(&mut reader)?);
+            outputs.push(OutputDescription::read(&mut reader, version)?);
         }
 
         let mut mints = Vec::with_capacity(num_mints as usize);
@@ -703,6 +1081,21 @@ impl Transaction {
         self.expiration
     }
 
+    /// Decode the public contents of this transaction into a human readable
+    /// [`TransactionSummary`].
+    pub fn summary(&self) -> TransactionSummary {
+        TransactionSummary::new(
+            self.version,
+            self.fee,
+            self.expiration,
+            &self.randomized_public_key,
+            self.spends.iter(),
+            &self.outputs,
+            self.mints.iter(),
+            &self.burns,
+        )
+    }
+
     /// Get the expiration sequence for this transaction
     pub fn randomized_public_key(&self) -> &redjubjub::PublicKey {
         &self.randomized_public_key
@@ -768,6 +1161,67 @@ impl Transaction {
     }
 }
 
+/// Call `build` on every item, spreading the work across at most
+/// `thread_count` threads, and return the results in the same order as the
+/// items.
+///
+/// [`IronfishError`] can't be sent across threads, so the worker threads only
+/// report which items failed. Those items are then built again on the calling
+/// thread to surface the actual error.
+fn build_in_parallel<T, R, F>(
+    items: &[T],
+    thread_count: usize,
+    build: F,
+) -> Result<Vec<R>, IronfishError>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> Result<R, IronfishError> + Sync,
+{
+    if thread_count <= 1 || items.len() <= 1 {
+        return items.iter().map(&build).collect();
+    }
+
+    let chunk_size = (items.len() + thread_count - 1) / thread_count;
+    let chunk_results = thread::scope(|scope| {
+        let handles: Vec<_> = items
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let build = &build;
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|item| build(item).ok())
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        // Join every handle, even after a panic, so that `thread::scope` does
+        // not propagate the panic itself
+        handles
+            .into_iter()
+            .map(|handle| handle.join())
+            .collect::<Vec<_>>()
+    });
+
+    let mut results = Vec::with_capacity(items.len());
+    for chunk_result in chunk_results {
+        let chunk_result =
+            chunk_result.map_err(|_| IronfishError::new(IronfishErrorKind::ThreadPanicked))?;
+        results.extend(chunk_result);
+    }
+
+    items
+        .iter()
+        .zip(results)
+        .map(|(item, result)| match result {
+            Some(result) => Ok(result),
+            None => build(item),
+        })
+        .collect()
+}
+
 /// Convert the integer value to a point on the Jubjub curve, accounting for
 /// negative values
 fn fee_to_point(value: i64) -> Result<ExtendedPoint, IronfishError> {
@@ -827,103 +1281,100 @@ fn internal_batch_verify_transactions<'a>(
     output_verifying_key: &PreparedVerifyingKey<Bls12>,
     mint_verifying_key: &PreparedVerifyingKey<Bls12>,
 ) -> Result<(), IronfishError> {
-    let mut spend_proofs = vec![];
-    let mut spend_public_inputs = vec![];
-
-    let mut output_proofs = vec![];
-    let mut output_public_inputs = vec![];
-
-    let mut mint_proofs = vec![];
-    let mut mint_public_inputs = vec![];
+    let mut batch = ProofBatch::new();
 
     for transaction in transactions {
-        // Context to accumulate a signature of all the spends and outputs and
-        // guarantee they are part of this transaction, unmodified.
-        let mut binding_verification_key = ExtendedPoint::identity();
-
-        let hash_to_verify_signature = transaction.transaction_signature_hash()?;
-
-        for spend in transaction.spends.iter() {
-            spend.partial_verify()?;
-
-            spend_proofs.push(&spend.proof);
-            spend_public_inputs.push(
-                spend
-                    .public_inputs(transaction.randomized_public_key())
-                    .to_vec(),
-            );
+        batch.add_transaction(transaction)?;
+    }
 
-            binding_verification_key += spend.value_commitment;
+    // This entry point has always reported invalid mint proofs as
+    // `InvalidOutputProof`, and existing callers match on that kind, so keep
+    // it here. The per-transaction APIs report `InvalidMintProof`.
+    batch
+        .verify(
+            spend_verifying_key,
+            output_verifying_key,
+            mint_verifying_key,
+        )
+        .map_err(|e| match e.kind {
+            IronfishErrorKind::InvalidMintProof => {
+                IronfishError::new(IronfishErrorKind::InvalidOutputProof)
+            }
+            _ => e,
+        })
+}
 
-            spend.verify_signature(
-                &hash_to_verify_signature,
-                transaction.randomized_public_key(),
-            )?;
+fn internal_batch_verify_transactions_with_failures<'a>(
+    transactions: impl IntoIterator<Item = &'a Transaction>,
+    spend_verifying_key: &PreparedVerifyingKey<Bls12>,
+    output_verifying_key: &PreparedVerifyingKey<Bls12>,
+    mint_verifying_key: &PreparedVerifyingKey<Bls12>,
+) -> Vec<(usize, IronfishErrorKind)> {
+    let mut failures = vec![];
+    let mut batches = vec![];
+
+    for (index, transaction) in transactions.into_iter().enumerate() {
+        let mut batch = ProofBatch::new();
+        match batch.add_transaction(transaction) {
+            Ok(()) => batches.push((index, batch)),
+            Err(e) => failures.push((index, e.kind)),
         }
+    }
 
-        for output in transaction.outputs.iter() {
-            output.partial_verify()?;
+    failures.extend(bisect_proof_failures(
+        &batches,
+        spend_verifying_key,
+        output_verifying_key,
+        mint_verifying_key,
+    ));
+    failures.sort_by_key(|(index, _)| *index);
 
-            output_proofs.push(&output.proof);
-            output_public_inputs.push(
-                output
-                    .public_inputs(transaction.randomized_public_key())
-                    .to_vec(),
-            );
+    failures
+}
 
-            binding_verification_key -= output.merkle_note.value_commitment;
-        }
+/// Verify the proofs of several transactions together. If the combined batch
+/// fails, split it in half and recurse until every failing transaction has
+/// been isolated.
+fn bisect_proof_failures(
+    batches: &[(usize, ProofBatch)],
+    spend_verifying_key: &PreparedVerifyingKey<Bls12>,
+    output_verifying_key: &PreparedVerifyingKey<Bls12>,
+    mint_verifying_key: &PreparedVerifyingKey<Bls12>,
+) -> Vec<(usize, IronfishErrorKind)> {
+    if batches.is_empty() {
+        return vec![];
+    }
 
-        for mint in transaction.mints.iter() {
-            mint.partial_verify()?;
+    let mut combined = ProofBatch::new();
+    for (_, batch) in batches {
+        combined.append(batch);
+    }
 
-            mint_proofs.push(&mint.proof);
-            mint_public_inputs.push(
-                mint.public_inputs(transaction.randomized_public_key())
-                    .to_vec(),
+    match combined.verify(
+        spend_verifying_key,
+        output_verifying_key,
+        mint_verifying_key,
+    ) {
+        Ok(()) => vec![],
+        Err(e) if batches.len() == 1 => vec![(batches[0].0, e.kind)],
+        Err(_) => {
+            let (left, right) = batches.split_at(batches.len() / 2);
+
+            let mut failures = bisect_proof_failures(
+                left,
+                spend_verifying_key,
+                output_verifying_key,
+                mint_verifying_key,
             );
-
-            mint.verify_signature(
-                &hash_to_verify_signature,
-                transaction.randomized_public_key(),
-            )?;
+            failures.extend(bisect_proof_failures(
+                right,
+                spend_verifying_key,
+                output_verifying_key,
+                mint_verifying_key,
+            ));
+            failures
         }
-
-        transaction.verify_binding_signature(&binding_verification_key)?;
     }
-
-    if !spend_proofs.is_empty()
-        && !verify_proofs_batch(
-            spend_verifying_key,
-            &mut OsRng,
-            &spend_proofs[..],
-            &spend_public_inputs[..],
-        )?
-    {
-        return Err(IronfishError::new(IronfishErrorKind::InvalidSpendProof));
-    }
-    if !output_proofs.is_empty()
-        && !verify_proofs_batch(
-            output_verifying_key,
-            &mut OsRng,
-            &output_proofs[..],
-            &output_public_inputs[..],
-        )?
-    {
-        return Err(IronfishError::new(IronfishErrorKind::InvalidOutputProof));
-    }
-    if !mint_proofs.is_empty()
-        && !verify_proofs_batch(
-            mint_verifying_key,
-            &mut OsRng,
-            &mint_proofs[..],
-            &mint_public_inputs[..],
-        )?
-    {
-        return Err(IronfishError::new(IronfishErrorKind::InvalidOutputProof));
-    }
-
-    Ok(())
 }
 
 /// Validate the transaction. Confirms that:
@@ -945,3 +1396,22 @@ pub fn batch_verify_transactions<'a>(
         &SAPLING.mint_verifying_key,
     )
 }
+
+/// Verify a batch of transactions like [`batch_verify_transactions`], but
+/// instead of failing the whole batch when one transaction is invalid, return
+/// the index and error kind of every transaction that failed. An empty result
+/// means that all the transactions are valid.
+///
+/// The proofs of all the transactions are first checked in a single batch, and
+/// the batch is only bisected if that check fails, so the cost for a valid
+/// batch is the same as [`batch_verify_transactions`].
+pub fn batch_verify_transactions_with_failures<'a>(
+    transactions: impl IntoIterator<Item = &'a Transaction>,
+) -> Vec<(usize, IronfishErrorKind)> {
+    internal_batch_verify_transactions_with_failures(
+        transactions,
+        &SAPLING.spend_verifying_key,
+        &SAPLING.output_verifying_key,
+        &SAPLING.mint_verifying_key,
+    )
+}
@@ -0,0 +1,508 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
+
+use crate::{
+    errors::{IronfishError, IronfishErrorKind},
+    keys::{IncomingViewKey, OutgoingViewKey, ViewKey},
+    note::Note,
+};
+
+use super::Transaction;
+
+/// The view key that was able to decrypt a note, as an index into the
+/// incoming view keys or outgoing view keys of the [`DecryptionKeys`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchedKey {
+    /// The note is owned by the account with this incoming view key
+    Incoming(usize),
+    /// The note was sent by the account with this outgoing view key
+    Outgoing(usize),
+}
+
+/// A note decrypted by [`decrypt_notes`]
+#[derive(Clone)]
+pub struct DecryptedNote {
+    /// Index of the transaction in the list of transactions
+    pub transaction_index: usize,
+    /// Index of the output in the transaction
+    pub output_index: usize,
+    pub key: MatchedKey,
+    /// Index of the diversified address that received the note, needed to
+    /// spend it, see [`crate::MerkleNote::decrypt_note_for_owner`]. Always 0
+    /// for outgoing matches.
+    pub diversifier_index: u64,
+    pub note: Note,
+}
+
+/// The keys notes are decrypted with by [`decrypt_notes`]. They are shared
+/// with the threads of the [`DecryptionThreadPool`] without being copied, so
+/// they should be created once and reused for every block.
+#[derive(Clone)]
+pub struct DecryptionKeys {
+    incoming_view_keys: Arc<[IncomingViewKey]>,
+    /// View keys of the incoming view keys, in the same order, used to check
+    /// the owner of notes received on diversified addresses
+    view_keys: Option<Arc<[ViewKey]>>,
+    outgoing_view_keys: Arc<[OutgoingViewKey]>,
+}
+
+impl DecryptionKeys {
+    /// Keys that only decrypt the notes received on the regular public
+    /// address of each account, see
+    /// [`MerkleNote::decrypt_note_for_incoming_view_key`](crate::MerkleNote::decrypt_note_for_incoming_view_key).
+    pub fn new(
+        incoming_view_keys: Vec<IncomingViewKey>,
+        outgoing_view_keys: Vec<OutgoingViewKey>,
+    ) -> Self {
+        DecryptionKeys {
+            incoming_view_keys: incoming_view_keys.into(),
+            view_keys: None,
+            outgoing_view_keys: outgoing_view_keys.into(),
+        }
+    }
+
+    /// Keys that decrypt the notes received on any address of each account,
+    /// see [`MerkleNote::decrypt_note_for_owner`](crate::MerkleNote::decrypt_note_for_owner).
+    /// Matches are reported with the index of the view key.
+    pub fn with_view_keys(
+        view_keys: Vec<ViewKey>,
+        outgoing_view_keys: Vec<OutgoingViewKey>,
+    ) -> Result<Self, IronfishError> {
+        let incoming_view_keys = view_keys
+            .iter()
+            .map(ViewKey::incoming_view_key)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(DecryptionKeys {
+            incoming_view_keys: incoming_view_keys.into(),
+            view_keys: Some(view_keys.into()),
+            outgoing_view_keys: outgoing_view_keys.into(),
+        })
+    }
+
+    pub fn incoming_view_keys(&self) -> &[IncomingViewKey] {
+        &self.incoming_view_keys
+    }
+
+    pub fn outgoing_view_keys(&self) -> &[OutgoingViewKey] {
+        &self.outgoing_view_keys
+    }
+}
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Threads used by [`decrypt_notes`]. The threads are started once and kept
+/// until the pool is dropped, so that scanning every block does not start new
+/// threads.
+pub struct DecryptionThreadPool {
+    sender: Option<mpsc::Sender<Job>>,
+    threads: Vec<thread::JoinHandle<()>>,
+}
+
+impl DecryptionThreadPool {
+    /// Start `thread_count` threads. With a single thread, notes are
+    /// decrypted on the calling thread and no thread is started.
+    pub fn new(thread_count: usize) -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let threads = match thread_count {
+            0 | 1 => vec![],
+            _ => (0..thread_count)
+                .map(|_| {
+                    let receiver = receiver.clone();
+                    thread::spawn(move || loop {
+                        let job = receiver.lock().unwrap().recv();
+                        match job {
+                            // A panicking job is reported by the call that
+                            // queued it, and the thread keeps running
+                            Ok(job) => {
+                                let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                            }
+                            // The pool was dropped
+                            Err(_) => break,
+                        }
+                    })
+                })
+                .collect(),
+        };
+
+        DecryptionThreadPool {
+            sender: Some(sender),
+            threads,
+        }
+    }
+
+    /// Number of threads that decrypt notes
+    pub fn thread_count(&self) -> usize {
+        self.threads.len().max(1)
+    }
+
+    /// Run `job` for every index in `0..count` on the threads of the pool,
+    /// returning the results in the order of the indexes. Fails with
+    /// [`IronfishErrorKind::ThreadPanicked`] if any of the jobs panicked.
+    fn map<T, F>(&self, count: usize, job: F) -> Result<Vec<T>, IronfishError>
+    where
+        T: Send + 'static,
+        F: Fn(usize) -> T + Send + Sync + 'static,
+    {
+        let job = Arc::new(job);
+        let (sender, receiver) = mpsc::channel();
+
+        for index in 0..count {
+            let job = job.clone();
+            let sender = sender.clone();
+
+            self.sender
+                .as_ref()
+                .expect("the sender is only taken when the pool is dropped")
+                .send(Box::new(move || {
+                    // The receiver only goes away if the caller panicked
+                    let _ = sender.send((index, job(index)));
+                }))
+                .map_err(|_| IronfishError::new(IronfishErrorKind::ThreadPanicked))?;
+        }
+        drop(sender);
+
+        // The sender of a panicking job is dropped without sending anything
+        let mut results: Vec<(usize, T)> = receiver.iter().collect();
+        if results.len() != count {
+            return Err(IronfishError::new(IronfishErrorKind::ThreadPanicked));
+        }
+        results.sort_by_key(|(index, _)| *index);
+
+        Ok(results.into_iter().map(|(_, result)| result).collect())
+    }
+}
+
+impl Default for DecryptionThreadPool {
+    /// A pool with one thread per available core
+    fn default() -> Self {
+        let thread_count = thread::available_parallelism()
+            .map(|threads| threads.get())
+            .unwrap_or(1);
+
+        Self::new(thread_count)
+    }
+}
+
+impl Drop for DecryptionThreadPool {
+    fn drop(&mut self) {
+        // Closing the channel stops the threads once the queued jobs are done
+        drop(self.sender.take());
+
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Decrypt all the notes of a single transaction that can be decrypted with
+/// any of the given keys, on the calling thread. See [`decrypt_notes`].
+pub fn decrypt_transaction_notes(
+    transaction: &Transaction,
+    keys: &DecryptionKeys,
+) -> Vec<DecryptedNote> {
+    let transactions = std::slice::from_ref(transaction);
+    decrypt_outputs(transactions, &output_indexes(transactions), keys)
+}
+
+/// Decrypt all the notes in `transactions` (for example, all the transactions
+/// in a block) that can be decrypted with any of the given keys.
+///
+/// A note is reported once for the first incoming view key that can decrypt
+/// it, and once for the first outgoing view key that can decrypt it, so a note
+/// sent by an account to itself is returned twice. Since a note can only be
+/// owned by a single account, the remaining incoming view keys are not tried
+/// after one succeeds, and the same goes for outgoing view keys. Notes sent to
+/// any diversified address of an account are decrypted with a single
+/// Diffie-Hellman, and are only reported if the [`DecryptionKeys`] were
+/// created from view keys that verify their owner.
+///
+/// Notes are decrypted on the threads of `pool`, which share `transactions`
+/// without copying them. Results are ordered by transaction, then output,
+/// with incoming matches before outgoing ones.
+/// Fails with [`IronfishErrorKind::ThreadPanicked`] if decryption panicked on
+/// one of the threads.
+pub fn decrypt_notes(
+    pool: &DecryptionThreadPool,
+    transactions: &Arc<[Transaction]>,
+    keys: &DecryptionKeys,
+) -> Result<Vec<DecryptedNote>, IronfishError> {
+    let outputs = output_indexes(transactions);
+
+    let thread_count = pool.thread_count();
+    if thread_count <= 1 || outputs.len() <= 1 {
+        return Ok(decrypt_outputs(transactions, &outputs, keys));
+    }
+
+    // The threads of the pool outlive this call, so they share the
+    // transactions and keys through an Arc
+    let transactions = transactions.clone();
+    let outputs: Arc<[(usize, usize)]> = outputs.into();
+    let keys = keys.clone();
+
+    let chunk_size = (outputs.len() + thread_count - 1) / thread_count;
+    let chunk_count = (outputs.len() + chunk_size - 1) / chunk_size;
+
+    let chunks = pool.map(chunk_count, move |chunk_index| {
+        let start = chunk_index * chunk_size;
+        let end = (start + chunk_size).min(outputs.len());
+        decrypt_outputs(&transactions, &outputs[start..end], &keys)
+    })?;
+
+    Ok(chunks.into_iter().flatten().collect())
+}
+
+/// Index of the transaction and output of every output in `transactions`
+fn output_indexes(transactions: &[Transaction]) -> Vec<(usize, usize)> {
+    transactions
+        .iter()
+        .enumerate()
+        .flat_map(|(transaction_index, transaction)| {
+            (0..transaction.outputs.len())
+                .map(move |output_index| (transaction_index, output_index))
+        })
+        .collect()
+}
+
+fn decrypt_outputs(
+    transactions: &[Transaction],
+    outputs: &[(usize, usize)],
+    keys: &DecryptionKeys,
+) -> Vec<DecryptedNote> {
+    let mut decrypted = vec![];
+
+    for &(transaction_index, output_index) in outputs {
+        let merkle_note = &transactions[transaction_index].outputs[output_index].merkle_note;
+
+        let incoming = keys
+            .incoming_view_keys
+            .iter()
+            .enumerate()
+            .find_map(|(index, key)| {
+                let view_key = keys.view_keys.as_ref().map(|view_keys| &view_keys[index]);
+                merkle_note
+                    .decrypt_note_for_incoming_keys(key, view_key)
+                    .ok()
+                    .map(|(diversifier_index, note)| {
+                        (MatchedKey::Incoming(index), diversifier_index, note)
+                    })
+            });
+        let outgoing = keys
+            .outgoing_view_keys
+            .iter()
+            .enumerate()
+            .find_map(|(index, key)| {
+                merkle_note
+                    .decrypt_note_for_spender(key)
+                    .ok()
+                    .map(|note| (MatchedKey::Outgoing(index), 0, note))
+            });
+
+        for (key, diversifier_index, note) in incoming.into_iter().chain(outgoing) {
+            decrypted.push(DecryptedNote {
+                transaction_index,
+                output_index,
+                key,
+                diversifier_index,
+                note,
+            });
+        }
+    }
+
+    decrypted
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::{
+        decrypt_notes, decrypt_transaction_notes, DecryptionKeys, DecryptionThreadPool, MatchedKey,
+    };
+    use crate::{
+        assets::asset_identifier::NATIVE_ASSET,
+        errors::IronfishErrorKind,
+        keys::DiversifiedAddress,
+        note::Note,
+        test_util::make_fake_witness,
+        transaction::{ProposedTransaction, Transaction, TransactionVersion},
+        SaplingKey,
+    };
+
+    fn make_transaction(sender: &SaplingKey, receivers: &[DiversifiedAddress]) -> Transaction {
+        let in_note = Note::new(
+            sender.public_address(),
+            100,
+            "",
+            NATIVE_ASSET,
+            sender.public_address(),
+        );
+        let witness = make_fake_witness(&in_note);
+
+        let mut proposed_transaction = ProposedTransaction::new(TransactionVersion::V3);
+        proposed_transaction.add_spend(in_note, &witness).unwrap();
+        for receiver in receivers {
+            let out_note = Note::new(
+                receiver.owner(),
+                10,
+                "",
+                NATIVE_ASSET,
+                sender.public_address(),
+            );
+            proposed_transaction
+                .add_diversified_output(out_note, *receiver)
+                .unwrap();
+        }
+
+        proposed_transaction.post(sender, None, 1).unwrap()
+    }
+
+    #[test]
+    fn test_decrypt_notes() {
+        let alice = SaplingKey::generate_key();
+        let bob = SaplingKey::generate_key();
+        let carol = SaplingKey::generate_key();
+        let stranger = SaplingKey::generate_key();
+
+        let (alice_index, alice_address) = alice.view_key().next_diversified_address(2).unwrap();
+
+        // Outputs: [bob, carol, alice's change]
+        let transaction1 = make_transaction(
+            &alice,
+            &[bob.public_address().into(), carol.public_address().into()],
+        );
+        // Outputs: [alice's diversified address, bob's change]
+        let transaction2 = make_transaction(&bob, &[alice_address]);
+
+        let keys = DecryptionKeys::with_view_keys(
+            vec![
+                stranger.view_key().clone(),
+                bob.view_key().clone(),
+                alice.view_key().clone(),
+            ],
+            vec![alice.outgoing_view_key().clone()],
+        )
+        .unwrap();
+
+        let pool = DecryptionThreadPool::new(4);
+        let transactions: Arc<[Transaction]> = vec![transaction1, transaction2].into();
+        let decrypted = decrypt_notes(&pool, &transactions, &keys).unwrap();
+
+        let matches: Vec<(usize, usize, MatchedKey)> = decrypted
+            .iter()
+            .map(|decrypted| {
+                (
+                    decrypted.transaction_index,
+                    decrypted.output_index,
+                    decrypted.key,
+                )
+            })
+            .collect();
+        assert_eq!(
+            matches,
+            vec![
+                (0, 0, MatchedKey::Incoming(1)),
+                (0, 0, MatchedKey::Outgoing(0)),
+                (0, 1, MatchedKey::Outgoing(0)),
+                (0, 2, MatchedKey::Incoming(2)),
+                (0, 2, MatchedKey::Outgoing(0)),
+                (1, 0, MatchedKey::Incoming(2)),
+                (1, 1, MatchedKey::Incoming(1)),
+            ]
+        );
+
+        assert_eq!(decrypted[0].note.owner, bob.public_address());
+        assert_eq!(decrypted[0].note.value(), 10);
+        assert_eq!(decrypted[3].note.value(), 79);
+        assert_eq!(decrypted[3].diversifier_index, 0);
+        assert_eq!(decrypted[5].note.owner, alice_address.owner());
+        assert_eq!(decrypted[5].diversifier_index, alice_index);
+
+        // The result does not depend on how the work is split, and the pool
+        // can be reused
+        let sequential =
+            decrypt_notes(&DecryptionThreadPool::new(1), &transactions, &keys).unwrap();
+        assert_eq!(sequential.len(), decrypted.len());
+        let again = decrypt_notes(&pool, &transactions, &keys).unwrap();
+        assert_eq!(again.len(), decrypted.len());
+
+        let incoming_only = DecryptionKeys::with_view_keys(
+            vec![bob.view_key().clone(), alice.view_key().clone()],
+            vec![],
+        )
+        .unwrap();
+        let single = decrypt_transaction_notes(&transactions[1], &incoming_only);
+        assert_eq!(single.len(), 2);
+        assert!(single
+            .iter()
+            .all(|decrypted| decrypted.transaction_index == 0));
+
+        // Incoming view keys cannot check the owner of diversified notes, so
+        // they only decrypt the notes sent to regular addresses
+        let regular_only = DecryptionKeys::new(keys.incoming_view_keys().to_vec(), vec![]);
+        let decrypted = decrypt_transaction_notes(&transactions[1], &regular_only);
+        assert_eq!(decrypted.len(), 1);
+        assert_eq!(decrypted[0].output_index, 1);
+        assert_eq!(decrypted[0].key, MatchedKey::Incoming(1));
+    }
+
+    #[test]
+    fn test_decrypt_notes_forged_owner() {
+        let alice = SaplingKey::generate_key();
+        let bob = SaplingKey::generate_key();
+
+        // Bob sends notes to the addresses of alice, but makes them
+        // spendable by himself
+        let (_, diversified_address) = alice.view_key().next_diversified_address(1).unwrap();
+        let forged_addresses = [
+            DiversifiedAddress::from(alice.public_address()),
+            diversified_address,
+        ]
+        .map(|address| DiversifiedAddress {
+            owner: bob.public_address(),
+            ..address
+        });
+        let transactions: Arc<[Transaction]> =
+            vec![make_transaction(&bob, &forged_addresses)].into();
+
+        let keys = DecryptionKeys::with_view_keys(vec![alice.view_key().clone()], vec![]).unwrap();
+        let pool = DecryptionThreadPool::new(2);
+        assert!(decrypt_notes(&pool, &transactions, &keys)
+            .unwrap()
+            .is_empty());
+
+        let keys = DecryptionKeys::new(vec![alice.incoming_view_key().clone()], vec![]);
+        assert!(decrypt_notes(&pool, &transactions, &keys)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_decryption_thread_panic() {
+        let pool = DecryptionThreadPool::new(2);
+
+        let result = pool.map(4, |index| {
+            if index == 1 {
+                panic!("decryption failed");
+            }
+            index
+        });
+        assert!(matches!(
+            result,
+            Err(e) if matches!(e.kind, IronfishErrorKind::ThreadPanicked)
+        ));
+
+        // The threads survive the panic
+        assert_eq!(pool.thread_count(), 2);
+        assert_eq!(pool.map(4, |index| index * 2).unwrap(), vec![0, 2, 4, 6]);
+    }
+}
@@ -0,0 +1,389 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::collections::HashMap;
+
+use crate::{
+    assets::asset_identifier::AssetIdentifier,
+    errors::{IronfishError, IronfishErrorKind},
+    note::Note,
+    witness::WitnessTrait,
+};
+
+use super::value_balances::ValueBalances;
+
+/// Maximum number of branches explored by [`SelectionStrategy::BranchAndBound`]
+/// for a single asset before falling back to
+/// [`SelectionStrategy::LargestFirst`].
+const BRANCH_AND_BOUND_MAX_TRIES: usize = 100_000;
+
+/// A note that can be spent, along with the witness proving its location in
+/// the note commitment tree.
+pub struct SpendableNote<'a> {
+    pub note: Note,
+    pub witness: &'a dyn WitnessTrait,
+    /// Index of the diversified address that received the note, as reported
+    /// by [`crate::transaction::note_decryption::DecryptedNote`]
+    pub diversifier_index: u64,
+}
+
+impl<'a> SpendableNote<'a> {
+    pub fn new(note: Note, witness: &'a dyn WitnessTrait) -> Self {
+        Self::new_diversified(note, witness, 0)
+    }
+
+    /// A note sent to the diversified address at `diversifier_index`
+    pub fn new_diversified(
+        note: Note,
+        witness: &'a dyn WitnessTrait,
+        diversifier_index: u64,
+    ) -> Self {
+        Self {
+            note,
+            witness,
+            diversifier_index,
+        }
+    }
+}
+
+/// The strategy used to pick which notes to spend for each asset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SelectionStrategy {
+    /// Spend the notes with the largest values first. Minimizes the number of
+    /// spends in the transaction.
+    LargestFirst,
+    /// Spend the notes with the smallest values first. Consolidates small
+    /// notes, at the cost of larger transactions.
+    SmallestFirst,
+    /// Search for a set of notes whose values add up to exactly the amount
+    /// needed, so that no change note has to be created. Falls back to
+    /// [`SelectionStrategy::LargestFirst`] if no such set is found.
+    BranchAndBound,
+}
+
+/// Pick notes from `candidates` so that, for every asset in `targets`, the
+/// total value of the selected notes of that asset is at least the target
+/// amount.
+///
+/// Returns the indexes of the selected candidates, in ascending order. Fails
+/// with [`IronfishErrorKind::InvalidBalance`] if the candidates for any asset
+/// are not enough to cover its target, with the asset and amounts in the
+/// payload.
+pub fn select_notes(
+    candidates: &[SpendableNote],
+    targets: &HashMap<AssetIdentifier, u64>,
+    strategy: SelectionStrategy,
+) -> Result<Vec<usize>, IronfishError> {
+    let mut selected = vec![];
+
+    for (asset_id, target) in targets.iter() {
+        if *target == 0 {
+            continue;
+        }
+
+        let values: Vec<(usize, u64)> = candidates
+            .iter()
+            .enumerate()
+            .filter(|(_, candidate)| candidate.note.asset_id() == asset_id)
+            .map(|(index, candidate)| (index, candidate.note.value()))
+            .collect();
+
+        let asset_selection = match strategy {
+            SelectionStrategy::LargestFirst => largest_first(values.clone(), *target),
+            SelectionStrategy::SmallestFirst => smallest_first(values.clone(), *target),
+            SelectionStrategy::BranchAndBound => branch_and_bound(values.clone(), *target),
+        };
+
+        match asset_selection {
+            Some(asset_selection) => selected.extend(asset_selection),
+            None => return Err(insufficient_funds_error(asset_id, &values, *target)),
+        }
+    }
+
+    selected.sort_unstable();
+    Ok(selected)
+}
+
+/// Error for candidates of `asset_id` whose values do not cover `target`,
+/// reported as a transaction spending all of them to pay `target`
+fn insufficient_funds_error(
+    asset_id: &AssetIdentifier,
+    values: &[(usize, u64)],
+    target: u64,
+) -> IronfishError {
+    let mut balances = ValueBalances::new();
+    let result = values
+        .iter()
+        .try_for_each(|(_, value)| balances.add(asset_id, i64::try_from(*value)?))
+        .and_then(|_| balances.subtract(asset_id, i64::try_from(target)?))
+        .and_then(|_| balances.change(0));
+
+    match result {
+        Err(error) => error,
+        Ok(_) => IronfishError::new(IronfishErrorKind::InvalidBalance),
+    }
+}
+
+fn largest_first(mut values: Vec<(usize, u64)>, target: u64) -> Option<Vec<usize>> {
+    values.sort_by(|a, b| b.1.cmp(&a.1));
+    accumulate(&values, target)
+}
+
+fn smallest_first(mut values: Vec<(usize, u64)>, target: u64) -> Option<Vec<usize>> {
+    values.sort_by(|a, b| a.1.cmp(&b.1));
+    accumulate(&values, target)
+}
+
+/// Take values in order until their sum reaches the target.
+fn accumulate(values: &[(usize, u64)], target: u64) -> Option<Vec<usize>> {
+    let mut selected = vec![];
+    let mut total: u128 = 0;
+
+    for (index, value) in values {
+        if total >= target as u128 {
+            break;
+        }
+        selected.push(*index);
+        total += *value as u128;
+    }
+
+    if total >= target as u128 {
+        Some(selected)
+    } else {
+        None
+    }
+}
+
+fn branch_and_bound(mut values: Vec<(usize, u64)>, target: u64) -> Option<Vec<usize>> {
+    values.sort_by(|a, b| b.1.cmp(&a.1));
+
+    // remaining[i] is the sum of all the values from position i onwards
+    let mut remaining = vec![0u128; values.len() + 1];
+    for position in (0..values.len()).rev() {
+        remaining[position] = remaining[position + 1] + values[position].1 as u128;
+    }
+
+    let mut search = BranchAndBound {
+        values: &values,
+        remaining,
+        target: target as u128,
+        tries: 0,
+        selected: vec![],
+    };
+
+    if search.search() {
+        Some(search.selected)
+    } else {
+        accumulate(&values, target)
+    }
+}
+
+/// Depth-first search for a subset of values that adds up to exactly the
+/// target, pruning branches that overshoot the target or can no longer reach
+/// it. The search can go as deep as the number of values, so it keeps the
+/// branches to explore on an explicit stack rather than recursing.
+struct BranchAndBound<'a> {
+    /// Candidate values, sorted from largest to smallest
+    values: &'a [(usize, u64)],
+    remaining: Vec<u128>,
+    target: u128,
+    tries: usize,
+    selected: Vec<usize>,
+}
+
+impl<'a> BranchAndBound<'a> {
+    fn search(&mut self) -> bool {
+        // Branches to explore, as the position of the next value to include
+        // or exclude, the total so far and the number of selected values
+        let mut stack: Vec<(usize, u128, usize)> = vec![(0, 0, 0)];
+
+        while let Some((position, total, selected_count)) = stack.pop() {
+            self.selected.truncate(selected_count);
+
+            if total == self.target {
+                return true;
+            }
+            if total > self.target
+                || position == self.values.len()
+                || total + self.remaining[position] < self.target
+            {
+                continue;
+            }
+
+            self.tries += 1;
+            if self.tries > BRANCH_AND_BOUND_MAX_TRIES {
+                return false;
+            }
+
+            // Exclude the value at this position, once the branches that
+            // include it are explored. Notes with the same value are
+            // interchangeable, so skip over all of them to avoid exploring
+            // identical branches.
+            let (index, value) = self.values[position];
+            let mut next = position + 1;
+            while next < self.values.len() && self.values[next].1 == value {
+                next += 1;
+            }
+            stack.push((next, total, selected_count));
+
+            // Include it
+            self.selected.push(index);
+            stack.push((position + 1, total + value as u128, selected_count + 1));
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::{branch_and_bound, select_notes, SelectionStrategy, SpendableNote};
+    use crate::{
+        assets::{asset::Asset, asset_identifier::NATIVE_ASSET},
+        errors::{IronfishErrorKind, IronfishErrorPayload},
+        note::Note,
+        test_util::make_fake_witness,
+        witness::Witness,
+        SaplingKey,
+    };
+
+    fn make_notes(key: &SaplingKey, values: &[u64]) -> Vec<(Note, Witness)> {
+        values
+            .iter()
+            .map(|value| {
+                let note = Note::new(
+                    key.public_address(),
+                    *value,
+                    "",
+                    NATIVE_ASSET,
+                    key.public_address(),
+                );
+                let witness = make_fake_witness(&note);
+                (note, witness)
+            })
+            .collect()
+    }
+
+    fn candidates(notes: &[(Note, Witness)]) -> Vec<SpendableNote> {
+        notes
+            .iter()
+            .map(|(note, witness)| SpendableNote::new(note.clone(), witness))
+            .collect()
+    }
+
+    #[test]
+    fn test_largest_first() {
+        let key = SaplingKey::generate_key();
+        let notes = make_notes(&key, &[5, 20, 1, 10]);
+        let candidates = candidates(&notes);
+
+        let targets = HashMap::from([(NATIVE_ASSET, 25)]);
+        let selected =
+            select_notes(&candidates, &targets, SelectionStrategy::LargestFirst).unwrap();
+
+        assert_eq!(selected, vec![1, 3]);
+    }
+
+    #[test]
+    fn test_smallest_first() {
+        let key = SaplingKey::generate_key();
+        let notes = make_notes(&key, &[5, 20, 1, 10]);
+        let candidates = candidates(&notes);
+
+        let targets = HashMap::from([(NATIVE_ASSET, 12)]);
+        let selected =
+            select_notes(&candidates, &targets, SelectionStrategy::SmallestFirst).unwrap();
+
+        assert_eq!(selected, vec![0, 2, 3]);
+    }
+
+    #[test]
+    fn test_branch_and_bound_exact_match() {
+        let key = SaplingKey::generate_key();
+        let notes = make_notes(&key, &[5, 20, 1, 10, 7]);
+        let candidates = candidates(&notes);
+
+        // Largest first would pick 20 and 10, which requires change
+        let targets = HashMap::from([(NATIVE_ASSET, 22)]);
+        let selected =
+            select_notes(&candidates, &targets, SelectionStrategy::BranchAndBound).unwrap();
+
+        let total: u64 = selected.iter().map(|i| candidates[*i].note.value()).sum();
+        assert_eq!(total, 22);
+    }
+
+    #[test]
+    fn test_branch_and_bound_falls_back() {
+        let key = SaplingKey::generate_key();
+        let notes = make_notes(&key, &[5, 20, 10]);
+        let candidates = candidates(&notes);
+
+        let targets = HashMap::from([(NATIVE_ASSET, 12)]);
+        let selected =
+            select_notes(&candidates, &targets, SelectionStrategy::BranchAndBound).unwrap();
+
+        assert_eq!(selected, vec![1]);
+    }
+
+    #[test]
+    fn test_branch_and_bound_many_candidates() {
+        // The search goes as deep as the number of candidates it includes
+        let values: Vec<(usize, u64)> = (0..1_000_000).map(|index| (index, 1)).collect();
+        let selected = branch_and_bound(values, 500_000).unwrap();
+
+        assert_eq!(selected.len(), 500_000);
+    }
+
+    #[test]
+    fn test_select_notes_multiple_assets() {
+        let key = SaplingKey::generate_key();
+        let asset = Asset::new(key.public_address(), "Testcoin", "").unwrap();
+
+        let mut notes = make_notes(&key, &[5, 20]);
+        let asset_note = Note::new(
+            key.public_address(),
+            3,
+            "",
+            *asset.id(),
+            key.public_address(),
+        );
+        let asset_witness = make_fake_witness(&asset_note);
+        notes.push((asset_note, asset_witness));
+        let candidates = candidates(&notes);
+
+        let targets = HashMap::from([(NATIVE_ASSET, 4), (*asset.id(), 2)]);
+        let selected =
+            select_notes(&candidates, &targets, SelectionStrategy::SmallestFirst).unwrap();
+
+        assert_eq!(selected, vec![0, 2]);
+    }
+
+    #[test]
+    fn test_select_notes_insufficient_funds() {
+        let key = SaplingKey::generate_key();
+        let notes = make_notes(&key, &[5, 20]);
+        let candidates = candidates(&notes);
+
+        let targets = HashMap::from([(NATIVE_ASSET, 26)]);
+
+        for strategy in [
+            SelectionStrategy::LargestFirst,
+            SelectionStrategy::SmallestFirst,
+            SelectionStrategy::BranchAndBound,
+        ] {
+            let error = select_notes(&candidates, &targets, strategy).unwrap_err();
+            assert!(matches!(error.kind, IronfishErrorKind::InvalidBalance));
+            match error.payload {
+                Some(IronfishErrorPayload::InvalidBalance(details)) => {
+                    assert_eq!(details.asset_id, NATIVE_ASSET);
+                    assert_eq!(details.spends_and_mints, 25);
+                    assert_eq!(details.outputs_and_burns, 26);
+                }
+                _ => panic!("expected the balance details in the payload"),
+            }
+        }
+    }
+}
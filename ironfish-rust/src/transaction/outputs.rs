@@ -4,15 +4,17 @@
 
 use crate::{
     errors::{IronfishError, IronfishErrorKind},
-    keys::EphemeralKeyPair,
-    merkle_note::MerkleNote,
+    keys::{DiversifiedAddress, EphemeralKeyPair},
+    merkle_note::{merkle_note_size, MerkleNote, MERKLE_NOTE_SIZE},
     note::Note,
     sapling_bls12::SAPLING,
+    serializing::read_scalar,
     OutgoingViewKey,
 };
 
 use bellperson::groth16;
 use blstrs::{Bls12, Scalar};
+use byteorder::{ReadBytesExt, WriteBytesExt};
 use ff::Field;
 use group::Curve;
 use ironfish_zkp::{primitives::ValueCommitment, proofs::Output, redjubjub, ProofGenerationKey};
@@ -21,7 +23,7 @@ use rand::thread_rng;
 
 use std::io;
 
-use super::utils::verify_output_proof;
+use super::{utils::verify_output_proof, TransactionVersion};
 
 /// Parameters used when constructing proof that a new note exists. The owner
 /// of this note is the recipient of funds in a transaction. The note is signed
@@ -34,6 +36,10 @@ pub struct OutputBuilder {
     /// random number. Randomized to help maintain zero knowledge.
     pub(crate) value_commitment: ValueCommitment,
 
+    /// Address the note is encrypted to. The regular public address of the
+    /// owner, unless the note is sent to a diversified address.
+    pub(crate) recipient: DiversifiedAddress,
+
     /// Flag to determine if the output to build is used for a miner's fee
     /// transaction. Not used directly here, but passed down into the
     /// [`MerkleNote`].
@@ -42,14 +48,32 @@ pub struct OutputBuilder {
 
 pub const PROOF_SIZE: u32 = 192;
 
+/// Size of a serialized [`OutputDescription`] before
+/// [`TransactionVersion::V3`], see [`output_description_size`]
+pub const OUTPUT_DESCRIPTION_SIZE: usize = PROOF_SIZE as usize + MERKLE_NOTE_SIZE;
+
+/// Size of a serialized [`OutputDescription`] in a transaction of the given
+/// version
+pub fn output_description_size(version: TransactionVersion) -> usize {
+    PROOF_SIZE as usize + merkle_note_size(version)
+}
+
 impl OutputBuilder {
     /// Create a new [`OutputBuilder`] attempting to create a note.
     pub(crate) fn new(note: Note) -> Self {
+        let recipient = DiversifiedAddress::from(note.owner);
+        Self::new_diversified(note, recipient)
+    }
+
+    /// Create a new [`OutputBuilder`] for a note sent to the diversified
+    /// address `recipient`, which must be owned by the owner of the note.
+    pub(crate) fn new_diversified(note: Note, recipient: DiversifiedAddress) -> Self {
         let value_commitment = ValueCommitment::new(note.value, note.asset_generator());
 
         Self {
             note,
             value_commitment,
+            recipient,
             is_miners_fee: false,
         }
     }
@@ -64,6 +88,45 @@ impl OutputBuilder {
         self.is_miners_fee
     }
 
+    /// Load an [`OutputBuilder`] previously stored with
+    /// [`OutputBuilder::write`].
+    pub(crate) fn read<R: io::Read>(mut reader: R) -> Result<Self, IronfishError> {
+        let note = Note::read(&mut reader)?;
+        let value_commitment = ValueCommitment {
+            value: note.value,
+            randomness: read_scalar(&mut reader)?,
+            asset_generator: note.asset_generator(),
+        };
+        let recipient = DiversifiedAddress::read(&mut reader)?;
+        if recipient.owner != note.owner {
+            return Err(IronfishError::new(IronfishErrorKind::InvalidData));
+        }
+        let is_miners_fee = match reader.read_u8()? {
+            0 => false,
+            1 => true,
+            _ => return Err(IronfishError::new(IronfishErrorKind::InvalidData)),
+        };
+
+        Ok(Self {
+            note,
+            value_commitment,
+            recipient,
+            is_miners_fee,
+        })
+    }
+
+    /// Store the note, value commitment randomness and recipient of this
+    /// [`OutputBuilder`], so that the proof can be generated later, or by a
+    /// different process.
+    pub(crate) fn write<W: io::Write>(&self, mut writer: W) -> Result<(), IronfishError> {
+        self.note.write(&mut writer)?;
+        writer.write_all(&self.value_commitment.randomness.to_bytes())?;
+        self.recipient.write(&mut writer)?;
+        writer.write_u8(self.is_miners_fee as u8)?;
+
+        Ok(())
+    }
+
     /// Get the value_commitment from this proof as an edwards Point.
     ///
     /// This integrates the value and randomness into a single point, using an
@@ -79,8 +142,13 @@ impl OutputBuilder {
     ///
     /// Verifies the proof before returning to prevent posting broken
     /// transactions.
+    ///
+    /// The note is encrypted to the recipient if `version` has
+    /// [`TransactionVersion::has_diversified_notes`], and to the owner
+    /// otherwise.
     pub(crate) fn build(
         &self,
+        version: TransactionVersion,
         proof_generation_key: &ProofGenerationKey,
         outgoing_view_key: &OutgoingViewKey,
         public_key_randomness: &jubjub::Fr,
@@ -100,15 +168,30 @@ impl OutputBuilder {
 
         let proof =
             groth16::create_random_proof(circuit, &SAPLING.output_params, &mut thread_rng())?;
-        let merkle_note = if self.is_miners_fee {
-            MerkleNote::new_for_miners_fee(&self.note, &self.value_commitment, &diffie_hellman_keys)
-        } else {
-            MerkleNote::new(
+        let recipient = match version.has_diversified_notes() {
+            true => Some(&self.recipient),
+            false => None,
+        };
+        let merkle_note = match (self.is_miners_fee, recipient) {
+            (true, _) => MerkleNote::new_for_miners_fee(
+                &self.note,
+                recipient,
+                &self.value_commitment,
+                &diffie_hellman_keys,
+            ),
+            (false, Some(recipient)) => MerkleNote::new_diversified(
                 outgoing_view_key,
+                recipient,
                 &self.note,
                 &self.value_commitment,
                 &diffie_hellman_keys,
-            )
+            ),
+            (false, None) => MerkleNote::new(
+                outgoing_view_key,
+                &self.note,
+                &self.value_commitment,
+                &diffie_hellman_keys,
+            ),
         };
 
         let description = OutputDescription { proof, merkle_note };
@@ -143,9 +226,12 @@ impl OutputDescription {
     /// Load an [`OutputDescription`] from a Read implementation( e.g: socket, file)
     /// This is the main entry-point when reconstructing a serialized
     /// transaction.
-    pub fn read<R: io::Read>(mut reader: R) -> Result<Self, IronfishError> {
+    pub fn read<R: io::Read>(
+        mut reader: R,
+        version: TransactionVersion,
+    ) -> Result<Self, IronfishError> {
         let proof = groth16::Proof::read(&mut reader)?;
-        let merkle_note = MerkleNote::read(&mut reader)?;
+        let merkle_note = MerkleNote::read(&mut reader, version)?;
 
         Ok(OutputDescription { proof, merkle_note })
     }
@@ -167,10 +253,17 @@ impl OutputDescription {
     }
 
     fn verify_not_small_order(&self) -> Result<(), IronfishError> {
+        let diversified_small_order = self.merkle_note.diversified.as_ref().map_or(false, |d| {
+            ExtendedPoint::from(d.ephemeral_public_key)
+                .is_small_order()
+                .into()
+        });
+
         if self.merkle_note.value_commitment.is_small_order().into()
             || ExtendedPoint::from(self.merkle_note.ephemeral_public_key)
                 .is_small_order()
                 .into()
+            || diversified_small_order
         {
             return Err(IronfishError::new(IronfishErrorKind::IsSmallOrder));
         }
@@ -224,11 +317,13 @@ impl OutputDescription {
 
 #[cfg(test)]
 mod test {
-    use super::{OutputBuilder, OutputDescription};
+    use super::{output_description_size, OutputBuilder, OutputDescription};
     use crate::{
-        assets::asset_identifier::NATIVE_ASSET, keys::SaplingKey,
-        merkle_note::NOTE_ENCRYPTION_MINER_KEYS, note::Note,
-        transaction::utils::verify_output_proof,
+        assets::asset_identifier::NATIVE_ASSET,
+        keys::SaplingKey,
+        merkle_note::NOTE_ENCRYPTION_MINER_KEYS,
+        note::Note,
+        transaction::{utils::verify_output_proof, TransactionVersion},
     };
     use ff::{Field, PrimeField};
     use group::Curve;
@@ -259,6 +354,7 @@ mod test {
 
         let proof = output
             .build(
+                TransactionVersion::latest(),
                 &spender_key.sapling_proof_generation_key(),
                 spender_key.outgoing_view_key(),
                 &public_key_randomness,
@@ -292,6 +388,7 @@ mod test {
         let output = OutputBuilder::new(note);
         let proof = output
             .build(
+                TransactionVersion::latest(),
                 &spender_key.sapling_proof_generation_key(),
                 spender_key.outgoing_view_key(),
                 &public_key_randomness,
@@ -331,6 +428,7 @@ mod test {
         let output = OutputBuilder::new(note);
         let description = output
             .build(
+                TransactionVersion::latest(),
                 &spender_key.sapling_proof_generation_key(),
                 spender_key.outgoing_view_key(),
                 &public_key_randomness,
@@ -347,6 +445,7 @@ mod test {
         // Wrong spender key
         assert!(output
             .build(
+                TransactionVersion::latest(),
                 &receiver_key.sapling_proof_generation_key(),
                 receiver_key.outgoing_view_key(),
                 &public_key_randomness,
@@ -357,6 +456,7 @@ mod test {
         // Wrong public key randomness
         assert!(output
             .build(
+                TransactionVersion::latest(),
                 &spender_key.sapling_proof_generation_key(),
                 spender_key.outgoing_view_key(),
                 &other_public_key_randomness,
@@ -367,6 +467,7 @@ mod test {
         // Wrong randomized public key
         assert!(output
             .build(
+                TransactionVersion::latest(),
                 &spender_key.sapling_proof_generation_key(),
                 spender_key.outgoing_view_key(),
                 &public_key_randomness,
@@ -401,6 +502,7 @@ mod test {
         let output = OutputBuilder::new(note);
         let proof = output
             .build(
+                TransactionVersion::latest(),
                 &spender_key.sapling_proof_generation_key(),
                 spender_key.outgoing_view_key(),
                 &public_key_randomness,
@@ -415,9 +517,11 @@ mod test {
         proof
             .write(&mut serialized_proof)
             .expect("Should be able to serialize proof");
-        let read_back_proof: OutputDescription =
-            OutputDescription::read(&mut serialized_proof[..].as_ref())
-                .expect("Should be able to deserialize valid proof");
+        let read_back_proof: OutputDescription = OutputDescription::read(
+            &mut serialized_proof[..].as_ref(),
+            TransactionVersion::latest(),
+        )
+        .expect("Should be able to deserialize valid proof");
 
         assert_eq!(proof.proof.a, read_back_proof.proof.a);
         assert_eq!(proof.proof.b, read_back_proof.proof.b);
@@ -449,4 +553,67 @@ mod test {
             .expect("should be able to serialize proof again");
         assert_eq!(serialized_proof, serialized_again);
     }
+
+    #[test]
+    fn test_output_diversified() {
+        let spender_key = SaplingKey::generate_key();
+        let receiver_key = SaplingKey::generate_key();
+        let public_key_randomness = jubjub::Fr::random(thread_rng());
+        let randomized_public_key =
+            redjubjub::PublicKey(spender_key.view_key.authorizing_key.into())
+                .randomize(public_key_randomness, *SPENDING_KEY_GENERATOR);
+
+        let (index, recipient) = receiver_key.view_key().next_diversified_address(1).unwrap();
+        let note = Note::new(
+            recipient.owner(),
+            42,
+            "",
+            NATIVE_ASSET,
+            spender_key.public_address(),
+        );
+
+        let output = OutputBuilder::new_diversified(note, recipient);
+        let description = output
+            .build(
+                TransactionVersion::V3,
+                &spender_key.sapling_proof_generation_key(),
+                spender_key.outgoing_view_key(),
+                &public_key_randomness,
+                &randomized_public_key,
+            )
+            .expect("should be able to build output proof");
+
+        let mut serialized = vec![];
+        description.write(&mut serialized).unwrap();
+        assert_eq!(
+            serialized.len(),
+            output_description_size(TransactionVersion::V3)
+        );
+        let description = OutputDescription::read(&serialized[..], TransactionVersion::V3)
+            .expect("should be able to deserialize output");
+
+        let (decrypted_index, note) = description
+            .merkle_note
+            .decrypt_note_for_owner(receiver_key.view_key())
+            .unwrap();
+        assert_eq!(decrypted_index, index);
+        assert_eq!(note.owner(), recipient.owner());
+        assert_eq!(note.value(), 42);
+
+        // Earlier versions encrypt the note to the owner address
+        let description = output
+            .build(
+                TransactionVersion::V2,
+                &spender_key.sapling_proof_generation_key(),
+                spender_key.outgoing_view_key(),
+                &public_key_randomness,
+                &randomized_public_key,
+            )
+            .unwrap();
+        assert!(description.merkle_note.diversified.is_none());
+        assert!(description
+            .merkle_note
+            .decrypt_note_for_owner(receiver_key.view_key())
+            .is_err());
+    }
 }
@@ -0,0 +1,653 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Payment disclosures, which let the sender of a transaction prove to a third
+//! party which address and amount one of its outputs paid, without revealing
+//! their [`OutgoingViewKey`](crate::OutgoingViewKey).
+//!
+//! A disclosure reveals the owner address and the ephemeral secret key of a
+//! single output, which are otherwise only readable with the outgoing view
+//! key. They are enough to decrypt that output and nothing else. Outputs of
+//! [`TransactionVersion::V3`](super::TransactionVersion::V3) transactions
+//! disclose the transmission key of the diversified address that received the
+//! note instead of the owner address.
+//!
+//! The disclosure is signed with the spend authorizing key of the sender,
+//! randomized with the public key randomness of the transaction, so that it
+//! verifies against the randomized public key of the transaction. This proves
+//! that the disclosure was made by whoever authorized the transaction, and
+//! binds it to the transaction signature hash. Multisig accounts sign it with
+//! the same FROST ceremony as their transactions, see
+//! [`UnsignedPaymentDisclosure`].
+//!
+//! The public key randomness is not part of the posted [`Transaction`]. The
+//! sender must store it when creating the transaction, from
+//! [`ProposedTransaction::public_key_randomness`](super::ProposedTransaction::public_key_randomness)
+//! or [`UnsignedTransaction::public_key_randomness`](super::unsigned::UnsignedTransaction::public_key_randomness),
+//! as the outputs of a transaction whose randomness was lost cannot be
+//! disclosed.
+
+use std::{collections::BTreeMap, io};
+
+use blake2b_simd::Params as Blake2b;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use group::GroupEncoding;
+use ironfish_frost::{
+    frost::{
+        aggregate, keys::PublicKeyPackage, round1::SigningCommitments, round2::SignatureShare,
+        Identifier, RandomizedParams, Randomizer, SigningPackage as FrostSigningPackage,
+    },
+    participant::Identity,
+};
+use ironfish_zkp::{
+    constants::SPENDING_KEY_GENERATOR,
+    redjubjub::{self, Signature},
+};
+use rand::thread_rng;
+
+use super::{Transaction, TRANSACTION_PUBLIC_KEY_SIZE};
+use crate::{
+    errors::{IronfishError, IronfishErrorKind},
+    frost_utils::signing_package::DisclosureSigningPackage,
+    keys::{OutgoingViewKey, PublicAddress, SaplingKey},
+    note::Note,
+    serializing::read_scalar,
+};
+
+const PAYMENT_DISCLOSURE_PERSONALIZATION: &[u8; 16] = b"Iron Fish Disclo";
+
+/// Size of a serialized [`PaymentDisclosure`]: output index, owner address,
+/// ephemeral secret key and signature.
+pub const PAYMENT_DISCLOSURE_SIZE: usize = 4 + 32 + 32 + 64;
+
+/// Proof of the recipient and contents of one output of a transaction
+#[derive(Clone)]
+pub struct PaymentDisclosure {
+    /// Index of the disclosed output in the transaction
+    output_index: u32,
+
+    /// Address the note was encrypted to, as encrypted with the outgoing view
+    /// key of the sender: the owner address, or the transmission key of the
+    /// diversified address that received the note
+    owner: PublicAddress,
+
+    /// Ephemeral secret key used to encrypt the note
+    ephemeral_secret_key: jubjub::Fr,
+
+    /// Signature over the fields above and the transaction signature hash,
+    /// with the randomized spend authorizing key of the sender
+    signature: Signature,
+}
+
+/// A payment disclosure that still needs its signature, used to disclose the
+/// outputs of multisig accounts created with
+/// [`crate::frost_utils::split_spender_key::split_spender_key`].
+///
+/// It only needs the outgoing view key of the sender. The coordinator creates
+/// the signing package from the commitments of the participants, each
+/// participant signs it with the randomizer returned by
+/// [`UnsignedPaymentDisclosure::public_key_randomness`], which is the public
+/// key randomness of the transaction, and the coordinator aggregates the
+/// signature shares.
+#[derive(Clone)]
+pub struct UnsignedPaymentDisclosure {
+    output_index: u32,
+    owner: PublicAddress,
+    ephemeral_secret_key: jubjub::Fr,
+    public_key_randomness: jubjub::Fr,
+    randomized_public_key: redjubjub::PublicKey,
+    disclosure_hash: [u8; 32],
+}
+
+impl UnsignedPaymentDisclosure {
+    /// Prepare the disclosure of the output at `output_index` of
+    /// `transaction`, which must have been created by the account with
+    /// `outgoing_view_key` with the given `public_key_randomness` (see
+    /// [`super::unsigned::UnsignedTransaction::public_key_randomness`]).
+    pub fn new(
+        transaction: &Transaction,
+        output_index: u32,
+        outgoing_view_key: &OutgoingViewKey,
+        public_key_randomness: jubjub::Fr,
+    ) -> Result<Self, IronfishError> {
+        let merkle_note = transaction
+            .outputs()
+            .get(output_index as usize)
+            .ok_or_else(|| IronfishError::new(IronfishErrorKind::InvalidData))?
+            .merkle_note();
+
+        let (owner, ephemeral_secret_key) =
+            merkle_note.decrypt_note_encryption_keys(outgoing_view_key)?;
+
+        let disclosure_hash =
+            hash_disclosure(transaction, output_index, &owner, &ephemeral_secret_key)?;
+
+        Ok(UnsignedPaymentDisclosure {
+            output_index,
+            owner,
+            ephemeral_secret_key,
+            public_key_randomness,
+            randomized_public_key: redjubjub::PublicKey(transaction.randomized_public_key().0),
+            disclosure_hash,
+        })
+    }
+
+    /// Randomness of the transaction, used as the randomizer of the FROST
+    /// signature shares
+    pub fn public_key_randomness(&self) -> jubjub::Fr {
+        self.public_key_randomness
+    }
+
+    /// Data to be signed by the participants of a multisig account
+    pub fn disclosure_hash(&self) -> [u8; 32] {
+        self.disclosure_hash
+    }
+
+    /// Create the signing package sent to the participants from the
+    /// commitments of the signers
+    pub fn signing_package<Iter>(&self, commitments: Iter) -> DisclosureSigningPackage
+    where
+        Iter: IntoIterator<Item = (Identity, SigningCommitments)>,
+    {
+        let mut commitments_map = BTreeMap::new();
+        let mut signers = Vec::new();
+        for (signer_identity, signer_commitments) in commitments {
+            commitments_map.insert(signer_identity.to_frost_identifier(), signer_commitments);
+            signers.push(signer_identity);
+        }
+
+        let frost_signing_package =
+            FrostSigningPackage::new(commitments_map, &self.disclosure_hash);
+
+        DisclosureSigningPackage {
+            unsigned_disclosure: self.clone(),
+            frost_signing_package,
+            signers,
+        }
+    }
+
+    /// Sign with the spend authorizing key of a single-signer account
+    pub fn sign(self, spender_key: &SaplingKey) -> Result<PaymentDisclosure, IronfishError> {
+        let private_key = redjubjub::PrivateKey(spender_key.spend_authorizing_key)
+            .randomize(self.public_key_randomness);
+        let public_key = redjubjub::PublicKey::from_private(&private_key, *SPENDING_KEY_GENERATOR);
+        if public_key.0 != self.randomized_public_key.0 {
+            return Err(IronfishError::new(IronfishErrorKind::InvalidSigningKey));
+        }
+
+        let signature = private_key.sign(
+            &signature_data(&self.randomized_public_key, &self.disclosure_hash),
+            &mut thread_rng(),
+            *SPENDING_KEY_GENERATOR,
+        );
+
+        Ok(self.add_signature(signature))
+    }
+
+    /// Aggregate the signature shares of the participants of a multisig
+    /// account into the signature of the disclosure
+    pub fn aggregate_signature_shares(
+        self,
+        public_key_package: &PublicKeyPackage,
+        signing_package: &FrostSigningPackage,
+        signature_shares: BTreeMap<Identifier, SignatureShare>,
+    ) -> Result<PaymentDisclosure, IronfishError> {
+        let randomizer = Randomizer::deserialize(&self.public_key_randomness.to_bytes())
+            .map_err(|e| IronfishError::new_with_source(IronfishErrorKind::InvalidRandomizer, e))?;
+        let randomized_params =
+            RandomizedParams::from_randomizer(public_key_package.verifying_key(), randomizer);
+
+        let group_signature = aggregate(
+            signing_package,
+            &signature_shares,
+            public_key_package,
+            &randomized_params,
+        )
+        .map_err(|e| {
+            IronfishError::new_with_source(IronfishErrorKind::FailedSignatureAggregation, e)
+        })?;
+
+        randomized_params
+            .randomized_verifying_key()
+            .verify(&self.disclosure_hash, &group_signature)
+            .map_err(|e| {
+                IronfishError::new_with_source(IronfishErrorKind::FailedSignatureVerification, e)
+            })?;
+
+        let signature = Signature::read(&mut group_signature.serialize().as_ref())?;
+
+        // The account must be the one that created the transaction
+        if !self.randomized_public_key.verify(
+            &signature_data(&self.randomized_public_key, &self.disclosure_hash),
+            &signature,
+            *SPENDING_KEY_GENERATOR,
+        ) {
+            return Err(IronfishError::new(IronfishErrorKind::InvalidSigningKey));
+        }
+
+        Ok(self.add_signature(signature))
+    }
+
+    pub fn read<R: io::Read>(mut reader: R) -> Result<Self, IronfishError> {
+        let output_index = reader.read_u32::<LittleEndian>()?;
+        let owner = PublicAddress::read(&mut reader)?;
+        let ephemeral_secret_key = read_scalar(&mut reader)?;
+        let public_key_randomness = read_scalar(&mut reader)?;
+        let randomized_public_key = redjubjub::PublicKey::read(&mut reader)?;
+        let mut disclosure_hash = [0; 32];
+        reader.read_exact(&mut disclosure_hash)?;
+
+        Ok(UnsignedPaymentDisclosure {
+            output_index,
+            owner,
+            ephemeral_secret_key,
+            public_key_randomness,
+            randomized_public_key,
+            disclosure_hash,
+        })
+    }
+
+    pub fn write<W: io::Write>(&self, mut writer: W) -> Result<(), IronfishError> {
+        writer.write_u32::<LittleEndian>(self.output_index)?;
+        self.owner.write(&mut writer)?;
+        writer.write_all(&self.ephemeral_secret_key.to_bytes())?;
+        writer.write_all(&self.public_key_randomness.to_bytes())?;
+        writer.write_all(&self.randomized_public_key.0.to_bytes())?;
+        writer.write_all(&self.disclosure_hash)?;
+
+        Ok(())
+    }
+
+    fn add_signature(self, signature: Signature) -> PaymentDisclosure {
+        PaymentDisclosure {
+            output_index: self.output_index,
+            owner: self.owner,
+            ephemeral_secret_key: self.ephemeral_secret_key,
+            signature,
+        }
+    }
+}
+
+impl PaymentDisclosure {
+    /// Disclose the output at `output_index` of `transaction`, which must have
+    /// been created by `spender_key` with the given `public_key_randomness`
+    /// (see [`super::unsigned::UnsignedTransaction::public_key_randomness`]).
+    /// Multisig accounts use [`UnsignedPaymentDisclosure`] instead.
+    pub fn new(
+        transaction: &Transaction,
+        output_index: u32,
+        spender_key: &SaplingKey,
+        public_key_randomness: jubjub::Fr,
+    ) -> Result<Self, IronfishError> {
+        UnsignedPaymentDisclosure::new(
+            transaction,
+            output_index,
+            spender_key.outgoing_view_key(),
+            public_key_randomness,
+        )?
+        .sign(spender_key)
+    }
+
+    pub fn output_index(&self) -> u32 {
+        self.output_index
+    }
+
+    /// Check the disclosure against `transaction` and return the disclosed
+    /// note.
+    ///
+    /// Fails if the signature was not made by the sender of `transaction`, or
+    /// if the disclosed keys do not decrypt the output.
+    pub fn verify(&self, transaction: &Transaction) -> Result<Note, IronfishError> {
+        let merkle_note = transaction
+            .outputs()
+            .get(self.output_index as usize)
+            .ok_or_else(|| IronfishError::new(IronfishErrorKind::InvalidData))?
+            .merkle_note();
+
+        let randomized_public_key = transaction.randomized_public_key();
+        if randomized_public_key.0.is_small_order().into() {
+            return Err(IronfishError::new(IronfishErrorKind::IsSmallOrder));
+        }
+
+        let disclosure_hash = hash_disclosure(
+            transaction,
+            self.output_index,
+            &self.owner,
+            &self.ephemeral_secret_key,
+        )?;
+        if !randomized_public_key.verify(
+            &signature_data(randomized_public_key, &disclosure_hash),
+            &self.signature,
+            *SPENDING_KEY_GENERATOR,
+        ) {
+            return Err(IronfishError::new(IronfishErrorKind::InvalidSignature));
+        }
+
+        merkle_note.decrypt_note_with_ephemeral_secret(&self.owner, &self.ephemeral_secret_key)
+    }
+
+    pub fn read<R: io::Read>(mut reader: R) -> Result<Self, IronfishError> {
+        let output_index = reader.read_u32::<LittleEndian>()?;
+        let owner = PublicAddress::read(&mut reader)?;
+        let ephemeral_secret_key = read_scalar(&mut reader)?;
+        let signature = Signature::read(&mut reader)?;
+
+        Ok(PaymentDisclosure {
+            output_index,
+            owner,
+            ephemeral_secret_key,
+            signature,
+        })
+    }
+
+    pub fn write<W: io::Write>(&self, mut writer: W) -> Result<(), IronfishError> {
+        writer.write_u32::<LittleEndian>(self.output_index)?;
+        self.owner.write(&mut writer)?;
+        writer.write_all(&self.ephemeral_secret_key.to_bytes())?;
+        self.signature.write(&mut writer)?;
+
+        Ok(())
+    }
+}
+
+/// Hash of the transaction signature hash and of the disclosed fields
+fn hash_disclosure(
+    transaction: &Transaction,
+    output_index: u32,
+    owner: &PublicAddress,
+    ephemeral_secret_key: &jubjub::Fr,
+) -> Result<[u8; 32], IronfishError> {
+    let hash = Blake2b::new()
+        .hash_length(32)
+        .personal(PAYMENT_DISCLOSURE_PERSONALIZATION)
+        .to_state()
+        .update(&transaction.transaction_signature_hash()?)
+        .update(&output_index.to_le_bytes())
+        .update(&owner.public_address())
+        .update(&ephemeral_secret_key.to_bytes())
+        .finalize();
+
+    let mut disclosure_hash = [0; 32];
+    disclosure_hash.copy_from_slice(hash.as_bytes());
+    Ok(disclosure_hash)
+}
+
+/// Data signed by the sender: the randomized public key of the transaction,
+/// followed by the disclosure hash. The public key is prefixed to the
+/// message, as for the spend signatures.
+fn signature_data(
+    randomized_public_key: &redjubjub::PublicKey,
+    disclosure_hash: &[u8; 32],
+) -> [u8; 64] {
+    let mut data_to_be_signed = [0; 64];
+    data_to_be_signed[..TRANSACTION_PUBLIC_KEY_SIZE]
+        .copy_from_slice(&randomized_public_key.0.to_bytes());
+    data_to_be_signed[TRANSACTION_PUBLIC_KEY_SIZE..].copy_from_slice(disclosure_hash);
+    data_to_be_signed
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{BTreeMap, HashMap};
+
+    use ironfish_frost::{
+        frost::{round2, Randomizer},
+        nonces::deterministic_signing_nonces,
+    };
+
+    use super::{PaymentDisclosure, UnsignedPaymentDisclosure, PAYMENT_DISCLOSURE_SIZE};
+    use crate::{
+        assets::asset_identifier::NATIVE_ASSET,
+        errors::IronfishErrorKind,
+        frost_utils::{
+            signing_package::DisclosureSigningPackage, split_spender_key::split_spender_key,
+        },
+        keys::SaplingKey,
+        note::{MemoContent, Note},
+        test_util::{create_multisig_identities, make_fake_witness},
+        transaction::{ProposedTransaction, Transaction, TransactionVersion},
+    };
+
+    fn make_transaction(
+        spender_key: &SaplingKey,
+        receiver_key: &SaplingKey,
+    ) -> (Transaction, jubjub::Fr) {
+        let in_note = Note::new(
+            spender_key.public_address(),
+            42,
+            "",
+            NATIVE_ASSET,
+            spender_key.public_address(),
+        );
+        let out_note = Note::new(
+            receiver_key.public_address(),
+            40,
+            "invoice 17",
+            NATIVE_ASSET,
+            spender_key.public_address(),
+        );
+        let witness = make_fake_witness(&in_note);
+
+        let mut transaction = ProposedTransaction::new(TransactionVersion::latest());
+        transaction.add_spend(in_note, &witness).unwrap();
+        transaction.add_output(out_note).unwrap();
+
+        let unsigned = transaction
+            .build(
+                spender_key.proof_authorizing_key,
+                spender_key.view_key().clone(),
+                spender_key.outgoing_view_key().clone(),
+                1,
+                None,
+            )
+            .unwrap();
+        let public_key_randomness = unsigned.public_key_randomness();
+
+        (unsigned.sign(spender_key).unwrap(), public_key_randomness)
+    }
+
+    #[test]
+    fn test_payment_disclosure() {
+        let spender_key = SaplingKey::generate_key();
+        let receiver_key = SaplingKey::generate_key();
+        let (transaction, public_key_randomness) = make_transaction(&spender_key, &receiver_key);
+
+        let disclosure =
+            PaymentDisclosure::new(&transaction, 0, &spender_key, public_key_randomness).unwrap();
+
+        let mut serialized = vec![];
+        disclosure.write(&mut serialized).unwrap();
+        assert_eq!(serialized.len(), PAYMENT_DISCLOSURE_SIZE);
+        let disclosure = PaymentDisclosure::read(&serialized[..]).unwrap();
+        assert_eq!(disclosure.output_index(), 0);
+
+        let note = disclosure.verify(&transaction).unwrap();
+        assert_eq!(note.owner(), receiver_key.public_address());
+        assert_eq!(note.value(), 40);
+        assert_eq!(
+            note.memo().decode(),
+            MemoContent::Text("invoice 17".to_string())
+        );
+        assert_eq!(note.sender(), spender_key.public_address());
+    }
+
+    #[test]
+    fn test_payment_disclosure_diversified() {
+        let spender_key = SaplingKey::generate_key();
+        let receiver_key = SaplingKey::generate_key();
+        let (_, address) = receiver_key.view_key().next_diversified_address(1).unwrap();
+
+        let in_note = Note::new(
+            spender_key.public_address(),
+            42,
+            "",
+            NATIVE_ASSET,
+            spender_key.public_address(),
+        );
+        let out_note = Note::new(
+            address.owner(),
+            40,
+            "",
+            NATIVE_ASSET,
+            spender_key.public_address(),
+        );
+        let witness = make_fake_witness(&in_note);
+
+        let mut transaction = ProposedTransaction::new(TransactionVersion::V3);
+        transaction.add_spend(in_note, &witness).unwrap();
+        transaction
+            .add_diversified_output(out_note, address)
+            .unwrap();
+        let unsigned = transaction
+            .build(
+                spender_key.proof_authorizing_key,
+                spender_key.view_key().clone(),
+                spender_key.outgoing_view_key().clone(),
+                1,
+                None,
+            )
+            .unwrap();
+        let public_key_randomness = unsigned.public_key_randomness();
+        let transaction = unsigned.sign(&spender_key).unwrap();
+
+        let disclosure =
+            PaymentDisclosure::new(&transaction, 0, &spender_key, public_key_randomness).unwrap();
+        let note = disclosure.verify(&transaction).unwrap();
+        assert_eq!(note.owner(), address.owner());
+        assert_eq!(note.value(), 40);
+    }
+
+    #[test]
+    fn test_payment_disclosure_multisig() {
+        let spender_key = SaplingKey::generate_key();
+        let receiver_key = SaplingKey::generate_key();
+        let (transaction, public_key_randomness) = make_transaction(&spender_key, &receiver_key);
+
+        let identities = create_multisig_identities(5);
+        let key_packages = split_spender_key(&spender_key, 3, identities.clone()).unwrap();
+
+        let unsigned = UnsignedPaymentDisclosure::new(
+            &transaction,
+            0,
+            &key_packages.outgoing_view_key,
+            public_key_randomness,
+        )
+        .unwrap();
+        let disclosure_hash = unsigned.disclosure_hash();
+
+        // Round 1
+        let mut commitments = HashMap::new();
+        for (identity, key_package) in key_packages.key_packages.iter() {
+            let nonces = deterministic_signing_nonces(
+                key_package.signing_share(),
+                &disclosure_hash,
+                &identities,
+            );
+            commitments.insert(identity.clone(), (&nonces).into());
+        }
+        let signing_package = unsigned.signing_package(commitments);
+
+        // The coordinator sends the signing package to the participants
+        let mut serialized = Vec::new();
+        signing_package.write(&mut serialized).unwrap();
+        let signing_package = DisclosureSigningPackage::read(&serialized[..]).unwrap();
+        assert_eq!(
+            signing_package.unsigned_disclosure.disclosure_hash(),
+            disclosure_hash
+        );
+
+        // Round 2
+        let randomizer = Randomizer::deserialize(
+            &signing_package
+                .unsigned_disclosure
+                .public_key_randomness()
+                .to_bytes(),
+        )
+        .expect("should be able to deserialize randomizer");
+        let mut signature_shares = BTreeMap::new();
+        for (identity, key_package) in key_packages.key_packages.iter() {
+            let nonces = deterministic_signing_nonces(
+                key_package.signing_share(),
+                &disclosure_hash,
+                &identities,
+            );
+            let signature_share = round2::sign(
+                &signing_package.frost_signing_package,
+                &nonces,
+                key_package,
+                randomizer,
+            )
+            .unwrap();
+            signature_shares.insert(identity.to_frost_identifier(), signature_share);
+        }
+
+        let disclosure = unsigned
+            .aggregate_signature_shares(
+                &key_packages.public_key_package,
+                &signing_package.frost_signing_package,
+                signature_shares,
+            )
+            .unwrap();
+
+        let note = disclosure.verify(&transaction).unwrap();
+        assert_eq!(note.owner(), receiver_key.public_address());
+        assert_eq!(note.value(), 40);
+    }
+
+    #[test]
+    fn test_payment_disclosure_wrong_keys() {
+        let spender_key = SaplingKey::generate_key();
+        let receiver_key = SaplingKey::generate_key();
+        let (transaction, public_key_randomness) = make_transaction(&spender_key, &receiver_key);
+
+        // The randomness must match the transaction
+        assert!(matches!(
+            PaymentDisclosure::new(&transaction, 0, &spender_key, jubjub::Fr::one()),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidSigningKey)
+        ));
+
+        // Only the sender can decrypt the note encryption keys
+        assert!(
+            PaymentDisclosure::new(&transaction, 0, &receiver_key, public_key_randomness).is_err()
+        );
+
+        assert!(matches!(
+            PaymentDisclosure::new(&transaction, 5, &spender_key, public_key_randomness),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidData)
+        ));
+    }
+
+    #[test]
+    fn test_payment_disclosure_tampered() {
+        let spender_key = SaplingKey::generate_key();
+        let receiver_key = SaplingKey::generate_key();
+        let (transaction, public_key_randomness) = make_transaction(&spender_key, &receiver_key);
+        let (other_transaction, _) = make_transaction(&spender_key, &receiver_key);
+
+        let disclosure =
+            PaymentDisclosure::new(&transaction, 0, &spender_key, public_key_randomness).unwrap();
+
+        // Bound to the transaction
+        assert!(matches!(
+            disclosure.verify(&other_transaction),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidSignature)
+        ));
+
+        // Bound to the output
+        let mut tampered = disclosure.clone();
+        tampered.output_index = 1;
+        assert!(matches!(
+            tampered.verify(&transaction),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidSignature)
+        ));
+
+        // Bound to the disclosed owner
+        let mut tampered = disclosure;
+        tampered.owner = SaplingKey::generate_key().public_address();
+        assert!(matches!(
+            tampered.verify(&transaction),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidSignature)
+        ));
+    }
+}
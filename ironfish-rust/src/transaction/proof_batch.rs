@@ -0,0 +1,162 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use bellperson::groth16::{verify_proofs_batch, PreparedVerifyingKey, Proof};
+use blstrs::{Bls12, Scalar};
+use jubjub::ExtendedPoint;
+use rand::rngs::OsRng;
+
+use crate::errors::{IronfishError, IronfishErrorKind};
+
+use super::Transaction;
+
+/// Groth16 proofs and their public inputs, accumulated from one or more
+/// transactions so that each circuit only needs a single batch verification.
+#[derive(Clone, Default)]
+pub(crate) struct ProofBatch {
+    spend_proofs: Vec<Proof<Bls12>>,
+    spend_public_inputs: Vec<Vec<Scalar>>,
+
+    output_proofs: Vec<Proof<Bls12>>,
+    output_public_inputs: Vec<Vec<Scalar>>,
+
+    mint_proofs: Vec<Proof<Bls12>>,
+    mint_public_inputs: Vec<Vec<Scalar>>,
+}
+
+impl ProofBatch {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run all the checks on a transaction that do not involve the zero
+    /// knowledge proofs (small order checks, spend and mint signatures and the
+    /// binding signature), then queue its proofs to be verified later by
+    /// [`ProofBatch::verify`].
+    ///
+    /// If any check fails, nothing from the transaction is added to the batch.
+    pub(crate) fn add_transaction(
+        &mut self,
+        transaction: &Transaction,
+    ) -> Result<(), IronfishError> {
+        // Context to accumulate a signature of all the spends and outputs and
+        // guarantee they are part of this transaction, unmodified.
+        let mut binding_verification_key = ExtendedPoint::identity();
+
+        let hash_to_verify_signature = transaction.transaction_signature_hash()?;
+
+        for spend in transaction.spends.iter() {
+            spend.partial_verify()?;
+
+            binding_verification_key += spend.value_commitment;
+
+            spend.verify_signature(
+                &hash_to_verify_signature,
+                transaction.randomized_public_key(),
+            )?;
+        }
+
+        for output in transaction.outputs.iter() {
+            output.partial_verify()?;
+
+            binding_verification_key -= output.merkle_note.value_commitment;
+        }
+
+        for mint in transaction.mints.iter() {
+            mint.partial_verify()?;
+
+            mint.verify_signature(
+                &hash_to_verify_signature,
+                transaction.randomized_public_key(),
+            )?;
+        }
+
+        transaction.verify_binding_signature(&binding_verification_key)?;
+
+        // Every check passed, so the proofs can be queued
+        let randomized_public_key = transaction.randomized_public_key();
+
+        for spend in transaction.spends.iter() {
+            self.spend_proofs.push(spend.proof.clone());
+            self.spend_public_inputs
+                .push(spend.public_inputs(randomized_public_key).to_vec());
+        }
+
+        for output in transaction.outputs.iter() {
+            self.output_proofs.push(output.proof.clone());
+            self.output_public_inputs
+                .push(output.public_inputs(randomized_public_key).to_vec());
+        }
+
+        for mint in transaction.mints.iter() {
+            self.mint_proofs.push(mint.proof.clone());
+            self.mint_public_inputs
+                .push(mint.public_inputs(randomized_public_key).to_vec());
+        }
+
+        Ok(())
+    }
+
+    /// Add all the proofs queued in `other` to this batch.
+    pub(crate) fn append(&mut self, other: &ProofBatch) {
+        self.spend_proofs.extend_from_slice(&other.spend_proofs);
+        self.spend_public_inputs
+            .extend_from_slice(&other.spend_public_inputs);
+
+        self.output_proofs.extend_from_slice(&other.output_proofs);
+        self.output_public_inputs
+            .extend_from_slice(&other.output_public_inputs);
+
+        self.mint_proofs.extend_from_slice(&other.mint_proofs);
+        self.mint_public_inputs
+            .extend_from_slice(&other.mint_public_inputs);
+    }
+
+    /// Verify every queued proof, using one batch verification per circuit.
+    pub(crate) fn verify(
+        &self,
+        spend_verifying_key: &PreparedVerifyingKey<Bls12>,
+        output_verifying_key: &PreparedVerifyingKey<Bls12>,
+        mint_verifying_key: &PreparedVerifyingKey<Bls12>,
+    ) -> Result<(), IronfishError> {
+        verify_batch(
+            spend_verifying_key,
+            &self.spend_proofs,
+            &self.spend_public_inputs,
+            IronfishErrorKind::InvalidSpendProof,
+        )?;
+        verify_batch(
+            output_verifying_key,
+            &self.output_proofs,
+            &self.output_public_inputs,
+            IronfishErrorKind::InvalidOutputProof,
+        )?;
+        verify_batch(
+            mint_verifying_key,
+            &self.mint_proofs,
+            &self.mint_public_inputs,
+            IronfishErrorKind::InvalidMintProof,
+        )?;
+
+        Ok(())
+    }
+}
+
+fn verify_batch(
+    verifying_key: &PreparedVerifyingKey<Bls12>,
+    proofs: &[Proof<Bls12>],
+    public_inputs: &[Vec<Scalar>],
+    error_kind: IronfishErrorKind,
+) -> Result<(), IronfishError> {
+    if proofs.is_empty() {
+        return Ok(());
+    }
+
+    let proofs: Vec<&Proof<Bls12>> = proofs.iter().collect();
+    if !verify_proofs_batch(verifying_key, &mut OsRng, &proofs[..], public_inputs)? {
+        return Err(IronfishError::new(error_kind));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,572 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Proofs of reserves, which show that the holder of a spending key owns a
+//! set of notes and how much of each asset they hold, for example to an
+//! exchange or an auditor.
+//!
+//! Every note is proven with the regular spend circuit, but against a marker
+//! Merkle tree instead of the note commitment tree: the auth path of the
+//! marker tree and the position of the note in it are derived from a hash of
+//! the message, the index of the note in the proof and its commitment. The
+//! anchor of the marker tree is never a root of the note commitment tree, so
+//! the spend proofs can never be used in a transaction, and the nullifiers
+//! they reveal are computed for a position that the note does not have on
+//! chain, so they are not the nullifiers that will be revealed when the notes
+//! are spent.
+//!
+//! The values, asset ids and value commitment randomness of the notes are
+//! revealed so that the verifier can total the value per asset, along with
+//! the commitment and the witness of every note in the note commitment tree.
+//! The whole proof is signed with the randomized spend authorizing key, which
+//! binds it to the message.
+//!
+//! Whether the notes are still unspent cannot be proven without revealing
+//! their nullifiers. Verifiers must check that the anchors returned by
+//! [`ReserveProof::verify`] are roots of the note commitment tree.
+
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+};
+
+use blake2b_simd::Params as Blake2b;
+use blstrs::Scalar;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use ff::{Field, PrimeField};
+use group::GroupEncoding;
+use ironfish_zkp::{
+    constants::{SPENDING_KEY_GENERATOR, TREE_DEPTH},
+    primitives::ValueCommitment,
+    redjubjub::{self, Signature},
+    ProofGenerationKey,
+};
+use jubjub::ExtendedPoint;
+use rand::thread_rng;
+
+use super::{
+    note_selection::SpendableNote,
+    spends::{SpendBuilder, SpendDescription},
+    utils::verify_spend_proof,
+    TRANSACTION_PUBLIC_KEY_SIZE,
+};
+use crate::{
+    assets::asset_identifier::AssetIdentifier,
+    errors::{IronfishError, IronfishErrorKind},
+    keys::SaplingKey,
+    serializing::read_scalar,
+    witness::{Witness, WitnessNode, WitnessTrait},
+    MerkleNoteHash,
+};
+
+const MESSAGE_PERSONALIZATION: &[u8; 16] = b"Iron Fish ResMsg";
+const MARKER_PERSONALIZATION: &[u8; 16] = b"Iron Fish Marker";
+const SIGNATURE_PERSONALIZATION: &[u8; 16] = b"Iron Fish ResSig";
+
+/// Proof that one note is owned by the key that signed the [`ReserveProof`]
+struct NoteReserveProof {
+    /// Spend proof against the marker tree of the note
+    spend: SpendDescription,
+
+    asset_id: AssetIdentifier,
+
+    value: u64,
+
+    /// Randomness of the value commitment of `spend`, revealed so that the
+    /// verifier can check `value` and `asset_id`
+    value_commitment_randomness: jubjub::Fr,
+
+    note_commitment: Scalar,
+
+    /// Witness of the note in the note commitment tree
+    witness: Witness,
+}
+
+/// Total value per asset owned by the signer of a [`ReserveProof`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReserveSummary {
+    pub totals: HashMap<AssetIdentifier, u64>,
+
+    /// Root hash and size of the note commitment tree in which each note was
+    /// witnessed. Verifiers must check these against the chain.
+    pub anchors: Vec<(Scalar, u32)>,
+}
+
+/// Message-bound proof of ownership of a set of notes. See the module
+/// documentation.
+pub struct ReserveProof {
+    randomized_public_key: redjubjub::PublicKey,
+    notes: Vec<NoteReserveProof>,
+    signature: Signature,
+}
+
+impl ReserveProof {
+    /// Prove that `spender_key` owns `notes`, binding the proof to `message`.
+    ///
+    /// Fails if a note is not owned by the diversified address of the key
+    /// given by its `diversifier_index`, or if a witness does not match its
+    /// note.
+    pub fn new(
+        spender_key: &SaplingKey,
+        notes: &[SpendableNote],
+        message: &[u8],
+    ) -> Result<Self, IronfishError> {
+        let message_hash = hash_message(message);
+
+        let public_key_randomness = jubjub::Fr::random(thread_rng());
+        let private_key = redjubjub::PrivateKey(spender_key.spend_authorizing_key)
+            .randomize(public_key_randomness);
+        let randomized_public_key =
+            redjubjub::PublicKey::from_private(&private_key, *SPENDING_KEY_GENERATOR);
+
+        let proof_generation_key = ProofGenerationKey {
+            ak: spender_key.view_key().authorizing_key,
+            nsk: spender_key.proof_authorizing_key,
+        };
+
+        let mut note_proofs = Vec::with_capacity(notes.len());
+        for (index, spendable) in notes.iter().enumerate() {
+            let note = &spendable.note;
+            if note.owner
+                != spender_key
+                    .view_key()
+                    .diversified_owner(spendable.diversifier_index)?
+            {
+                return Err(IronfishError::new(IronfishErrorKind::InvalidPublicAddress));
+            }
+
+            let note_commitment = note.commitment_point();
+            if !spendable
+                .witness
+                .verify(&MerkleNoteHash::new(note_commitment))
+            {
+                return Err(IronfishError::new(IronfishErrorKind::InconsistentWitness));
+            }
+
+            let marker_witness = marker_witness(&message_hash, index, note_commitment);
+            let mut builder = SpendBuilder::new(note.clone(), &marker_witness);
+            builder.diversifier_index = spendable.diversifier_index;
+            let spend = builder
+                .build(
+                    &proof_generation_key,
+                    spender_key.view_key(),
+                    &public_key_randomness,
+                    &randomized_public_key,
+                )?
+                .description;
+
+            note_proofs.push(NoteReserveProof {
+                spend,
+                asset_id: note.asset_id,
+                value: note.value,
+                value_commitment_randomness: builder.value_commitment.randomness,
+                note_commitment,
+                witness: Witness {
+                    tree_size: spendable.witness.tree_size() as usize,
+                    root_hash: spendable.witness.root_hash(),
+                    auth_path: spendable.witness.get_auth_path(),
+                },
+            });
+        }
+
+        let data_to_be_signed =
+            signature_data(&randomized_public_key, &message_hash, &note_proofs)?;
+        let signature = private_key.sign(
+            &data_to_be_signed,
+            &mut thread_rng(),
+            *SPENDING_KEY_GENERATOR,
+        );
+
+        Ok(ReserveProof {
+            randomized_public_key,
+            notes: note_proofs,
+            signature,
+        })
+    }
+
+    /// Number of notes in the proof
+    pub fn note_count(&self) -> usize {
+        self.notes.len()
+    }
+
+    /// Check the proof against `message` and return the value owned per
+    /// asset.
+    pub fn verify(&self, message: &[u8]) -> Result<ReserveSummary, IronfishError> {
+        if self.randomized_public_key.0.is_small_order().into() {
+            return Err(IronfishError::new(IronfishErrorKind::IsSmallOrder));
+        }
+
+        let message_hash = hash_message(message);
+        let data_to_be_signed =
+            signature_data(&self.randomized_public_key, &message_hash, &self.notes)?;
+        if !self.randomized_public_key.verify(
+            &data_to_be_signed,
+            &self.signature,
+            *SPENDING_KEY_GENERATOR,
+        ) {
+            return Err(IronfishError::new(IronfishErrorKind::InvalidSignature));
+        }
+
+        let mut summary = ReserveSummary {
+            totals: HashMap::new(),
+            anchors: Vec::with_capacity(self.notes.len()),
+        };
+        let mut note_commitments = HashSet::new();
+
+        for (index, note_proof) in self.notes.iter().enumerate() {
+            let note_commitment = note_proof.note_commitment;
+            if !note_commitments.insert(note_commitment.to_bytes_le()) {
+                return Err(IronfishError::new(IronfishErrorKind::InvalidData));
+            }
+
+            if !note_proof
+                .witness
+                .verify(&MerkleNoteHash::new(note_commitment))
+            {
+                return Err(IronfishError::new(IronfishErrorKind::InconsistentWitness));
+            }
+
+            // The note must be proven against its marker tree
+            let marker_witness = marker_witness(&message_hash, index, note_commitment);
+            if note_proof.spend.root_hash != marker_witness.root_hash {
+                return Err(IronfishError::new(IronfishErrorKind::InvalidSpendProof));
+            }
+
+            // The revealed value and asset must match the value commitment
+            let value_commitment = ValueCommitment {
+                value: note_proof.value,
+                randomness: note_proof.value_commitment_randomness,
+                asset_generator: note_proof.asset_id.asset_generator(),
+            };
+            if ExtendedPoint::from(value_commitment.commitment())
+                != note_proof.spend.value_commitment
+            {
+                return Err(IronfishError::new(IronfishErrorKind::InvalidCommitment));
+            }
+
+            note_proof.spend.partial_verify()?;
+            verify_spend_proof(
+                &note_proof.spend.proof,
+                &note_proof.spend.public_inputs(&self.randomized_public_key),
+            )?;
+
+            let total = summary.totals.entry(note_proof.asset_id).or_insert(0);
+            *total = total
+                .checked_add(note_proof.value)
+                .ok_or_else(|| IronfishError::new(IronfishErrorKind::IllegalValue))?;
+            summary.anchors.push((
+                note_proof.witness.root_hash,
+                note_proof.witness.tree_size as u32,
+            ));
+        }
+
+        Ok(summary)
+    }
+
+    pub fn read<R: io::Read>(mut reader: R) -> Result<Self, IronfishError> {
+        let randomized_public_key = redjubjub::PublicKey::read(&mut reader)?;
+        let signature = Signature::read(&mut reader)?;
+
+        let note_count = reader.read_u32::<LittleEndian>()?;
+        let mut notes = vec![];
+        for _ in 0..note_count {
+            notes.push(NoteReserveProof::read(&mut reader)?);
+        }
+
+        Ok(ReserveProof {
+            randomized_public_key,
+            notes,
+            signature,
+        })
+    }
+
+    pub fn write<W: io::Write>(&self, mut writer: W) -> Result<(), IronfishError> {
+        writer.write_all(&self.randomized_public_key.0.to_bytes())?;
+        self.signature.write(&mut writer)?;
+
+        writer.write_u32::<LittleEndian>(self.notes.len().try_into()?)?;
+        for note_proof in self.notes.iter() {
+            note_proof.write(&mut writer)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl NoteReserveProof {
+    fn read<R: io::Read>(mut reader: R) -> Result<Self, IronfishError> {
+        let spend = SpendDescription::read(&mut reader)?;
+        let asset_id = AssetIdentifier::read(&mut reader)?;
+        let value = reader.read_u64::<LittleEndian>()?;
+        let value_commitment_randomness = read_scalar(&mut reader)?;
+        let note_commitment = read_scalar(&mut reader)?;
+
+        let root_hash = read_scalar(&mut reader)?;
+        let tree_size = reader.read_u32::<LittleEndian>()? as usize;
+        let mut auth_path = Vec::with_capacity(TREE_DEPTH);
+        for _ in 0..TREE_DEPTH {
+            let sibling_hash = read_scalar(&mut reader)?;
+            auth_path.push(match reader.read_u8()? {
+                0 => WitnessNode::Left(sibling_hash),
+                1 => WitnessNode::Right(sibling_hash),
+                _ => return Err(IronfishError::new(IronfishErrorKind::InvalidData)),
+            });
+        }
+
+        Ok(NoteReserveProof {
+            spend,
+            asset_id,
+            value,
+            value_commitment_randomness,
+            note_commitment,
+            witness: Witness {
+                tree_size,
+                root_hash,
+                auth_path,
+            },
+        })
+    }
+
+    fn write<W: io::Write>(&self, mut writer: W) -> Result<(), IronfishError> {
+        self.spend.write(&mut writer)?;
+        self.asset_id.write(&mut writer)?;
+        writer.write_u64::<LittleEndian>(self.value)?;
+        writer.write_all(&self.value_commitment_randomness.to_bytes())?;
+        writer.write_all(self.note_commitment.to_repr().as_ref())?;
+
+        writer.write_all(self.witness.root_hash.to_repr().as_ref())?;
+        writer.write_u32::<LittleEndian>(self.witness.tree_size.try_into()?)?;
+        for node in self.witness.auth_path.iter() {
+            let (sibling_hash, is_right) = match node {
+                WitnessNode::Left(hash) => (hash, 0),
+                WitnessNode::Right(hash) => (hash, 1),
+            };
+            writer.write_all(sibling_hash.to_repr().as_ref())?;
+            writer.write_u8(is_right)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn hash_message(message: &[u8]) -> [u8; 32] {
+    let hash = Blake2b::new()
+        .hash_length(32)
+        .personal(MESSAGE_PERSONALIZATION)
+        .hash(message);
+
+    let mut message_hash = [0; 32];
+    message_hash.copy_from_slice(hash.as_bytes());
+    message_hash
+}
+
+/// Witness of the note with the given commitment in its marker tree. The
+/// siblings and the position of the note are derived from the message, the
+/// index of the note in the proof and the note commitment.
+pub(crate) fn marker_witness(
+    message_hash: &[u8; 32],
+    index: usize,
+    note_commitment: Scalar,
+) -> Witness {
+    let mut auth_path = Vec::with_capacity(TREE_DEPTH);
+    let mut current_hash = note_commitment;
+
+    for depth in 0..TREE_DEPTH {
+        let hash = Blake2b::new()
+            .hash_length(32)
+            .personal(MARKER_PERSONALIZATION)
+            .to_state()
+            .update(message_hash)
+            .update(&(index as u64).to_le_bytes())
+            .update(&note_commitment.to_bytes_le())
+            .update(&(depth as u32).to_le_bytes())
+            .finalize();
+
+        // Clear the two most significant bits so that the sibling is lower
+        // than the modulus, and use one of them as the direction
+        let mut sibling_bytes = [0; 32];
+        sibling_bytes.copy_from_slice(hash.as_bytes());
+        let is_right = sibling_bytes[31] & 0x80 != 0;
+        sibling_bytes[31] &= 0x3f;
+        let sibling_hash = Scalar::from_repr(sibling_bytes).unwrap();
+
+        if is_right {
+            current_hash = MerkleNoteHash::combine_hash(depth, &sibling_hash, &current_hash);
+            auth_path.push(WitnessNode::Right(sibling_hash));
+        } else {
+            current_hash = MerkleNoteHash::combine_hash(depth, &current_hash, &sibling_hash);
+            auth_path.push(WitnessNode::Left(sibling_hash));
+        }
+    }
+
+    Witness {
+        tree_size: 0,
+        root_hash: current_hash,
+        auth_path,
+    }
+}
+
+/// Data signed with the randomized spend authorizing key: the randomized
+/// public key, followed by a hash of the message and of the note proofs.
+fn signature_data(
+    randomized_public_key: &redjubjub::PublicKey,
+    message_hash: &[u8; 32],
+    notes: &[NoteReserveProof],
+) -> Result<[u8; 64], IronfishError> {
+    let mut hasher = Blake2b::new()
+        .hash_length(32)
+        .personal(SIGNATURE_PERSONALIZATION)
+        .to_state();
+    hasher.update(message_hash);
+    for note_proof in notes {
+        note_proof.write(&mut hasher)?;
+    }
+
+    let mut data_to_be_signed = [0; 64];
+    data_to_be_signed[..TRANSACTION_PUBLIC_KEY_SIZE]
+        .copy_from_slice(&randomized_public_key.0.to_bytes());
+    data_to_be_signed[TRANSACTION_PUBLIC_KEY_SIZE..].copy_from_slice(hasher.finalize().as_bytes());
+
+    Ok(data_to_be_signed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::ReserveProof;
+    use crate::{
+        assets::{asset::Asset, asset_identifier::NATIVE_ASSET},
+        errors::IronfishErrorKind,
+        keys::SaplingKey,
+        merkle_note::position,
+        note::Note,
+        test_util::make_fake_witness,
+        transaction::note_selection::SpendableNote,
+    };
+
+    #[test]
+    fn test_reserve_proof() {
+        let key = SaplingKey::generate_key();
+        let sender = SaplingKey::generate_key().public_address();
+        let asset = Asset::new(key.public_address(), "Testcoin", "").unwrap();
+
+        let notes = [
+            Note::new(key.public_address(), 40, "", NATIVE_ASSET, sender),
+            Note::new(key.public_address(), 2, "", NATIVE_ASSET, sender),
+            Note::new(key.public_address(), 7, "", *asset.id(), sender),
+        ];
+        let witnesses: Vec<_> = notes.iter().map(make_fake_witness).collect();
+        let spendable: Vec<_> = notes
+            .iter()
+            .zip(witnesses.iter())
+            .map(|(note, witness)| SpendableNote::new(note.clone(), witness))
+            .collect();
+
+        let message = b"reserves of 2026-10-16";
+        let proof = ReserveProof::new(&key, &spendable, message).unwrap();
+        assert_eq!(proof.note_count(), 3);
+
+        let mut serialized = vec![];
+        proof.write(&mut serialized).unwrap();
+        let proof = ReserveProof::read(&serialized[..]).unwrap();
+
+        let summary = proof.verify(message).unwrap();
+        assert_eq!(summary.totals.len(), 2);
+        assert_eq!(summary.totals[&NATIVE_ASSET], 42);
+        assert_eq!(summary.totals[asset.id()], 7);
+        assert_eq!(summary.anchors.len(), 3);
+        assert_eq!(summary.anchors[0], (witnesses[0].root_hash, 1400));
+
+        // Bound to the message
+        assert!(matches!(
+            proof.verify(b"reserves of 2026-10-17"),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidSignature)
+        ));
+
+        // The spend proofs do not use the note commitment tree, and do not
+        // reveal the nullifiers of the notes
+        for (note_proof, witness) in proof.notes.iter().zip(witnesses.iter()) {
+            assert_ne!(note_proof.spend.root_hash, witness.root_hash);
+        }
+        let nullifier = notes[0].nullifier(key.view_key(), position(&witnesses[0]));
+        assert_ne!(proof.notes[0].spend.nullifier().0, nullifier.0);
+    }
+
+    #[test]
+    fn test_reserve_proof_diversified() {
+        let key = SaplingKey::generate_key();
+        let note = Note::new(
+            key.view_key().diversified_owner(4).unwrap(),
+            40,
+            "",
+            NATIVE_ASSET,
+            key.public_address(),
+        );
+        let witness = make_fake_witness(&note);
+
+        // The note is only owned by the key at its diversifier index
+        assert!(matches!(
+            ReserveProof::new(&key, &[SpendableNote::new(note.clone(), &witness)], b"message"),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidPublicAddress)
+        ));
+
+        let spendable = [SpendableNote::new_diversified(note, &witness, 4)];
+        let proof = ReserveProof::new(&key, &spendable, b"message").unwrap();
+        let summary = proof.verify(b"message").unwrap();
+        assert_eq!(summary.totals[&NATIVE_ASSET], 40);
+    }
+
+    #[test]
+    fn test_reserve_proof_tampered() {
+        let key = SaplingKey::generate_key();
+        let note = Note::new(
+            key.public_address(),
+            40,
+            "",
+            NATIVE_ASSET,
+            key.public_address(),
+        );
+        let witness = make_fake_witness(&note);
+        let spendable = [SpendableNote::new(note, &witness)];
+
+        let message = b"message";
+        let proof = ReserveProof::new(&key, &spendable, message).unwrap();
+
+        let mut serialized = vec![];
+        proof.write(&mut serialized).unwrap();
+
+        // Inflate the value: the signature covers the revealed values
+        let mut tampered = ReserveProof::read(&serialized[..]).unwrap();
+        tampered.notes[0].value = 4000;
+        assert!(matches!(
+            tampered.verify(message),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidSignature)
+        ));
+
+        // Count the same note twice. The duplicate is also proven against the
+        // wrong marker tree, and the signature no longer matches.
+        let mut tampered = ReserveProof::read(&serialized[..]).unwrap();
+        let duplicate = ReserveProof::read(&serialized[..]).unwrap().notes.remove(0);
+        tampered.notes.push(duplicate);
+        assert!(tampered.verify(message).is_err());
+    }
+
+    #[test]
+    fn test_reserve_proof_not_owned() {
+        let key = SaplingKey::generate_key();
+        let other_key = SaplingKey::generate_key();
+        let note = Note::new(
+            other_key.public_address(),
+            40,
+            "",
+            NATIVE_ASSET,
+            key.public_address(),
+        );
+        let witness = make_fake_witness(&note);
+
+        assert!(matches!(
+            ReserveProof::new(&key, &[SpendableNote::new(note, &witness)], b"message"),
+            Err(e) if matches!(e.kind, IronfishErrorKind::InvalidPublicAddress)
+        ));
+    }
+}
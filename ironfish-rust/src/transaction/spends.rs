@@ -4,7 +4,7 @@
 
 use crate::{
     errors::{IronfishError, IronfishErrorKind},
-    keys::SaplingKey,
+    keys::{diversified_proof_generation_key, SaplingKey},
     merkle_note::{position as witness_position, sapling_auth_path},
     note::Note,
     sapling_bls12::SAPLING,
@@ -20,7 +20,7 @@ use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use ff::{Field, PrimeField};
 use group::{Curve, GroupEncoding};
 use ironfish_zkp::{
-    constants::SPENDING_KEY_GENERATOR,
+    constants::{SPENDING_KEY_GENERATOR, TREE_DEPTH},
     primitives::ValueCommitment,
     proofs::Spend,
     redjubjub::{self, Signature},
@@ -30,7 +30,15 @@ use jubjub::ExtendedPoint;
 use rand::thread_rng;
 use std::io;
 
-use super::{utils::verify_spend_proof, TRANSACTION_PUBLIC_KEY_SIZE};
+use super::{
+    outputs::PROOF_SIZE, utils::verify_spend_proof, TRANSACTION_PUBLIC_KEY_SIZE,
+    TRANSACTION_SIGNATURE_SIZE,
+};
+
+/// Size of a serialized [`SpendDescription`]: proof, value commitment, root
+/// hash, tree size, nullifier and authorizing signature.
+pub const SPEND_DESCRIPTION_SIZE: usize =
+    PROOF_SIZE as usize + 32 + 32 + 4 + 32 + TRANSACTION_SIGNATURE_SIZE;
 
 /// Parameters used when constructing proof that the spender owns a note with
 /// a given value.
@@ -54,6 +62,11 @@ pub struct SpendBuilder {
     pub(crate) tree_size: u32,
     pub(crate) witness_position: u64,
     pub(crate) auth_path: Vec<Option<(Scalar, bool)>>,
+
+    /// Index of the diversified address that owns the note. The proof
+    /// generation key and nullifier deriving key are offset accordingly when
+    /// building the spend.
+    pub(crate) diversifier_index: u64,
 }
 
 impl SpendBuilder {
@@ -73,6 +86,7 @@ impl SpendBuilder {
             tree_size: witness.tree_size(),
             witness_position: witness_position(witness),
             auth_path: sapling_auth_path(witness),
+            diversifier_index: 0,
         }
     }
 
@@ -84,6 +98,70 @@ impl SpendBuilder {
         ExtendedPoint::from(self.value_commitment.commitment())
     }
 
+    /// Load a [`SpendBuilder`] previously stored with [`SpendBuilder::write`].
+    pub(crate) fn read<R: io::Read>(mut reader: R) -> Result<Self, IronfishError> {
+        let note = Note::read(&mut reader)?;
+        let value_commitment = ValueCommitment {
+            value: note.value,
+            randomness: read_scalar(&mut reader)?,
+            asset_generator: note.asset_generator(),
+        };
+        let root_hash = read_scalar(&mut reader)?;
+        let tree_size = reader.read_u32::<LittleEndian>()?;
+
+        let auth_path_length = reader.read_u64::<LittleEndian>()?;
+        if auth_path_length != TREE_DEPTH as u64 {
+            return Err(IronfishError::new(IronfishErrorKind::InvalidData));
+        }
+        let mut auth_path = vec![];
+        let mut witness_position = 0;
+        for depth in 0..auth_path_length {
+            let is_right = match reader.read_u8()? {
+                0 => false,
+                1 => true,
+                _ => return Err(IronfishError::new(IronfishErrorKind::InvalidData)),
+            };
+            let sibling_hash = read_scalar(&mut reader)?;
+
+            if is_right {
+                witness_position |= 1 << depth;
+            }
+            auth_path.push(Some((sibling_hash, is_right)));
+        }
+        let diversifier_index = reader.read_u64::<LittleEndian>()?;
+
+        Ok(SpendBuilder {
+            note,
+            value_commitment,
+            root_hash,
+            tree_size,
+            witness_position,
+            auth_path,
+            diversifier_index,
+        })
+    }
+
+    /// Store the note, witness, value commitment randomness and diversifier
+    /// index of this [`SpendBuilder`], so that the proof can be generated later, or by a
+    /// different process.
+    pub(crate) fn write<W: io::Write>(&self, mut writer: W) -> Result<(), IronfishError> {
+        self.note.write(&mut writer)?;
+        writer.write_all(&self.value_commitment.randomness.to_bytes())?;
+        writer.write_all(self.root_hash.to_repr().as_ref())?;
+        writer.write_u32::<LittleEndian>(self.tree_size)?;
+
+        writer.write_u64::<LittleEndian>(self.auth_path.len() as u64)?;
+        for element in self.auth_path.iter() {
+            let (sibling_hash, is_right) =
+                element.ok_or_else(|| IronfishError::new(IronfishErrorKind::InvalidData))?;
+            writer.write_u8(is_right as u8)?;
+            writer.write_all(sibling_hash.to_repr().as_ref())?;
+        }
+        writer.write_u64::<LittleEndian>(self.diversifier_index)?;
+
+        Ok(())
+    }
+
     /// Sign this spend with the private key, and return a [`SpendDescription`]
     /// suitable for serialization.
     ///
@@ -98,9 +176,16 @@ impl SpendBuilder {
     ) -> Result<UnsignedSpendDescription, IronfishError> {
         let value_commitment_point = self.value_commitment_point();
 
+        let proof_generation_key = diversified_proof_generation_key(
+            proof_generation_key,
+            view_key,
+            self.diversifier_index,
+        );
+        let view_key = view_key.diversified(self.diversifier_index);
+
         let circuit = Spend {
             value_commitment: Some(self.value_commitment.clone()),
-            proof_generation_key: Some(proof_generation_key.clone()),
+            proof_generation_key: Some(proof_generation_key),
             payment_address: Some(self.note.owner.0),
             auth_path: self.auth_path.clone(),
             commitment_randomness: Some(self.note.randomness),
@@ -116,7 +201,7 @@ impl SpendBuilder {
 
         // Bytes to be placed into the nullifier set to verify whether this note
         // has been previously spent.
-        let nullifier = self.note.nullifier(view_key, self.witness_position);
+        let nullifier = self.note.nullifier(&view_key, self.witness_position);
 
         let blank_signature = {
             let buf = [0u8; 64];
@@ -410,10 +495,12 @@ mod test {
     use super::{SpendBuilder, SpendDescription};
     use crate::assets::asset_identifier::NATIVE_ASSET;
     use crate::transaction::utils::verify_spend_proof;
-    use crate::{keys::SaplingKey, note::Note, test_util::make_fake_witness};
+    use crate::{
+        errors::IronfishErrorKind, keys::SaplingKey, note::Note, test_util::make_fake_witness,
+    };
     use ff::Field;
     use group::Curve;
-    use ironfish_zkp::constants::SPENDING_KEY_GENERATOR;
+    use ironfish_zkp::constants::{SPENDING_KEY_GENERATOR, TREE_DEPTH};
     use ironfish_zkp::redjubjub::{self, PrivateKey, PublicKey};
     use rand::prelude::*;
     use rand::{thread_rng, Rng};
@@ -506,6 +593,35 @@ mod test {
         .is_err());
     }
 
+    #[test]
+    fn test_spend_builder_read_rejects_bad_auth_path_length() {
+        let key = SaplingKey::generate_key();
+        let note = Note::new(
+            key.public_address(),
+            42,
+            "",
+            NATIVE_ASSET,
+            key.public_address(),
+        );
+        let witness = make_fake_witness(&note);
+        let spend = SpendBuilder::new(note, &witness);
+
+        let mut serialized = vec![];
+        spend.write(&mut serialized).unwrap();
+        SpendBuilder::read(&serialized[..]).unwrap();
+
+        // The length precedes the auth path and the diversifier index
+        let length_offset = serialized.len() - 8 - TREE_DEPTH * 33 - 8;
+        for length in [TREE_DEPTH as u64 - 1, u64::MAX] {
+            let mut tampered = serialized.clone();
+            tampered[length_offset..length_offset + 8].copy_from_slice(&length.to_le_bytes());
+            assert!(matches!(
+                SpendBuilder::read(&tampered[..]),
+                Err(e) if matches!(e.kind, IronfishErrorKind::InvalidData)
+            ));
+        }
+    }
+
     #[test]
     fn test_spend_round_trip() {
         let key = SaplingKey::generate_key();
@@ -0,0 +1,261 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use ff::PrimeField;
+use group::GroupEncoding;
+use ironfish_zkp::redjubjub;
+
+use crate::{serializing::bytes_to_hex, OutputDescription, SpendDescription};
+
+use super::{burns::BurnDescription, mints::MintDescription, TransactionVersion};
+
+/// Human readable view of the public contents of a
+/// [`Transaction`](crate::Transaction) or an
+/// [`UnsignedTransaction`](super::unsigned::UnsignedTransaction).
+///
+/// Byte values (keys, hashes, commitments, identifiers and addresses) are hex
+/// encoded. With the `serde` feature enabled, the summary can be serialized
+/// to JSON.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TransactionSummary {
+    pub version: u8,
+    pub fee: i64,
+    pub expiration: u32,
+    pub randomized_public_key: String,
+    pub spends: Vec<SpendSummary>,
+    pub outputs: Vec<OutputSummary>,
+    pub mints: Vec<MintSummary>,
+    pub burns: Vec<BurnSummary>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpendSummary {
+    pub nullifier: String,
+    pub root_hash: String,
+    pub tree_size: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OutputSummary {
+    pub note_commitment: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MintSummary {
+    pub asset_id: String,
+    /// Asset name, with the trailing zero padding removed
+    pub name: String,
+    /// Asset metadata, with the trailing zero padding removed
+    pub metadata: String,
+    pub creator: String,
+    pub nonce: u8,
+    pub value: u64,
+    pub owner: String,
+    pub transfer_ownership_to: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BurnSummary {
+    pub asset_id: String,
+    pub value: u64,
+}
+
+impl TransactionSummary {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new<'a>(
+        version: TransactionVersion,
+        fee: i64,
+        expiration: u32,
+        randomized_public_key: &redjubjub::PublicKey,
+        spends: impl Iterator<Item = &'a SpendDescription>,
+        outputs: &[OutputDescription],
+        mints: impl Iterator<Item = &'a MintDescription>,
+        burns: &[BurnDescription],
+    ) -> Self {
+        TransactionSummary {
+            version: version.as_u8(),
+            fee,
+            expiration,
+            randomized_public_key: bytes_to_hex(&randomized_public_key.0.to_bytes()),
+            spends: spends.map(SpendSummary::from).collect(),
+            outputs: outputs.iter().map(OutputSummary::from).collect(),
+            mints: mints.map(MintSummary::from).collect(),
+            burns: burns.iter().map(BurnSummary::from).collect(),
+        }
+    }
+}
+
+impl From<&SpendDescription> for SpendSummary {
+    fn from(spend: &SpendDescription) -> Self {
+        SpendSummary {
+            nullifier: bytes_to_hex(&spend.nullifier.0),
+            root_hash: bytes_to_hex(spend.root_hash.to_repr().as_ref()),
+            tree_size: spend.tree_size,
+        }
+    }
+}
+
+impl From<&OutputDescription> for OutputSummary {
+    fn from(output: &OutputDescription) -> Self {
+        OutputSummary {
+            note_commitment: bytes_to_hex(&output.merkle_note.note_commitment.to_bytes_le()),
+        }
+    }
+}
+
+impl From<&MintDescription> for MintSummary {
+    fn from(mint: &MintDescription) -> Self {
+        MintSummary {
+            asset_id: bytes_to_hex(mint.asset.id().as_bytes()),
+            name: padded_bytes_to_string(mint.asset.name()),
+            metadata: padded_bytes_to_string(mint.asset.metadata()),
+            creator: bytes_to_hex(&mint.asset.creator()),
+            nonce: mint.asset.nonce(),
+            value: mint.value,
+            owner: mint.owner.hex_public_address(),
+            transfer_ownership_to: mint
+                .transfer_ownership_to
+                .as_ref()
+                .map(|address| address.hex_public_address()),
+        }
+    }
+}
+
+impl From<&BurnDescription> for BurnSummary {
+    fn from(burn: &BurnDescription) -> Self {
+        BurnSummary {
+            asset_id: bytes_to_hex(burn.asset_id.as_bytes()),
+            value: burn.value,
+        }
+    }
+}
+
+fn padded_bytes_to_string(bytes: &[u8]) -> String {
+    let length = bytes
+        .iter()
+        .rposition(|byte| *byte != 0)
+        .map_or(0, |position| position + 1);
+
+    String::from_utf8_lossy(&bytes[..length]).into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::padded_bytes_to_string;
+    use crate::{
+        assets::{asset::Asset, asset_identifier::NATIVE_ASSET},
+        note::Note,
+        serializing::bytes_to_hex,
+        test_util::make_fake_witness,
+        transaction::{ProposedTransaction, TransactionVersion},
+        SaplingKey,
+    };
+
+    #[test]
+    fn test_padded_bytes_to_string() {
+        assert_eq!(padded_bytes_to_string(b"name\0\0\0"), "name");
+        assert_eq!(padded_bytes_to_string(b"\0\0"), "");
+        assert_eq!(padded_bytes_to_string(b"a\0b\0"), "a\0b");
+    }
+
+    #[test]
+    fn test_transaction_summary() {
+        let key = SaplingKey::generate_key();
+        let other_key = SaplingKey::generate_key();
+
+        let asset = Asset::new(key.public_address(), "Testcoin", "metadata").unwrap();
+
+        let in_note = Note::new(
+            key.public_address(),
+            42,
+            "",
+            NATIVE_ASSET,
+            key.public_address(),
+        );
+        let witness = make_fake_witness(&in_note);
+
+        let mut proposed_transaction = ProposedTransaction::new(TransactionVersion::latest());
+        proposed_transaction.add_spend(in_note, &witness).unwrap();
+        proposed_transaction
+            .add_mint_with_new_owner(asset, 5, other_key.public_address())
+            .unwrap();
+        proposed_transaction.add_burn(*asset.id(), 2).unwrap();
+        proposed_transaction.set_expiration(10);
+
+        let unsigned_transaction = proposed_transaction
+            .build(
+                key.proof_authorizing_key,
+                key.view_key().clone(),
+                key.outgoing_view_key().clone(),
+                1,
+                None,
+            )
+            .unwrap();
+        let transaction = unsigned_transaction.sign(&key).unwrap();
+
+        let summary = transaction.summary();
+        assert_eq!(summary, unsigned_transaction.summary());
+
+        assert_eq!(summary.version, TransactionVersion::latest().as_u8());
+        assert_eq!(summary.fee, 1);
+        assert_eq!(summary.expiration, 10);
+
+        assert_eq!(summary.spends.len(), 1);
+        assert_eq!(
+            summary.spends[0].nullifier,
+            bytes_to_hex(&transaction.spends[0].nullifier().0)
+        );
+        assert_eq!(summary.spends[0].tree_size as usize, witness.tree_size);
+
+        // Change notes for the native asset and the minted asset
+        assert_eq!(summary.outputs.len(), 2);
+
+        assert_eq!(summary.mints.len(), 1);
+        assert_eq!(
+            summary.mints[0].asset_id,
+            bytes_to_hex(asset.id().as_bytes())
+        );
+        assert_eq!(summary.mints[0].name, "Testcoin");
+        assert_eq!(summary.mints[0].metadata, "metadata");
+        assert_eq!(
+            summary.mints[0].creator,
+            key.public_address().hex_public_address()
+        );
+        assert_eq!(summary.mints[0].value, 5);
+        assert_eq!(
+            summary.mints[0].owner,
+            key.public_address().hex_public_address()
+        );
+        assert_eq!(
+            summary.mints[0].transfer_ownership_to,
+            Some(other_key.public_address().hex_public_address())
+        );
+
+        assert_eq!(summary.burns.len(), 1);
+        assert_eq!(summary.burns[0].value, 2);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_transaction_summary_json() {
+        let key = SaplingKey::generate_key();
+        let asset = Asset::new(key.public_address(), "Testcoin", "").unwrap();
+
+        let mut proposed_transaction = ProposedTransaction::new(TransactionVersion::latest());
+        proposed_transaction.add_mint(asset, 5).unwrap();
+        let transaction = proposed_transaction.post(&key, None, 0).unwrap();
+
+        let summary = transaction.summary();
+        let json = serde_json::to_string(&summary).unwrap();
+        assert!(json.contains("\"name\":\"Testcoin\""));
+
+        let decoded = serde_json::from_str(&json).unwrap();
+        assert_eq!(summary, decoded);
+    }
+}
@@ -4,23 +4,28 @@
 
 use std::collections::{BTreeMap, HashMap};
 
+use super::{build_in_parallel, ProposedTransaction, Transaction};
 #[cfg(test)]
-use super::internal_batch_verify_transactions;
-use super::{ProposedTransaction, Transaction};
+use super::{internal_batch_verify_transactions, internal_batch_verify_transactions_with_failures};
 use crate::test_util::create_multisig_identities;
 use crate::transaction::tests::split_spender_key::split_spender_key;
 use crate::{
     assets::{asset::Asset, asset_identifier::NATIVE_ASSET},
-    errors::{IronfishError, IronfishErrorKind},
+    errors::{BalanceErrorDetails, IronfishError, IronfishErrorKind, IronfishErrorPayload},
     frost_utils::split_spender_key,
     keys::SaplingKey,
-    merkle_note::NOTE_ENCRYPTION_MINER_KEYS,
+    merkle_note::{position, NOTE_ENCRYPTION_MINER_KEYS},
     note::Note,
     sapling_bls12::SAPLING,
     test_util::make_fake_witness,
     transaction::{
-        batch_verify_transactions, verify_transaction, TransactionVersion,
-        TRANSACTION_EXPIRATION_SIZE, TRANSACTION_FEE_SIZE, TRANSACTION_SIGNATURE_SIZE,
+        batch_verify_transactions, batch_verify_transactions_with_failures,
+        burns::BurnBuilder,
+        mints::MintBuilder,
+        note_selection::{SelectionStrategy, SpendableNote},
+        outputs::OUTPUT_DESCRIPTION_SIZE,
+        verify_transaction, TransactionVersion, TRANSACTION_EXPIRATION_SIZE, TRANSACTION_FEE_SIZE,
+        TRANSACTION_SIGNATURE_SIZE,
     },
 };
 
@@ -200,9 +205,9 @@ fn test_transaction_simple() {
     assert_eq!(public_transaction.mints.len(), 0);
     assert_eq!(public_transaction.burns.len(), 0);
 
-    let received_note = public_transaction.outputs[1]
+    let (_, received_note) = public_transaction.outputs[1]
         .merkle_note()
-        .decrypt_note_for_owner(&spender_key_clone.incoming_viewing_key)
+        .decrypt_note_for_owner(spender_key_clone.view_key())
         .unwrap();
     assert_eq!(received_note.sender, spender_key_clone.public_address());
 }
@@ -255,13 +260,656 @@ fn test_proposed_transaction_build() {
     assert_eq!(unsigned_transaction.mints.len(), 0);
     assert_eq!(unsigned_transaction.burns.len(), 0);
 
-    let received_note = unsigned_transaction.outputs[1]
+    let (_, received_note) = unsigned_transaction.outputs[1]
         .merkle_note()
-        .decrypt_note_for_owner(&spender_key_clone.incoming_viewing_key)
+        .decrypt_note_for_owner(spender_key_clone.view_key())
         .unwrap();
     assert_eq!(received_note.sender, spender_key_clone.public_address());
 }
 
+#[test]
+fn test_proposed_transaction_build_with_proving_threads() {
+    let spender_key = SaplingKey::generate_key();
+    let receiver_key = SaplingKey::generate_key();
+
+    let asset = Asset::new(spender_key.public_address(), "Testcoin", "")
+        .expect("should be able to create an asset");
+
+    let mut transaction = ProposedTransaction::new(TransactionVersion::latest());
+    transaction.set_proving_threads(3);
+
+    for value in 1..=4 {
+        let in_note = Note::new(
+            spender_key.public_address(),
+            value * 10,
+            "",
+            NATIVE_ASSET,
+            spender_key.public_address(),
+        );
+        let witness = make_fake_witness(&in_note);
+        transaction.add_spend(in_note, &witness).unwrap();
+    }
+
+    for value in 1..=5 {
+        let out_note = Note::new(
+            receiver_key.public_address(),
+            value,
+            "",
+            NATIVE_ASSET,
+            spender_key.public_address(),
+        );
+        transaction.add_output(out_note).unwrap();
+    }
+
+    transaction.add_mint(asset, 3).unwrap();
+    transaction.add_mint(asset, 4).unwrap();
+
+    let public_transaction = transaction
+        .post(&spender_key, None, 1)
+        .expect("should be able to post transaction");
+    verify_transaction(&public_transaction).expect("should be able to verify transaction");
+
+    assert_eq!(public_transaction.spends.len(), 4);
+    assert_eq!(public_transaction.mints.len(), 2);
+    // 5 outputs, plus a change note for each asset
+    assert_eq!(public_transaction.outputs.len(), 7);
+
+    // Descriptions are in the same order they were added in
+    for (index, output) in public_transaction.outputs[..5].iter().enumerate() {
+        let (_, note) = output
+            .merkle_note()
+            .decrypt_note_for_owner(receiver_key.view_key())
+            .expect("should be able to decrypt note");
+        assert_eq!(note.value(), index as u64 + 1);
+    }
+    assert_eq!(public_transaction.mints[0].value, 3);
+    assert_eq!(public_transaction.mints[1].value, 4);
+}
+
+#[test]
+fn test_build_in_parallel_thread_panic() {
+    let items = [1, 2, 3, 4];
+
+    let result = build_in_parallel(&items, 2, |item| {
+        if *item == 3 {
+            panic!("proving failed");
+        }
+        Ok(*item)
+    });
+
+    assert!(matches!(
+        result,
+        Err(e) if matches!(e.kind, IronfishErrorKind::ThreadPanicked)
+    ));
+}
+
+#[test]
+fn test_proposed_transaction_select_spends() {
+    let spender_key = SaplingKey::generate_key();
+    let receiver_key = SaplingKey::generate_key();
+
+    let asset = Asset::new(spender_key.public_address(), "Testcoin", "")
+        .expect("should be able to create an asset");
+
+    let mut notes = vec![];
+    for value in [5, 20, 7] {
+        let note = Note::new(
+            spender_key.public_address(),
+            value,
+            "",
+            NATIVE_ASSET,
+            spender_key.public_address(),
+        );
+        let witness = make_fake_witness(&note);
+        notes.push((note, witness));
+    }
+    let asset_note = Note::new(
+        spender_key.public_address(),
+        10,
+        "",
+        *asset.id(),
+        spender_key.public_address(),
+    );
+    let asset_witness = make_fake_witness(&asset_note);
+    notes.push((asset_note, asset_witness));
+
+    let candidates: Vec<SpendableNote> = notes
+        .iter()
+        .map(|(note, witness)| SpendableNote::new(note.clone(), witness))
+        .collect();
+
+    let mut transaction = ProposedTransaction::new(TransactionVersion::latest());
+    transaction
+        .add_output(Note::new(
+            receiver_key.public_address(),
+            11,
+            "",
+            NATIVE_ASSET,
+            spender_key.public_address(),
+        ))
+        .unwrap();
+    transaction
+        .add_output(Note::new(
+            receiver_key.public_address(),
+            4,
+            "",
+            *asset.id(),
+            spender_key.public_address(),
+        ))
+        .unwrap();
+
+    // 11 + 1 can be paid exactly with the 5 and the 7 notes
+    let selected = transaction
+        .select_spends(&candidates, 1, SelectionStrategy::BranchAndBound)
+        .expect("should be able to select notes");
+    assert_eq!(selected, vec![0, 2, 3]);
+
+    let public_transaction = transaction
+        .post(&spender_key, None, 1)
+        .expect("should be able to post transaction");
+    verify_transaction(&public_transaction).expect("should be able to verify transaction");
+
+    assert_eq!(public_transaction.spends.len(), 3);
+    // 2 outputs, plus a change note for the custom asset only
+    assert_eq!(public_transaction.outputs.len(), 3);
+
+    let mut transaction = ProposedTransaction::new(TransactionVersion::latest());
+    transaction
+        .add_output(Note::new(
+            receiver_key.public_address(),
+            32,
+            "",
+            NATIVE_ASSET,
+            spender_key.public_address(),
+        ))
+        .unwrap();
+    assert!(matches!(
+        transaction.select_spends(&candidates, 1, SelectionStrategy::LargestFirst),
+        Err(e) if matches!(e.kind, IronfishErrorKind::InvalidBalance)
+    ));
+    assert!(transaction.spends.is_empty());
+}
+
+#[test]
+fn test_proposed_transaction_select_spends_twice() {
+    let spender_key = SaplingKey::generate_key();
+    let receiver_key = SaplingKey::generate_key();
+
+    let mut notes = vec![];
+    for value in [5, 20, 7] {
+        let note = Note::new(
+            spender_key.public_address(),
+            value,
+            "",
+            NATIVE_ASSET,
+            spender_key.public_address(),
+        );
+        let witness = make_fake_witness(&note);
+        notes.push((note, witness));
+    }
+
+    let candidates: Vec<SpendableNote> = notes
+        .iter()
+        .map(|(note, witness)| SpendableNote::new(note.clone(), witness))
+        .collect();
+
+    let mut transaction = ProposedTransaction::new(TransactionVersion::latest());
+    transaction
+        .add_output(Note::new(
+            receiver_key.public_address(),
+            4,
+            "",
+            NATIVE_ASSET,
+            spender_key.public_address(),
+        ))
+        .unwrap();
+
+    let selected = transaction
+        .select_spends(&candidates, 1, SelectionStrategy::SmallestFirst)
+        .expect("should be able to select notes");
+    assert_eq!(selected, vec![0]);
+
+    transaction
+        .add_output(Note::new(
+            receiver_key.public_address(),
+            6,
+            "",
+            NATIVE_ASSET,
+            spender_key.public_address(),
+        ))
+        .unwrap();
+
+    // The note of value 5 is already spent, so only 7 is needed to cover the
+    // missing 6
+    let selected = transaction
+        .select_spends(&candidates, 1, SelectionStrategy::SmallestFirst)
+        .expect("should be able to select notes");
+    assert_eq!(selected, vec![2]);
+    assert_eq!(transaction.spends.len(), 2);
+}
+
+#[test]
+fn test_proposed_transaction_select_spends_minimum_balance() {
+    let spender_key = SaplingKey::generate_key();
+
+    let asset = Asset::new(spender_key.public_address(), "Testcoin", "")
+        .expect("should be able to create an asset");
+
+    // Bring the balance of the asset down to i64::MIN
+    let mut transaction = ProposedTransaction::new(TransactionVersion::latest());
+    transaction.add_burn(*asset.id(), i64::MAX as u64).unwrap();
+    transaction.add_burn(*asset.id(), 1).unwrap();
+
+    assert!(matches!(
+        transaction.select_spends(&[], 1, SelectionStrategy::LargestFirst),
+        Err(e) if matches!(e.kind, IronfishErrorKind::InvalidBalance)
+    ));
+}
+
+#[test]
+fn test_proposed_transaction_estimated_size() {
+    let spender_key = SaplingKey::generate_key();
+    let receiver_key = SaplingKey::generate_key();
+
+    let asset = Asset::new(spender_key.public_address(), "Testcoin", "")
+        .expect("should be able to create an asset");
+    let other_asset = Asset::new(spender_key.public_address(), "Othercoin", "")
+        .expect("should be able to create an asset");
+
+    for version in [TransactionVersion::V1, TransactionVersion::V2] {
+        let in_note = Note::new(
+            spender_key.public_address(),
+            42,
+            "",
+            NATIVE_ASSET,
+            spender_key.public_address(),
+        );
+        let out_note = Note::new(
+            receiver_key.public_address(),
+            40,
+            "",
+            NATIVE_ASSET,
+            spender_key.public_address(),
+        );
+        let witness = make_fake_witness(&in_note);
+
+        let mut transaction = ProposedTransaction::new(version);
+        transaction.add_spend(in_note, &witness).unwrap();
+        transaction.add_output(out_note).unwrap();
+        // Minted value that is not burned gets a change note
+        transaction.add_mint(asset, 10).unwrap();
+        transaction.add_burn(*asset.id(), 4).unwrap();
+        if version.has_mint_transfer_ownership_to() {
+            transaction
+                .add_mint_with_new_owner(other_asset, 0, receiver_key.public_address())
+                .unwrap();
+        }
+
+        // With a fee of 2 there is no native change note
+        let estimated_size = transaction.estimated_size(2);
+        assert!(estimated_size < transaction.estimated_size(1));
+        assert_eq!(transaction.estimate_fee(0), 0);
+
+        let public_transaction = transaction
+            .post(&spender_key, None, 2)
+            .expect("should be able to post transaction");
+
+        let mut serialized = vec![];
+        public_transaction.write(&mut serialized).unwrap();
+        assert_eq!(serialized.len(), estimated_size);
+    }
+}
+
+#[test]
+fn test_proposed_transaction_estimate_fee() {
+    let spender_key = SaplingKey::generate_key();
+    let receiver_key = SaplingKey::generate_key();
+
+    let make_transaction = |spend_value| {
+        let in_note = Note::new(
+            spender_key.public_address(),
+            spend_value,
+            "",
+            NATIVE_ASSET,
+            spender_key.public_address(),
+        );
+        let out_note = Note::new(
+            receiver_key.public_address(),
+            10,
+            "",
+            NATIVE_ASSET,
+            spender_key.public_address(),
+        );
+        let witness = make_fake_witness(&in_note);
+
+        let mut transaction = ProposedTransaction::new(TransactionVersion::latest());
+        transaction.add_spend(in_note, &witness).unwrap();
+        transaction.add_output(out_note).unwrap();
+        transaction
+    };
+
+    let transaction = make_transaction(10);
+    let size_without_change = transaction.estimated_size(0);
+    let size_with_change = transaction.estimated_size(0) + OUTPUT_DESCRIPTION_SIZE;
+
+    // Not enough to pay for the transaction, so no change is assumed
+    assert_eq!(transaction.estimate_fee(2), size_without_change as u64 * 2);
+
+    // Plenty of surplus, so the fee accounts for a change note
+    let transaction = make_transaction(1_000_000);
+    assert_eq!(transaction.estimated_size(1), size_with_change);
+    assert_eq!(transaction.estimate_fee(2), size_with_change as u64 * 2);
+
+    // The surplus covers the fee, but not the fee of a change note, so all of
+    // it goes to the fee
+    let surplus = size_without_change as u64 * 2 + 1;
+    let transaction = make_transaction(10 + surplus);
+    assert_eq!(transaction.estimate_fee(2), surplus);
+    assert_eq!(transaction.estimated_size(surplus), size_without_change);
+}
+
+#[test]
+fn test_proposed_transaction_serialization() {
+    let spender_key = SaplingKey::generate_key();
+    let receiver_key = SaplingKey::generate_key();
+
+    let asset = Asset::new(spender_key.public_address(), "Testcoin", "")
+        .expect("should be able to create an asset");
+
+    let in_note = Note::new(
+        spender_key.public_address(),
+        42,
+        "",
+        NATIVE_ASSET,
+        spender_key.public_address(),
+    );
+    let out_note = Note::new(
+        receiver_key.public_address(),
+        40,
+        "memo",
+        NATIVE_ASSET,
+        spender_key.public_address(),
+    );
+    let witness = make_fake_witness(&in_note);
+
+    let mut transaction = ProposedTransaction::new(TransactionVersion::latest());
+    transaction.add_spend(in_note, &witness).unwrap();
+    transaction.add_output(out_note).unwrap();
+    transaction
+        .add_mint_with_new_owner(asset, 10, receiver_key.public_address())
+        .unwrap();
+    transaction.add_burn(*asset.id(), 3).unwrap();
+    transaction.set_change_destination(*asset.id(), receiver_key.public_address());
+    transaction.set_expiration(1234);
+
+    let mut serialized = vec![];
+    transaction
+        .write(&mut serialized)
+        .expect("should be able to serialize proposed transaction");
+
+    let mut deserialized = ProposedTransaction::read(&serialized[..])
+        .expect("should be able to deserialize proposed transaction");
+
+    let mut reserialized = vec![];
+    deserialized
+        .write(&mut reserialized)
+        .expect("should be able to serialize proposed transaction");
+    assert_eq!(serialized, reserialized);
+
+    assert_eq!(deserialized.expiration(), 1234);
+    assert_eq!(
+        deserialized.public_key_randomness,
+        transaction.public_key_randomness
+    );
+    assert_eq!(
+        deserialized.estimated_size(1),
+        transaction.estimated_size(1)
+    );
+
+    // The value balances are restored, so change notes are created for both
+    // assets
+    let public_transaction = deserialized
+        .post(&spender_key, None, 1)
+        .expect("should be able to post deserialized transaction");
+    verify_transaction(&public_transaction).expect("should be able to verify transaction");
+    assert_eq!(public_transaction.spends.len(), 1);
+    assert_eq!(public_transaction.outputs.len(), 3);
+    assert_eq!(public_transaction.mints.len(), 1);
+    assert_eq!(public_transaction.burns.len(), 1);
+    assert_eq!(
+        public_transaction.mints[0].transfer_ownership_to,
+        Some(receiver_key.public_address())
+    );
+
+    // Unknown format versions are rejected
+    serialized[0] = 0;
+    assert!(matches!(
+        ProposedTransaction::read(&serialized[..]),
+        Err(e) if matches!(e.kind, IronfishErrorKind::InvalidData)
+    ));
+}
+
+#[test]
+fn test_proposed_transaction_batch_mints_and_burns() {
+    let spender_key = SaplingKey::generate_key();
+    let treasury_key = SaplingKey::generate_key();
+    let owner_key = SaplingKey::generate_key();
+    let native_change_key = SaplingKey::generate_key();
+
+    let creator = spender_key.public_address();
+    let asset_one = Asset::new(creator, "asset one", "").unwrap();
+    let asset_two = Asset::new(creator, "asset two", "").unwrap();
+    let asset_three = Asset::new(creator, "asset three", "").unwrap();
+
+    let in_note = Note::new(creator, 42, "", NATIVE_ASSET, creator);
+    let witness = make_fake_witness(&in_note);
+
+    let mut transaction = ProposedTransaction::new(TransactionVersion::latest());
+    transaction.add_spend(in_note, &witness).unwrap();
+
+    let mut transfer = MintBuilder::new(asset_two, 20);
+    transfer.transfer_ownership_to(owner_key.public_address());
+    transaction
+        .add_mints(vec![MintBuilder::new(asset_one, 10), transfer])
+        .unwrap();
+    transaction
+        .add_burns(vec![
+            BurnBuilder::new(*asset_one.id(), 4),
+            BurnBuilder::new(*asset_two.id(), 5),
+        ])
+        .unwrap();
+
+    // Nothing is added if one of the burns is not covered
+    let error = transaction
+        .add_burns(vec![
+            BurnBuilder::new(*asset_one.id(), 1),
+            BurnBuilder::new(*asset_three.id(), i64::MAX as u64),
+            BurnBuilder::new(*asset_three.id(), i64::MAX as u64),
+        ])
+        .unwrap_err();
+    assert!(matches!(error.kind, IronfishErrorKind::InvalidBalance));
+    assert_eq!(
+        error.payload,
+        Some(IronfishErrorPayload::InvalidBalance(BalanceErrorDetails {
+            asset_id: *asset_three.id(),
+            spends_and_mints: 0,
+            outputs_and_burns: 2 * i64::MAX as u64,
+            intended_fee: 0,
+        }))
+    );
+
+    transaction.set_change_destination(*asset_one.id(), treasury_key.public_address());
+    transaction.set_change_destination(NATIVE_ASSET, native_change_key.public_address());
+
+    let public_transaction = transaction
+        .post(&spender_key, None, 2)
+        .expect("should be able to post transaction");
+    verify_transaction(&public_transaction).expect("should be able to verify transaction");
+    assert_eq!(public_transaction.mints.len(), 2);
+    assert_eq!(public_transaction.burns.len(), 2);
+    assert_eq!(public_transaction.outputs.len(), 3);
+
+    let change_for = |key: &SaplingKey| -> Vec<Note> {
+        public_transaction
+            .iter_outputs()
+            .filter_map(|output| {
+                output
+                    .merkle_note()
+                    .decrypt_note_for_owner(key.view_key())
+                    .map(|(_, note)| note)
+                    .ok()
+            })
+            .collect()
+    };
+
+    let treasury_notes = change_for(&treasury_key);
+    assert_eq!(treasury_notes.len(), 1);
+    assert_eq!(treasury_notes[0].asset_id(), asset_one.id());
+    assert_eq!(treasury_notes[0].value(), 6);
+
+    let native_notes = change_for(&native_change_key);
+    assert_eq!(native_notes.len(), 1);
+    assert_eq!(native_notes[0].asset_id(), &NATIVE_ASSET);
+    assert_eq!(native_notes[0].value(), 40);
+
+    // Change of assets without a destination goes to the spender
+    let spender_notes = change_for(&spender_key);
+    assert_eq!(spender_notes.len(), 1);
+    assert_eq!(spender_notes[0].asset_id(), asset_two.id());
+    assert_eq!(spender_notes[0].value(), 15);
+
+    // The missing asset is named when the transaction does not balance
+    let mut transaction = ProposedTransaction::new(TransactionVersion::latest());
+    transaction
+        .add_burns(vec![BurnBuilder::new(*asset_one.id(), 1)])
+        .unwrap();
+    let error = transaction.post(&spender_key, None, 0).unwrap_err();
+    assert!(matches!(error.kind, IronfishErrorKind::InvalidBalance));
+    assert_eq!(
+        error.payload,
+        Some(IronfishErrorPayload::InvalidBalance(BalanceErrorDetails {
+            asset_id: *asset_one.id(),
+            spends_and_mints: 0,
+            outputs_and_burns: 1,
+            intended_fee: 0,
+        }))
+    );
+}
+
+#[test]
+fn test_diversified_spend() {
+    let spender_key = SaplingKey::generate_key();
+    let receiver_key = SaplingKey::generate_key();
+
+    let (index, address) = spender_key.view_key().next_diversified_address(2).unwrap();
+    let in_note = Note::new(
+        address.owner(),
+        42,
+        "",
+        NATIVE_ASSET,
+        receiver_key.public_address(),
+    );
+    let out_note = Note::new(
+        receiver_key.public_address(),
+        40,
+        "",
+        NATIVE_ASSET,
+        spender_key.public_address(),
+    );
+    let witness = make_fake_witness(&in_note);
+
+    let mut transaction = ProposedTransaction::new(TransactionVersion::latest());
+    transaction
+        .add_diversified_spend(in_note.clone(), &witness, index)
+        .unwrap();
+    transaction.add_output(out_note.clone()).unwrap();
+
+    let public_transaction = transaction
+        .post(&spender_key, None, 1)
+        .expect("should be able to spend a diversified note");
+    verify_transaction(&public_transaction).expect("should be able to verify transaction");
+
+    let diversified_view_key = spender_key.view_key().diversified(index);
+    assert_eq!(
+        public_transaction.spends()[0].nullifier(),
+        in_note.nullifier(&diversified_view_key, position(&witness))
+    );
+
+    // The note cannot be spent without the diversifier index
+    let mut transaction = ProposedTransaction::new(TransactionVersion::latest());
+    transaction.add_spend(in_note, &witness).unwrap();
+    transaction.add_output(out_note).unwrap();
+    assert!(transaction.post(&spender_key, None, 1).is_err());
+}
+
+#[test]
+fn test_diversified_output() {
+    let spender_key = SaplingKey::generate_key();
+    let receiver_key = SaplingKey::generate_key();
+
+    let (index, address) = receiver_key.view_key().next_diversified_address(1).unwrap();
+    let in_note = Note::new(
+        spender_key.public_address(),
+        42,
+        "",
+        NATIVE_ASSET,
+        spender_key.public_address(),
+    );
+    let out_note = Note::new(
+        address.owner(),
+        40,
+        "",
+        NATIVE_ASSET,
+        spender_key.public_address(),
+    );
+    let witness = make_fake_witness(&in_note);
+
+    // Earlier versions only encrypt notes to regular addresses
+    let mut transaction = ProposedTransaction::new(TransactionVersion::V2);
+    assert!(matches!(
+        transaction.add_diversified_output(out_note.clone(), address),
+        Err(e) if matches!(e.kind, IronfishErrorKind::InvalidTransactionVersion)
+    ));
+
+    let mut transaction = ProposedTransaction::new(TransactionVersion::V3);
+    assert!(matches!(
+        transaction.add_diversified_output(out_note.clone(), receiver_key.public_address().into()),
+        Err(e) if matches!(e.kind, IronfishErrorKind::InvalidPublicAddress)
+    ));
+
+    transaction.add_spend(in_note, &witness).unwrap();
+    transaction
+        .add_diversified_output(out_note, address)
+        .unwrap();
+    let estimated_size = transaction.estimated_size(1);
+
+    let public_transaction = transaction
+        .post(&spender_key, None, 1)
+        .expect("should be able to post transaction");
+    verify_transaction(&public_transaction).expect("should be able to verify transaction");
+
+    let mut serialized = vec![];
+    public_transaction.write(&mut serialized).unwrap();
+    assert_eq!(serialized.len(), estimated_size);
+    let public_transaction = Transaction::read(&serialized[..]).unwrap();
+
+    let (received_index, received_note) = public_transaction.outputs[0]
+        .merkle_note()
+        .decrypt_note_for_owner(receiver_key.view_key())
+        .expect("should be able to decrypt diversified note");
+    assert_eq!(received_index, index);
+    assert_eq!(received_note.owner(), address.owner());
+    assert_eq!(received_note.value(), 40);
+
+    // Change goes to the regular address of the spender
+    let (change_index, change_note) = public_transaction.outputs[1]
+        .merkle_note()
+        .decrypt_note_for_owner(spender_key.view_key())
+        .expect("should be able to decrypt change note");
+    assert_eq!(change_index, 0);
+    assert_eq!(change_note.value(), 1);
+}
+
 #[test]
 fn test_miners_fee() {
     let spender_key = SaplingKey::generate_key();
@@ -564,13 +1212,19 @@ fn test_batch_verify_wrong_params() {
         &SAPLING.mint_verifying_key,
     )
     .expect_err("Should not verify if output verifying key is wrong");
-    internal_batch_verify_transactions(
+    let mint_error = internal_batch_verify_transactions(
         [&transaction1, &transaction2],
         &SAPLING.spend_verifying_key,
         &SAPLING.output_verifying_key,
         &wrong_mint_vk,
     )
     .expect_err("Should not verify if mint verifying key is wrong");
+    // The legacy entry point keeps reporting mint proof failures as output
+    // proof failures
+    assert!(matches!(
+        mint_error.kind,
+        IronfishErrorKind::InvalidOutputProof
+    ));
 }
 
 #[test]
@@ -650,6 +1304,83 @@ fn test_batch_verify() {
     ));
 }
 
+#[test]
+fn test_batch_verify_with_failures() {
+    let rng = &mut thread_rng();
+
+    let wrong_output_params =
+        bellperson::groth16::generate_random_parameters::<blstrs::Bls12, _, _>(
+            Output {
+                value_commitment: None,
+                payment_address: None,
+                commitment_randomness: None,
+                esk: None,
+                asset_id: [0; ASSET_ID_LENGTH],
+                ar: None,
+                proof_generation_key: None,
+            },
+            rng,
+        )
+        .unwrap();
+    let wrong_output_vk = bellperson::groth16::prepare_verifying_key(&wrong_output_params.vk);
+
+    let key = SaplingKey::generate_key();
+    let other_key = SaplingKey::generate_key();
+
+    let in_note = Note::new(
+        key.public_address(),
+        42,
+        "",
+        NATIVE_ASSET,
+        key.public_address(),
+    );
+    let out_note = Note::new(
+        other_key.public_address(),
+        40,
+        "",
+        NATIVE_ASSET,
+        key.public_address(),
+    );
+    let witness = make_fake_witness(&in_note);
+
+    let mut proposed_transaction1 = ProposedTransaction::new(TransactionVersion::latest());
+    proposed_transaction1.add_spend(in_note, &witness).unwrap();
+    proposed_transaction1.add_output(out_note).unwrap();
+    let transaction1 = proposed_transaction1.post(&key, None, 1).unwrap();
+
+    // A mint of zero value has no outputs, so its proofs are unaffected by the
+    // output verifying key
+    let asset = Asset::new(other_key.public_address(), "Othercoin", "").unwrap();
+    let mut proposed_transaction2 = ProposedTransaction::new(TransactionVersion::latest());
+    proposed_transaction2.add_mint(asset, 0).unwrap();
+    let transaction2 = proposed_transaction2.post(&other_key, None, 0).unwrap();
+    assert!(transaction2.outputs.is_empty());
+
+    // Invalidate the spend signature of a copy of the first transaction
+    let mut transaction3 = transaction1.clone();
+    transaction3.randomized_public_key = transaction2.randomized_public_key.clone();
+
+    assert!(batch_verify_transactions_with_failures([&transaction1, &transaction2]).is_empty());
+
+    assert_eq!(
+        batch_verify_transactions_with_failures([&transaction1, &transaction3, &transaction2]),
+        vec![(1, IronfishErrorKind::InvalidSpendSignature)]
+    );
+
+    assert_eq!(
+        internal_batch_verify_transactions_with_failures(
+            [&transaction2, &transaction1, &transaction2, &transaction3],
+            &SAPLING.spend_verifying_key,
+            &wrong_output_vk,
+            &SAPLING.mint_verifying_key,
+        ),
+        vec![
+            (1, IronfishErrorKind::InvalidOutputProof),
+            (3, IronfishErrorKind::InvalidSpendSignature)
+        ]
+    );
+}
+
 #[test]
 fn test_sign_simple() {
     let spender_key = SaplingKey::generate_key();
@@ -28,7 +28,8 @@ use crate::{
 
 use super::{
     burns::BurnDescription, mints::UnsignedMintDescription, spends::UnsignedSpendDescription,
-    TransactionVersion, SIGNATURE_HASH_PERSONALIZATION, TRANSACTION_SIGNATURE_VERSION,
+    TransactionSummary, TransactionVersion, SIGNATURE_HASH_PERSONALIZATION,
+    TRANSACTION_SIGNATURE_VERSION,
 };
 
 #[derive(Clone)]
@@ -95,7 +96,7 @@ impl UnsignedTransaction {
 
         let mut outputs = Vec::with_capacity(num_outputs as usize);
         for _ in 0..num_outputs {
-            outputs.push(OutputDescription::read(&mut reader)?);
+            outputs.push(OutputDescription::read(&mut reader, version)?);
         }
 
         let mut mints = Vec::with_capacity(num_mints as usize);
@@ -314,6 +315,21 @@ impl UnsignedTransaction {
         self.public_key_randomness
     }
 
+    /// Decode the public contents of this transaction into a human readable
+    /// [`TransactionSummary`].
+    pub fn summary(&self) -> TransactionSummary {
+        TransactionSummary::new(
+            self.version,
+            self.fee,
+            self.expiration,
+            &self.randomized_public_key,
+            self.spends.iter().map(|spend| &spend.description),
+            &self.outputs,
+            self.mints.iter().map(|mint| mint.description()),
+            &self.burns,
+        )
+    }
+
     pub fn outputs(&self) -> &Vec<OutputDescription> {
         &self.outputs
     }
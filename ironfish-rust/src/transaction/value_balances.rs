@@ -5,11 +5,17 @@ use std::collections::{hash_map, HashMap};
 
 use crate::{
     assets::asset_identifier::{AssetIdentifier, NATIVE_ASSET},
-    errors::{IronfishError, IronfishErrorKind},
+    errors::{BalanceErrorDetails, IronfishError, IronfishErrorKind, IronfishErrorPayload},
 };
 
+#[derive(Clone)]
 pub struct ValueBalances {
     values: HashMap<AssetIdentifier, i64>,
+
+    /// Total value of the spends and mints, and of the outputs and burns, of
+    /// each asset. Only used to report errors, so they saturate instead of
+    /// overflowing.
+    totals: HashMap<AssetIdentifier, (u64, u64)>,
 }
 
 impl ValueBalances {
@@ -18,16 +24,26 @@ impl ValueBalances {
 
         hash_map.insert(NATIVE_ASSET, 0);
 
-        ValueBalances { values: hash_map }
+        ValueBalances {
+            values: hash_map,
+            totals: HashMap::default(),
+        }
     }
 
     pub fn add(&mut self, asset_id: &AssetIdentifier, value: i64) -> Result<(), IronfishError> {
-        let current_value = self.values.entry(*asset_id).or_insert(0);
-        let new_value = current_value
+        let total_value = non_negative(value)?;
+        let new_value = self
+            .values
+            .get(asset_id)
+            .copied()
+            .unwrap_or(0)
             .checked_add(value)
-            .ok_or_else(|| IronfishError::new(IronfishErrorKind::InvalidBalance))?;
+            .ok_or_else(|| self.overflow_error(asset_id, total_value, 0))?;
+
+        self.values.insert(*asset_id, new_value);
 
-        *current_value = new_value;
+        let totals = self.totals.entry(*asset_id).or_insert((0, 0));
+        totals.0 = totals.0.saturating_add(total_value);
 
         Ok(())
     }
@@ -37,12 +53,19 @@ impl ValueBalances {
         asset_id: &AssetIdentifier,
         value: i64,
     ) -> Result<(), IronfishError> {
-        let current_value = self.values.entry(*asset_id).or_insert(0);
-        let new_value = current_value
+        let total_value = non_negative(value)?;
+        let new_value = self
+            .values
+            .get(asset_id)
+            .copied()
+            .unwrap_or(0)
             .checked_sub(value)
-            .ok_or_else(|| IronfishError::new(IronfishErrorKind::InvalidBalance))?;
+            .ok_or_else(|| self.overflow_error(asset_id, 0, total_value))?;
+
+        self.values.insert(*asset_id, new_value);
 
-        *current_value = new_value;
+        let totals = self.totals.entry(*asset_id).or_insert((0, 0));
+        totals.1 = totals.1.saturating_add(total_value);
 
         Ok(())
     }
@@ -54,12 +77,100 @@ impl ValueBalances {
     pub fn fee(&self) -> &i64 {
         self.values.get(&NATIVE_ASSET).unwrap()
     }
+
+    /// Value left for each asset once `intended_transaction_fee` is paid,
+    /// which must be returned as change. Fails if the spends and mints of an
+    /// asset do not cover its outputs and burns.
+    pub fn change(
+        &self,
+        intended_transaction_fee: i64,
+    ) -> Result<Vec<(AssetIdentifier, u64)>, IronfishError> {
+        let mut change = vec![];
+
+        for (asset_id, value) in self.values.iter() {
+            let needed = match asset_id == &NATIVE_ASSET {
+                true => intended_transaction_fee,
+                false => 0,
+            };
+
+            if *value < needed {
+                return Err(self.balance_error(asset_id, intended_transaction_fee));
+            }
+
+            // Both values are in range and the difference is positive
+            change.push((*asset_id, (*value as i128 - needed as i128) as u64));
+        }
+
+        Ok(change)
+    }
+
+    /// Check that the value of every asset balances exactly, with the native
+    /// asset paying `intended_transaction_fee`.
+    pub fn check_balanced(&self, intended_transaction_fee: i64) -> Result<(), IronfishError> {
+        for (asset_id, value) in self.values.iter() {
+            let expected = match asset_id == &NATIVE_ASSET {
+                true => intended_transaction_fee,
+                false => 0,
+            };
+
+            if *value != expected {
+                return Err(self.balance_error(asset_id, intended_transaction_fee));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn balance_error(&self, asset_id: &AssetIdentifier, intended_fee: i64) -> IronfishError {
+        let (spends_and_mints, outputs_and_burns) =
+            self.totals.get(asset_id).copied().unwrap_or_default();
+
+        IronfishError::new_with_payload(
+            IronfishErrorKind::InvalidBalance,
+            IronfishErrorPayload::InvalidBalance(BalanceErrorDetails {
+                asset_id: *asset_id,
+                spends_and_mints,
+                outputs_and_burns,
+                intended_fee,
+            }),
+        )
+    }
+
+    /// Error raised when adding the given values to the totals of `asset_id`
+    /// overflows its balance. The totals in the payload include those values,
+    /// and the fee is not known yet.
+    fn overflow_error(
+        &self,
+        asset_id: &AssetIdentifier,
+        spends_and_mints: u64,
+        outputs_and_burns: u64,
+    ) -> IronfishError {
+        let (total_spends_and_mints, total_outputs_and_burns) =
+            self.totals.get(asset_id).copied().unwrap_or_default();
+
+        IronfishError::new_with_payload(
+            IronfishErrorKind::InvalidBalance,
+            IronfishErrorPayload::InvalidBalance(BalanceErrorDetails {
+                asset_id: *asset_id,
+                spends_and_mints: total_spends_and_mints.saturating_add(spends_and_mints),
+                outputs_and_burns: total_outputs_and_burns.saturating_add(outputs_and_burns),
+                intended_fee: 0,
+            }),
+        )
+    }
+}
+
+/// Values of spends, outputs, mints and burns cannot be negative
+fn non_negative(value: i64) -> Result<u64, IronfishError> {
+    u64::try_from(value).map_err(|_| IronfishError::new(IronfishErrorKind::IllegalValue))
 }
 
 #[cfg(test)]
 mod test {
     use crate::{
         assets::{asset::Asset, asset_identifier::NATIVE_ASSET},
+        errors::{BalanceErrorDetails, IronfishErrorKind, IronfishErrorPayload},
+        serializing::bytes_to_hex,
         SaplingKey,
     };
 
@@ -117,7 +228,17 @@ mod test {
         vb.add(asset.id(), i64::MAX - 1).unwrap();
 
         // Second value add - overflows
-        assert!(vb.add(asset.id(), 100).is_err());
+        let error = vb.add(asset.id(), 100).unwrap_err();
+        assert_eq!(
+            error.payload,
+            Some(IronfishErrorPayload::InvalidBalance(BalanceErrorDetails {
+                asset_id: *asset.id(),
+                spends_and_mints: i64::MAX as u64 + 99,
+                outputs_and_burns: 0,
+                intended_fee: 0,
+            }))
+        );
+        assert_eq!(*vb.values.get(asset.id()).unwrap(), i64::MAX - 1);
     }
 
     #[test]
@@ -131,6 +252,88 @@ mod test {
         vb.subtract(asset.id(), i64::MAX - 1).unwrap();
 
         // Second value sub - overflows
-        assert!(vb.subtract(asset.id(), 100).is_err());
+        let error = vb.subtract(asset.id(), 100).unwrap_err();
+        assert_eq!(
+            error.payload,
+            Some(IronfishErrorPayload::InvalidBalance(BalanceErrorDetails {
+                asset_id: *asset.id(),
+                spends_and_mints: 0,
+                outputs_and_burns: i64::MAX as u64 + 99,
+                intended_fee: 0,
+            }))
+        );
+        assert_eq!(*vb.values.get(asset.id()).unwrap(), -(i64::MAX - 1));
+    }
+
+    #[test]
+    fn test_value_balances_rejects_negative_values() {
+        let mut vb = ValueBalances::new();
+
+        for result in [vb.add(&NATIVE_ASSET, -1), vb.subtract(&NATIVE_ASSET, -1)] {
+            assert_eq!(result.unwrap_err().kind, IronfishErrorKind::IllegalValue);
+        }
+        assert_eq!(*vb.fee(), 0);
+        assert!(vb.totals.is_empty());
+    }
+
+    #[test]
+    fn test_value_balances_change() {
+        let mut vb = ValueBalances::new();
+
+        let public_address = SaplingKey::generate_key().public_address();
+        let asset = Asset::new(public_address, "assetone", "").unwrap();
+
+        vb.add(&NATIVE_ASSET, 5).unwrap();
+        vb.add(asset.id(), 3).unwrap();
+
+        let mut change = vb.change(2).unwrap();
+        change.sort_by_key(|(asset_id, _)| *asset_id.as_bytes());
+        let mut expected = vec![(NATIVE_ASSET, 3), (*asset.id(), 3)];
+        expected.sort_by_key(|(asset_id, _)| *asset_id.as_bytes());
+        assert_eq!(change, expected);
+
+        // Not enough native asset to pay the fee
+        assert!(vb.change(6).is_err());
+
+        vb.subtract(asset.id(), 4).unwrap();
+        let error = vb.change(2).unwrap_err();
+        assert_eq!(error.kind, IronfishErrorKind::InvalidBalance);
+        assert_eq!(
+            error.payload,
+            Some(IronfishErrorPayload::InvalidBalance(BalanceErrorDetails {
+                asset_id: *asset.id(),
+                spends_and_mints: 3,
+                outputs_and_burns: 4,
+                intended_fee: 2,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_value_balances_check_balanced() {
+        let mut vb = ValueBalances::new();
+
+        let public_address = SaplingKey::generate_key().public_address();
+        let asset = Asset::new(public_address, "assetone", "").unwrap();
+
+        vb.add(&NATIVE_ASSET, 5).unwrap();
+        vb.add(asset.id(), 3).unwrap();
+        vb.subtract(asset.id(), 3).unwrap();
+        vb.check_balanced(5).unwrap();
+
+        let error = vb.check_balanced(4).unwrap_err();
+        assert_eq!(
+            error.payload,
+            Some(IronfishErrorPayload::InvalidBalance(BalanceErrorDetails {
+                asset_id: NATIVE_ASSET,
+                spends_and_mints: 5,
+                outputs_and_burns: 0,
+                intended_fee: 4,
+            }))
+        );
+        assert!(error.to_string().starts_with(&format!(
+            "InvalidBalance: asset {}, spends and mints 5, outputs and burns 0, intended fee 4",
+            bytes_to_hex(NATIVE_ASSET.as_bytes())
+        )));
     }
 }
@@ -17,6 +17,9 @@ pub enum TransactionVersion {
     /// Adds the `transfer_ownership_to` field of
     /// [`MintDescription`](crate::transaction::mints::MintDescription).
     V2,
+    /// Encrypts notes to the diversified transmission key of their owner, see
+    /// [`MerkleNote`](crate::MerkleNote).
+    V3,
 }
 
 impl TransactionVersion {
@@ -24,6 +27,7 @@ impl TransactionVersion {
         match self {
             Self::V1 => 1,
             Self::V2 => 2,
+            Self::V3 => 3,
         }
     }
 
@@ -31,6 +35,7 @@ impl TransactionVersion {
         match value {
             1 => Some(Self::V1),
             2 => Some(Self::V2),
+            3 => Some(Self::V3),
             _ => None,
         }
     }
@@ -53,6 +58,12 @@ impl TransactionVersion {
     pub fn has_mint_transfer_ownership_to(self) -> bool {
         self >= Self::V2
     }
+
+    /// Returns `true` if the notes of this [`TransactionVersion`] can be sent
+    /// to [`DiversifiedAddress`](crate::keys::DiversifiedAddress)es.
+    pub fn has_diversified_notes(self) -> bool {
+        self >= Self::V3
+    }
 }
 
 impl TryFrom<u8> for TransactionVersion {
@@ -93,12 +104,16 @@ mod tests {
 
         assert!(V2 <= V2);
         assert!(V2 >= V2);
+
+        assert!(V2 < V3);
+        assert!(V3 > V1);
     }
 
     #[test]
     fn test_as_u8() {
         assert_eq!(V1.as_u8(), 1);
         assert_eq!(V2.as_u8(), 2);
+        assert_eq!(V3.as_u8(), 3);
     }
 
     #[test]
@@ -106,7 +121,8 @@ mod tests {
         assert_eq!(TransactionVersion::from_u8(0), None);
         assert_eq!(TransactionVersion::from_u8(1), Some(V1));
         assert_eq!(TransactionVersion::from_u8(2), Some(V2));
-        for i in 3..=255 {
+        assert_eq!(TransactionVersion::from_u8(3), Some(V3));
+        for i in 4..=255 {
             assert_eq!(TransactionVersion::from_u8(i), None);
         }
     }
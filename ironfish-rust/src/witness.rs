@@ -33,8 +33,9 @@ pub trait WitnessTrait {
     fn tree_size(&self) -> u32;
 }
 
-/// A Rust implementation of a WitnessTrait, used for testing Witness-related
-/// code within Rust.
+/// A Rust implementation of a WitnessTrait, created by
+/// [`NoteCommitmentTree`](crate::NoteCommitmentTree) or used for testing
+/// Witness-related code within Rust.
 pub struct Witness {
     pub tree_size: usize,
     pub root_hash: Scalar,